@@ -0,0 +1,111 @@
+use crate::ClipboardEntry;
+use chrono::{DateTime, Datelike, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use log::info;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, VecDeque},
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Moves old entries out of `history.json` into compressed monthly archives
+/// so the live history stays small.
+pub struct Archiver;
+
+impl Archiver {
+    /// Moves unpinned entries older than `days` into
+    /// `archive_<year>_<month>.json.gz` files in `archive_dir`, appending to
+    /// any archive that already exists. `load` returns an entry's full
+    /// content for writing. Returns the number of entries moved.
+    pub fn archive_old(
+        entries: &mut VecDeque<ClipboardEntry>,
+        days: u32,
+        archive_dir: &Path,
+        load: impl Fn(&ClipboardEntry) -> ClipboardEntry,
+    ) -> io::Result<usize> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let cutoff = now.saturating_sub(u64::from(days) * SECONDS_PER_DAY);
+
+        let mut by_month: BTreeMap<(i32, u32), Vec<ClipboardEntry>> = BTreeMap::new();
+        for entry in entries.iter().filter(|e| !e.pinned && e.timestamp < cutoff) {
+            by_month
+                .entry(year_month(entry.timestamp))
+                .or_default()
+                .push(load(entry));
+        }
+
+        if by_month.is_empty() {
+            return Ok(0);
+        }
+
+        fs::create_dir_all(archive_dir)?;
+        let mut moved = 0;
+        for ((year, month), new_entries) in by_month {
+            let path = archive_dir.join(format!("archive_{}_{:02}.json.gz", year, month));
+            let mut archived = if path.exists() {
+                read_archive(&path)?
+            } else {
+                Vec::new()
+            };
+            moved += new_entries.len();
+            archived.extend(new_entries);
+            archived.sort_by_key(|e| Reverse(e.timestamp));
+            write_archive(&path, &archived)?;
+            info!("Archived entries to {}", path.display());
+        }
+
+        // Only drop entries from the live history once they're safely on disk
+        entries.retain(|e| e.pinned || e.timestamp >= cutoff);
+        Ok(moved)
+    }
+
+    /// Loads every archived entry in `archive_dir`, newest archive first.
+    pub fn load_all(archive_dir: &Path) -> io::Result<Vec<ClipboardEntry>> {
+        if !archive_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(archive_dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("archive_") && name.ends_with(".json.gz"))
+            })
+            .collect();
+        paths.sort();
+        paths.reverse();
+
+        let mut entries = Vec::new();
+        for path in paths {
+            entries.extend(read_archive(&path)?);
+        }
+        Ok(entries)
+    }
+}
+
+fn year_month(timestamp: u64) -> (i32, u32) {
+    let date = DateTime::<Utc>::from_timestamp(timestamp as i64, 0).unwrap_or_default();
+    (date.year(), date.month())
+}
+
+fn read_archive(path: &Path) -> io::Result<Vec<ClipboardEntry>> {
+    let mut json = String::new();
+    GzDecoder::new(File::open(path)?).read_to_string(&mut json)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+fn write_archive(path: &Path, entries: &[ClipboardEntry]) -> io::Result<()> {
+    let mut encoder = GzEncoder::new(File::create(path)?, Compression::default());
+    encoder.write_all(serde_json::to_string(entries)?.as_bytes())?;
+    encoder.finish()?;
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use crate::config::Config;
+use std::{
+    io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+pub const MIN_PIN_LENGTH: usize = 4;
+pub const MAX_PIN_LENGTH: usize = 6;
+const MAX_FAILED_ATTEMPTS: u32 = 3;
+const LOCKOUT_DURATION: Duration = Duration::from_secs(30);
+
+pub fn is_valid_pin(pin: &str) -> bool {
+    (MIN_PIN_LENGTH..=MAX_PIN_LENGTH).contains(&pin.len())
+        && pin.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Tracks PIN entry for the lock screen, including the lockout after too
+/// many failed attempts.
+#[derive(Debug, Default)]
+pub struct PinLock {
+    entered: String,
+    /// An attempt is out being checked against the hash.
+    checking: bool,
+    failed_attempts: u32,
+    locked_until: Option<Instant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinOutcome {
+    /// More digits are needed before the PIN can be checked.
+    Pending,
+    /// The PIN entered, to check with [`verify`] away from the UI, as
+    /// bcrypt is slow on purpose. The result goes to
+    /// [`PinLock::finish_check`].
+    Check(String),
+    Unlocked,
+    Rejected,
+    LockedOut,
+}
+
+impl PinLock {
+    pub fn entered_len(&self) -> usize {
+        self.entered.len()
+    }
+
+    /// Seconds left before another attempt is allowed, if locked out.
+    pub fn lockout_remaining(&self) -> Option<u64> {
+        self.locked_until
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+            .map(|remaining| remaining.as_secs() + 1)
+    }
+
+    /// Forgets the digits entered, and the result of any attempt still
+    /// being checked.
+    pub fn clear(&mut self) {
+        self.entered.clear();
+        self.checking = false;
+    }
+
+    pub fn backspace(&mut self) {
+        self.entered.pop();
+    }
+
+    /// Adds a digit, checking the PIN automatically once the maximum length
+    /// is reached.
+    pub fn push_digit(&mut self, digit: char) -> PinOutcome {
+        if self.lockout_remaining().is_some() {
+            return PinOutcome::LockedOut;
+        }
+        if self.checking || !digit.is_ascii_digit() || self.entered.len() >= MAX_PIN_LENGTH {
+            return PinOutcome::Pending;
+        }
+
+        self.entered.push(digit);
+        if self.entered.len() == MAX_PIN_LENGTH {
+            self.submit()
+        } else {
+            PinOutcome::Pending
+        }
+    }
+
+    /// Hands over the digits entered so far to be checked, unless one
+    /// attempt is being checked already.
+    pub fn submit(&mut self) -> PinOutcome {
+        if self.lockout_remaining().is_some() {
+            return PinOutcome::LockedOut;
+        }
+        if self.checking || self.entered.len() < MIN_PIN_LENGTH {
+            return PinOutcome::Pending;
+        }
+
+        self.checking = true;
+        PinOutcome::Check(std::mem::take(&mut self.entered))
+    }
+
+    /// Takes the result of checking the attempt `submit` handed over.
+    /// Ignored if there's no attempt out.
+    pub fn finish_check(&mut self, matched: bool) -> PinOutcome {
+        if !std::mem::take(&mut self.checking) {
+            return PinOutcome::Pending;
+        }
+        if matched {
+            self.failed_attempts = 0;
+            return PinOutcome::Unlocked;
+        }
+
+        self.failed_attempts += 1;
+        if self.failed_attempts >= MAX_FAILED_ATTEMPTS {
+            self.failed_attempts = 0;
+            self.locked_until = Some(Instant::now() + LOCKOUT_DURATION);
+            PinOutcome::LockedOut
+        } else {
+            PinOutcome::Rejected
+        }
+    }
+}
+
+/// Whether `pin` is the one `hash` was made from.
+pub fn verify(pin: &str, hash: &str) -> bool {
+    bcrypt::verify(pin, hash).unwrap_or(false)
+}
+
+/// Prompts for a new PIN without echoing it, then stores its bcrypt hash in
+/// the config and turns on `require_pin`.
+pub fn set_pin_interactive(config_path: &Path) -> io::Result<()> {
+    let pin = rpassword::prompt_password(format!(
+        "New PIN ({}-{} digits): ",
+        MIN_PIN_LENGTH, MAX_PIN_LENGTH
+    ))?;
+    if !is_valid_pin(&pin) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("PIN must be {}-{} digits", MIN_PIN_LENGTH, MAX_PIN_LENGTH),
+        ));
+    }
+
+    let confirmation = rpassword::prompt_password("Confirm PIN: ")?;
+    if confirmation != pin {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "PINs don't match",
+        ));
+    }
+
+    // Saving the defaults over a config that doesn't parse would lose it
+    let mut config = Config::try_load(config_path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "{} can't be read ({}); fix it first",
+                config_path.display(),
+                e
+            ),
+        )
+    })?;
+    let hash = bcrypt::hash(&pin, bcrypt::DEFAULT_COST).map_err(io::Error::other)?;
+    config.require_pin = true;
+    config.pin_hash = Some(hash);
+    config.save(config_path)
+}
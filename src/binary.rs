@@ -0,0 +1,60 @@
+//! Copies without any text, like images, kept as the raw pasteboard data and
+//! shown as a hex dump of their first bytes.
+
+use crate::macos::Flavors;
+
+/// Bytes shown in an entry's hex dump.
+const PREVIEW_BYTES: usize = 32;
+
+/// Formats that describe a copy best, tried in order before falling back to
+/// the largest flavor.
+const PREFERRED_FLAVORS: &[&str] = &["public.png", "public.tiff", "public.jpeg", "com.adobe.pdf"];
+
+/// The flavor an entry is previewed and labelled by.
+pub fn main_flavor(flavors: &Flavors) -> Option<(&str, &[u8])> {
+    PREFERRED_FLAVORS
+        .iter()
+        .find_map(|&name| flavors.get_key_value(name))
+        .or_else(|| {
+            flavors
+                .iter()
+                .filter(|(name, _)| !name.starts_with("dyn."))
+                .max_by_key(|(_, data)| data.len())
+        })
+        .map(|(name, data)| (name.as_str(), data.as_slice()))
+}
+
+/// The MIME type for a pasteboard type identifier, or the identifier itself
+/// if there's no common one.
+pub fn mime_type(flavor: &str) -> String {
+    match flavor {
+        "public.png" => "image/png",
+        "public.tiff" => "image/tiff",
+        "public.jpeg" => "image/jpeg",
+        "com.compuserve.gif" => "image/gif",
+        "com.adobe.pdf" => "application/pdf",
+        "public.mpeg-4" => "video/mp4",
+        "public.mp3" => "audio/mpeg",
+        other => return other.to_string(),
+    }
+    .to_string()
+}
+
+/// The first bytes of `data` in hex, e.g. "89 50 4E 47 0D 0A 1A 0A".
+pub fn hex_preview(data: &[u8]) -> String {
+    let mut preview = data
+        .iter()
+        .take(PREVIEW_BYTES)
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if data.len() > PREVIEW_BYTES {
+        preview.push_str(" …");
+    }
+    preview
+}
+
+/// Whether the pasteboard holds text, which the text monitor records instead.
+pub fn has_text(flavors: &Flavors) -> bool {
+    flavors.contains_key("public.utf8-plain-text")
+}
@@ -0,0 +1,123 @@
+use crate::{macos::Flavors, storage, ClipboardEntry};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs, io,
+    path::PathBuf,
+};
+
+/// Characters of an offloaded entry kept in memory for the list and search.
+const PREVIEW_CHARS: usize = 200;
+
+/// The parts of an entry moved to disk in low-memory mode.
+#[derive(Serialize, Deserialize)]
+struct Body {
+    content: String,
+    #[serde(default, with = "storage::flavors_base64")]
+    flavors: Flavors,
+}
+
+/// Keeps the full content of large entries in per-entry files so only a
+/// preview stays in memory.
+pub struct BodyStore {
+    dir: PathBuf,
+}
+
+impl BodyStore {
+    pub fn new(dir: PathBuf) -> Self {
+        BodyStore { dir }
+    }
+
+    /// Writes the entry's content and flavors to disk and leaves a preview
+    /// behind. Entries short enough to be their own preview are left alone.
+    pub fn offload(&self, entry: &mut ClipboardEntry) -> io::Result<()> {
+        if entry.body.is_some()
+            || (entry.content.chars().count() <= PREVIEW_CHARS && entry.flavors.is_empty())
+        {
+            return Ok(());
+        }
+
+        let hash = storage::content_hash(&entry.content);
+        let preview: String = entry.content.chars().take(PREVIEW_CHARS).collect();
+        let body = Body {
+            content: std::mem::replace(&mut entry.content, preview),
+            flavors: std::mem::take(&mut entry.flavors),
+        };
+
+        fs::create_dir_all(&self.dir)?;
+        if let Err(e) = fs::write(self.path(hash), serde_json::to_string(&body)?) {
+            // Put the entry back together rather than lose its content
+            entry.content = body.content;
+            entry.flavors = body.flavors;
+            return Err(e);
+        }
+        entry.body = Some(hash);
+        Ok(())
+    }
+
+    /// Offloads every entry that isn't already on disk.
+    pub fn offload_all(&self, entries: &mut VecDeque<ClipboardEntry>) -> usize {
+        let mut offloaded = 0;
+        for entry in entries.iter_mut().filter(|e| e.body.is_none()) {
+            match self.offload(entry) {
+                Ok(()) if entry.body.is_some() => offloaded += 1,
+                Ok(()) => {}
+                Err(e) => error!("Failed to offload entry: {}", e),
+            }
+        }
+        offloaded
+    }
+
+    /// Returns a copy of the entry with its full content loaded back from
+    /// disk, no longer tied to the body file. Falls back to the preview if the body file can't be read.
+    pub fn load(&self, entry: &ClipboardEntry) -> ClipboardEntry {
+        let Some(hash) = entry.body else {
+            return entry.clone();
+        };
+        let body = fs::read_to_string(self.path(hash))
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_json::from_str::<Body>(&data).map_err(|e| e.to_string()));
+        match body {
+            Ok(body) => ClipboardEntry {
+                content: body.content,
+                flavors: body.flavors,
+                body: None,
+                ..entry.clone()
+            },
+            Err(e) => {
+                error!("Failed to load entry body: {}", e);
+                entry.clone()
+            }
+        }
+    }
+
+    /// Deletes body files no longer referenced by any entry.
+    pub fn remove_orphans(&self, entries: &VecDeque<ClipboardEntry>) -> io::Result<usize> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+
+        let referenced: HashSet<PathBuf> = entries
+            .iter()
+            .filter_map(|e| e.body)
+            .map(|hash| self.path(hash))
+            .collect();
+        let mut removed = 0;
+        for file in fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if !referenced.contains(&path) {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            info!("Removed {} unused entry bodies", removed);
+        }
+        Ok(removed)
+    }
+
+    fn path(&self, hash: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", hash))
+    }
+}
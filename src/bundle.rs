@@ -0,0 +1,173 @@
+//! Packing the config, history, offloaded bodies and sessions into a single
+//! file for moving a whole setup to another machine. A bundle is gzipped
+//! JSON, like the archives, holding a manifest and each file's contents.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const BUNDLE_FORMAT: &str = "mac-clip-bundle";
+/// Bumped when the layout changes in a way older versions can't import.
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// The name the config has in a bundle, whichever profile it came from.
+const CONFIG_FILE: &str = "config.json";
+
+/// Files in the data directory that make up a setup. Window position,
+/// telemetry and archives stay behind.
+const FILES: &[&str] = &[
+    CONFIG_FILE,
+    "history.json",
+    "history.msgpack",
+    "collections.json",
+];
+const DIRS: &[&str] = &["bodies", "sessions"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    pub app_version: String,
+    pub created: u64,
+    pub files: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+    manifest: Manifest,
+    /// Paths relative to the data directory and their base64 contents.
+    files: BTreeMap<String, String>,
+}
+
+/// Where `name` from a bundle lives: the config at `config_path`, since a
+/// profile's is kept outside its directory, and the rest in `data_dir`.
+fn file_path(data_dir: &Path, config_path: &Path, name: &str) -> PathBuf {
+    if name == CONFIG_FILE {
+        config_path.to_path_buf()
+    } else {
+        data_dir.join(name)
+    }
+}
+
+/// Writes the setup in `data_dir`, with the config at `config_path`, to a
+/// bundle at `path`.
+pub fn export(data_dir: &Path, config_path: &Path, path: &Path) -> io::Result<Manifest> {
+    let mut files = BTreeMap::new();
+    for name in FILES {
+        let file = file_path(data_dir, config_path, name);
+        if file.exists() {
+            files.insert(name.to_string(), STANDARD.encode(fs::read(file)?));
+        }
+    }
+    for dir in DIRS {
+        let Ok(read_dir) = fs::read_dir(data_dir.join(dir)) else {
+            continue;
+        };
+        for item in read_dir {
+            let item = item?;
+            if item.file_type()?.is_file() {
+                let name = format!("{}/{}", dir, item.file_name().to_string_lossy());
+                files.insert(name, STANDARD.encode(fs::read(item.path())?));
+            }
+        }
+    }
+
+    let manifest = Manifest {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        files: files.keys().cloned().collect(),
+    };
+    let bundle = Bundle {
+        manifest: manifest.clone(),
+        files,
+    };
+
+    let mut encoder = GzEncoder::new(File::create(path)?, Compression::default());
+    encoder.write_all(serde_json::to_string(&bundle)?.as_bytes())?;
+    encoder.finish()?;
+    info!(
+        "Exported {} files to {}",
+        manifest.files.len(),
+        path.display()
+    );
+    Ok(manifest)
+}
+
+/// Replaces the setup in `data_dir`, and the config at `config_path`, with
+/// the bundle at `path`. The existing history is removed first, so it
+/// doesn't win over the bundle's when they are in different formats.
+pub fn import(data_dir: &Path, config_path: &Path, path: &Path) -> io::Result<Manifest> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut json = String::new();
+    GzDecoder::new(File::open(path)?)
+        .read_to_string(&mut json)
+        .map_err(|e| {
+            invalid(format!(
+                "{} is not a mac-clip bundle: {}",
+                path.display(),
+                e
+            ))
+        })?;
+    let bundle: Bundle = serde_json::from_str(&json)?;
+    if bundle.manifest.format != BUNDLE_FORMAT {
+        return Err(invalid(format!(
+            "{} is not a mac-clip bundle",
+            path.display()
+        )));
+    }
+    if bundle.manifest.version > BUNDLE_VERSION {
+        return Err(invalid(format!(
+            "The bundle is version {}, made by mac-clip {}; this version reads up to {}",
+            bundle.manifest.version, bundle.manifest.app_version, BUNDLE_VERSION
+        )));
+    }
+
+    // Decode everything before touching the data directory
+    let mut files = Vec::with_capacity(bundle.files.len());
+    for (name, contents) in &bundle.files {
+        let relative = Path::new(name);
+        if !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(invalid(format!("The bundle contains a bad path: {}", name)));
+        }
+        let contents = STANDARD
+            .decode(contents)
+            .map_err(|e| invalid(format!("Bad contents for {}: {}", name, e)))?;
+        files.push((file_path(data_dir, config_path, name), contents));
+    }
+
+    for name in ["history.json", "history.msgpack"] {
+        let file = data_dir.join(name);
+        if file.exists() {
+            fs::remove_file(file)?;
+        }
+    }
+    for (file, contents) in files {
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(file, contents)?;
+    }
+    info!(
+        "Imported {} files from {}",
+        bundle.manifest.files.len(),
+        path.display()
+    );
+    Ok(bundle.manifest)
+}
@@ -0,0 +1,182 @@
+use crate::{config::Config, markdown};
+use iced::Color;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// What kind of content an entry holds, worked out once when it's captured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentType {
+    PlainText,
+    Url,
+    Code,
+    Markdown,
+    Email,
+    Phone,
+    Json,
+    CreditCard,
+    FilePath,
+    /// Raw data from a copy without text, e.g. an image.
+    Binary,
+    /// Not classified yet, e.g. entries saved by older versions.
+    #[default]
+    Unknown,
+}
+
+impl ContentType {
+    pub fn label(self) -> &'static str {
+        match self {
+            ContentType::PlainText => "Text",
+            ContentType::Url => "URL",
+            ContentType::Code => "Code",
+            ContentType::Markdown => "MD",
+            ContentType::Email => "Email",
+            ContentType::Phone => "Phone",
+            ContentType::Json => "JSON",
+            ContentType::CreditCard => "Card",
+            ContentType::FilePath => "Path",
+            ContentType::Binary => "Bin",
+            ContentType::Unknown => "?",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            ContentType::Url => Color::from_rgb8(0x3b, 0x82, 0xf6),
+            ContentType::Code => Color::from_rgb8(0x8b, 0x5c, 0xf6),
+            ContentType::Markdown => Color::from_rgb8(0x64, 0x74, 0x8b),
+            ContentType::Email => Color::from_rgb8(0x10, 0xb9, 0x81),
+            ContentType::Phone => Color::from_rgb8(0x14, 0xb8, 0xa6),
+            ContentType::Json => Color::from_rgb8(0xf5, 0x9e, 0x0b),
+            ContentType::CreditCard => Color::from_rgb8(0xef, 0x44, 0x44),
+            ContentType::FilePath => Color::from_rgb8(0xa1, 0x62, 0x07),
+            ContentType::Binary => Color::from_rgb8(0xdb, 0x27, 0x77),
+            ContentType::PlainText | ContentType::Unknown => Color::from_rgb8(0x9c, 0xa3, 0xaf),
+        }
+    }
+}
+
+impl FromStr for ContentType {
+    type Err = String;
+
+    /// Parses the same snake_case names used in the config, e.g. `file_path`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(name.to_lowercase()))
+            .map_err(|_| format!("Unknown content type: {}", name))
+    }
+}
+
+/// Heuristic content classifier. Patterns from the config are tried before
+/// the built-in checks so users can override or extend them.
+pub struct Classifier {
+    custom: Vec<(Regex, ContentType)>,
+    url: Regex,
+    email: Regex,
+    phone: Regex,
+    date: Regex,
+    card: Regex,
+    path: Regex,
+    code_line: Regex,
+}
+
+impl Classifier {
+    pub fn new(config: &Config) -> Self {
+        Classifier {
+            custom: config.content_type_regexes(),
+            url: Regex::new(r"^(?i:(https?|ftp)://|www\.)\S+$").unwrap(),
+            email: Regex::new(r"^[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}$").unwrap(),
+            phone: Regex::new(r"^\+?[\d\s().-]{7,20}$").unwrap(),
+            date: Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap(),
+            card: Regex::new(r"^\d[\d -]{11,22}\d$").unwrap(),
+            path: Regex::new(r"^(~|\.{1,2})?/[^\n]*$").unwrap(),
+            code_line: Regex::new(
+                r"^\s*(fn|pub|def|class|import|from\s+\S+\s+import|function|const|let|var|func|package|#include|public|private|return)\b",
+            )
+            .unwrap(),
+        }
+    }
+
+    pub fn classify(&self, content: &str) -> ContentType {
+        let trimmed = content.trim();
+        if trimmed.is_empty() {
+            return ContentType::Unknown;
+        }
+        if let Some((_, content_type)) = self.custom.iter().find(|(re, _)| re.is_match(trimmed)) {
+            return *content_type;
+        }
+
+        let single_line = !trimmed.contains('\n');
+        if (trimmed.starts_with('{') || trimmed.starts_with('['))
+            && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+        {
+            ContentType::Json
+        } else if single_line && self.url.is_match(trimmed) {
+            ContentType::Url
+        } else if single_line && self.email.is_match(trimmed) {
+            ContentType::Email
+        } else if single_line && self.card.is_match(trimmed) && passes_luhn(trimmed) {
+            ContentType::CreditCard
+        } else if single_line
+            && self.phone.is_match(trimmed)
+            && !self.date.is_match(trimmed)
+            && digit_count(trimmed) >= 7
+        {
+            ContentType::Phone
+        } else if single_line && self.path.is_match(trimmed) {
+            ContentType::FilePath
+        } else if self.looks_like_code(trimmed) {
+            ContentType::Code
+        } else if markdown::looks_like_markdown(trimmed) {
+            ContentType::Markdown
+        } else {
+            ContentType::PlainText
+        }
+    }
+
+    /// Code either starts a line with a keyword or, over several lines,
+    /// mostly ends lines with statement or block punctuation.
+    fn looks_like_code(&self, content: &str) -> bool {
+        let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+        if lines.iter().any(|line| self.code_line.is_match(line)) {
+            return true;
+        }
+        let punctuated = lines
+            .iter()
+            .filter(|line| line.trim_end().ends_with([';', '{', '}', ')']))
+            .count();
+        lines.len() > 1 && punctuated * 2 >= lines.len()
+    }
+}
+
+/// The part of an email address after the `@`.
+pub fn email_domain(address: &str) -> Option<&str> {
+    address
+        .trim()
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .filter(|domain| !domain.is_empty())
+}
+
+fn digit_count(content: &str) -> usize {
+    content.chars().filter(char::is_ascii_digit).count()
+}
+
+/// Checks a card number's Luhn checksum, ignoring spaces and dashes.
+fn passes_luhn(content: &str) -> bool {
+    let digits: Vec<u32> = content.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2 == 1, d * 2) {
+            (true, doubled) if doubled > 9 => doubled - 9,
+            (true, doubled) => doubled,
+            (false, _) => d,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
@@ -0,0 +1,502 @@
+use crate::{
+    archive::Archiver,
+    bodies::BodyStore,
+    bundle,
+    classify::Classifier,
+    clipboard,
+    config::{Config, Severity},
+    daemon, export,
+    filter::{self, CaptureTypeFilter, ExclusionPatternFilter, FilterPipeline, SanitizeFilter},
+    history::{self, HistoryStore},
+    instance::{self, InstanceLock, LockError},
+    macos, monitor, pasta, query, simulate_paste, storage,
+    telemetry::TelemetryRecorder,
+    widgets, ClipboardEntry, CLIPBOARD_CHECK_INTERVAL,
+};
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How often `--daemon-logs` checks the log for new lines.
+const LOG_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Runs a GC pass over the persisted history and reports what was removed.
+pub fn run_gc(profile: Option<&str>) -> io::Result<()> {
+    let data_dir = storage::profile_dir(profile)?;
+    let config = Config::load_profile(profile);
+    let mut history = HistoryStore::new(&data_dir, config.storage_format, config.sync_safe_storage);
+
+    let mut entries = history.load();
+    let removed = storage::gc_history(&mut entries, &config);
+    if removed > 0 {
+        history.save(&mut entries)?;
+    }
+
+    println!("GC removed {} entries", removed);
+    Ok(())
+}
+
+/// Deletes the queued telemetry events.
+pub fn run_reset_telemetry(profile: Option<&str>) -> io::Result<()> {
+    let recorder = TelemetryRecorder::new(storage::profile_dir(profile)?.join("telemetry.jsonl"));
+    if recorder.reset()? {
+        println!("Deleted the queued telemetry events");
+    } else {
+        println!("No telemetry events queued");
+    }
+    Ok(())
+}
+
+/// Writes the config, history, bodies and sessions to a bundle at `path`.
+pub fn run_export_bundle(path: &Path, profile: Option<&str>) -> io::Result<()> {
+    let config_path = storage::config_path(&storage::data_dir()?, profile);
+    let manifest = bundle::export(&storage::profile_dir(profile)?, &config_path, path)?;
+    println!(
+        "Exported {} files to {}",
+        manifest.files.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Puts `text` on the clipboard and at the top of the history, reading it
+/// from stdin if it's `-`. It goes through the same filters as a copy the
+/// app sees, so it can end up on the clipboard only. A running mac-clip
+/// records it from the clipboard instead, as it would save its own history
+/// over one changed behind its back.
+pub fn run_copy(text: &str, profile: Option<&str>) -> io::Result<()> {
+    let text = if text == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        // echo and most commands end their output with a newline
+        let end = input.trim_end_matches(['\n', '\r']).len();
+        input.truncate(end);
+        input
+    } else {
+        text.to_string()
+    };
+    if text.trim().is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "nothing to copy",
+        ));
+    }
+
+    clipboard::system()?
+        .lock()
+        .map_err(|_| io::Error::other("clipboard lock poisoned"))?
+        .set_text(&text)?;
+    let _lock = match InstanceLock::acquire(&storage::runtime_dir()?.join(instance::LOCK_FILE)) {
+        Ok(lock) => lock,
+        Err(LockError::AlreadyRunning(_)) => {
+            println!(
+                "Copied; the running mac-clip adds it to the history unless its filters skip it"
+            );
+            return Ok(());
+        }
+        Err(LockError::Io(e)) => return Err(e),
+    };
+
+    let data_dir = storage::profile_dir(profile)?;
+    let config = Config::load_profile(profile);
+    let mut history = HistoryStore::new(&data_dir, config.storage_format, config.sync_safe_storage);
+    let mut entries = history.load();
+    let exclusions = config.exclusion_regexes();
+    let copy = ClipboardEntry {
+        content: text,
+        ..Default::default()
+    };
+    let copy = FilterPipeline::default()
+        .with(SanitizeFilter)
+        .with(CaptureTypeFilter { config: &config })
+        .with(ExclusionPatternFilter {
+            patterns: &exclusions,
+        })
+        .run(copy);
+    let text = match copy {
+        Ok(copy) => copy.content,
+        Err(reason) => {
+            println!("Copied, but not added to the history: {}", reason);
+            return Ok(());
+        }
+    };
+
+    // Copying something already in the history moves it to the top
+    let timestamp = storage::unix_now();
+    let existing = filter::find_duplicate(&entries, config.dedup_mode, &text)
+        .and_then(|index| entries.remove(index));
+    let entry = match existing {
+        Some(entry) => ClipboardEntry { timestamp, ..entry },
+        None => ClipboardEntry {
+            expires_at: config
+                .sensitive_regexes()
+                .iter()
+                .any(|re| re.is_match(&text))
+                .then(|| timestamp + config.sensitive_ttl_secs),
+            content_type: Classifier::new(&config).classify(&text),
+            content: text,
+            timestamp,
+            ..Default::default()
+        },
+    };
+    entries.push_front(entry);
+    storage::trim_history(&mut entries, config.max_history_size);
+    history.save(&mut entries)?;
+    println!("Copied and added to the history");
+    Ok(())
+}
+
+/// Writes the history to a plist at `path` for Automator and AppleScript.
+pub fn run_export_plist(path: &Path, profile: Option<&str>) -> io::Result<()> {
+    let data_dir = storage::profile_dir(profile)?;
+    let config = Config::load_profile(profile);
+    let bodies = BodyStore::new(data_dir.join("bodies"));
+    let entries: Vec<ClipboardEntry> =
+        HistoryStore::new(&data_dir, config.storage_format, config.sync_safe_storage)
+            .load()
+            .iter()
+            .map(|entry| bodies.load(entry))
+            .collect();
+    export::export_plist(&entries, path)?;
+    println!("Exported the history to {}", path.display());
+    Ok(())
+}
+
+/// Replaces the config, history, bodies and sessions with those in the
+/// bundle at `path`. Refuses while mac-clip is running, as it would write
+/// its own history back over the imported one.
+pub fn run_import_bundle(path: &Path, profile: Option<&str>) -> io::Result<()> {
+    let _lock = match InstanceLock::acquire(&storage::runtime_dir()?.join(instance::LOCK_FILE)) {
+        Ok(lock) => lock,
+        Err(e @ LockError::AlreadyRunning(_)) => {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{}; quit it before importing", e),
+            ))
+        }
+        Err(LockError::Io(e)) => return Err(e),
+    };
+    let config_path = storage::config_path(&storage::data_dir()?, profile);
+    let manifest = bundle::import(&storage::profile_dir(profile)?, &config_path, path)?;
+    println!(
+        "Imported {} files from a bundle made by mac-clip {}",
+        manifest.files.len(),
+        manifest.app_version
+    );
+    Ok(())
+}
+
+/// Adds the history of the Pasta database at `path`, or Pasta's own if
+/// `None`, to mac-clip's. Refuses while mac-clip is running, like
+/// `run_import_bundle`.
+pub fn run_migrate_from_pasta(path: Option<&Path>, profile: Option<&str>) -> io::Result<()> {
+    let _lock = match InstanceLock::acquire(&storage::runtime_dir()?.join(instance::LOCK_FILE)) {
+        Ok(lock) => lock,
+        Err(e @ LockError::AlreadyRunning(_)) => {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{}; quit it before migrating", e),
+            ))
+        }
+        Err(LockError::Io(e)) => return Err(e),
+    };
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => pasta::default_db_path()
+            .ok_or_else(|| io::Error::other("can't determine the home directory"))?,
+    };
+    let imported = pasta::parse_pasta_db(&path)?;
+    let found = imported.len();
+
+    let data_dir = storage::profile_dir(profile)?;
+    let config = Config::load_profile(profile);
+    let mut history = HistoryStore::new(&data_dir, config.storage_format, config.sync_safe_storage);
+    let mut entries = history.load();
+    let added = history::merge(&mut entries, imported.into());
+    if added > 0 {
+        history.save(&mut entries)?;
+    }
+    println!(
+        "Added {} of {} entries from {}",
+        added,
+        found,
+        path.display()
+    );
+    if entries.len() > config.max_history_size {
+        println!(
+            "Only the newest {} are kept once mac-clip starts; raise max_history_size to keep more",
+            config.max_history_size
+        );
+    }
+    Ok(())
+}
+
+/// Returns the value following `flag` on the command line, if any.
+pub fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Prints the problems found in the profile's config file and returns the
+/// exit code: 0 if it's valid, 1 if there are errors and 2 if there are only
+/// warnings.
+pub fn run_validate_config(profile: Option<&str>) -> io::Result<i32> {
+    let path = storage::config_path(&storage::data_dir()?, profile);
+    if !path.exists() {
+        println!("No config file at {}, using defaults", path.display());
+        return Ok(0);
+    }
+
+    let issues = Config::check_file(&path)?;
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    if issues.iter().any(|issue| issue.severity == Severity::Error) {
+        Ok(1)
+    } else if issues.is_empty() {
+        println!("{} is valid", path.display());
+        Ok(0)
+    } else {
+        Ok(2)
+    }
+}
+
+/// Prints the entries added to and removed from the history between the
+/// snapshots at `old` and `new`, as JSON with `json`.
+pub fn run_diff(old: &Path, new: &Path, json: bool) -> io::Result<()> {
+    let load = |path: &Path| -> io::Result<Vec<ClipboardEntry>> {
+        let data = fs::read(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        Ok(history::decode(&data)?.into())
+    };
+    let diff = storage::diff_histories(&load(old)?, &load(new)?);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+    for (heading, entries) in [("Added", &diff.added), ("Removed", &diff.removed)] {
+        println!("{} ({}):", heading, entries.len());
+        for entry in entries {
+            println!(
+                "  {}: {} {}",
+                entry.index,
+                crate::iso_timestamp(entry.timestamp).unwrap_or_default(),
+                preview(&entry.content)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Prints history entries matching `query`, optionally including archives.
+pub fn run_search(query: &str, include_archives: bool, profile: Option<&str>) -> io::Result<()> {
+    let data_dir = storage::profile_dir(profile)?;
+    let query = query::parse(query).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("invalid query: {}", e))
+    })?;
+    let matches = |content: &str| query.as_ref().is_none_or(|q| q.matches(content));
+
+    let entries = storage::load_history(&storage::history_path(&data_dir));
+    for (i, entry) in entries.iter().enumerate() {
+        if matches(&entry.search_text()) {
+            match entry.label() {
+                Some(label) => println!("{}: [{}] {}", i, label, preview(&entry.content)),
+                None => println!("{}: {}", i, preview(&entry.content)),
+            }
+        }
+    }
+
+    if include_archives {
+        for entry in Archiver::load_all(&storage::archive_dir(profile)?)? {
+            if matches(&entry.search_text()) {
+                println!("archived: {}", preview(&entry.content));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints `template` with `{content}`, `{timestamp}`, `{index}` and `{app}`
+/// replaced by the fields of history entry `index`. Content and app are
+/// shell-quoted unless `escape` is false.
+pub fn run_format_entry(
+    index: usize,
+    template: &str,
+    escape: bool,
+    profile: Option<&str>,
+) -> io::Result<()> {
+    let data_dir = storage::profile_dir(profile)?;
+    let entries = storage::load_history(&storage::history_path(&data_dir));
+    let entry = entries.get(index).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No history entry at index {}", index),
+        )
+    })?;
+    let entry = BodyStore::new(data_dir.join("bodies")).load(entry);
+
+    let quote = |value: &str| -> io::Result<String> {
+        if escape {
+            shlex::try_quote(value)
+                .map(|quoted| quoted.into_owned())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        } else {
+            Ok(value.to_string())
+        }
+    };
+
+    // One pass over the template, so a placeholder that turns up inside the
+    // entry's content is printed as is rather than expanded in turn.
+    let mut formatted = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        formatted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let placeholder = rest.find('}').map(|end| &rest[..=end]);
+        let value = match placeholder {
+            Some("{content}") => quote(&entry.content)?,
+            Some("{timestamp}") => entry.timestamp.to_string(),
+            Some("{index}") => index.to_string(),
+            Some("{app}") => quote(entry.source_app.as_deref().unwrap_or(""))?,
+            _ => {
+                formatted.push('{');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        formatted.push_str(&value);
+        rest = &rest[placeholder.map_or(0, str::len)..];
+    }
+    formatted.push_str(rest);
+    println!("{}", formatted);
+    Ok(())
+}
+
+/// Restores the `nth` most recent history entry (1 being the newest) to the
+/// clipboard and pastes it into the focused app with a simulated Command+V.
+pub fn run_auto_paste_last(nth: usize, profile: Option<&str>) -> io::Result<()> {
+    let data_dir = storage::profile_dir(profile)?;
+    let entries = storage::load_history(&storage::history_path(&data_dir));
+    let entry = nth
+        .checked_sub(1)
+        .and_then(|index| entries.get(index))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No history entry {} back", nth),
+            )
+        })?;
+    let entry = BodyStore::new(data_dir.join("bodies")).load(entry);
+
+    if !macos::write_pasteboard_flavors(&entry.flavors) {
+        clipboard::system()?
+            .lock()
+            .map_err(|_| io::Error::other("clipboard lock poisoned"))?
+            .set_text(&entry.content)?;
+    }
+    simulate_paste();
+    Ok(())
+}
+
+/// Prints clipboard changes to stdout as they happen until interrupted,
+/// either as plain text or as newline-delimited JSON entries.
+pub fn run_tail(json: bool, profile: Option<&str>) -> io::Result<()> {
+    let config = Config::load_profile(profile);
+    let settle = Duration::from_millis(config.clipboard_settle_ms);
+    monitor::watch_clipboard(
+        clipboard::system()?,
+        CLIPBOARD_CHECK_INTERVAL,
+        settle,
+        None,
+        // Nothing checks on it from the command line
+        &monitor::HeartbeatMonitor::default(),
+        |content, _| {
+            if json {
+                let entry = ClipboardEntry {
+                    content,
+                    timestamp: storage::unix_now(),
+                    ..Default::default()
+                };
+                if let Ok(line) = serde_json::to_string(&entry) {
+                    println!("{}", line);
+                }
+            } else {
+                println!("{}", content);
+            }
+        },
+    );
+
+    Ok(())
+}
+
+/// Prints the last `lines` lines of the daemon's log, then new lines as
+/// they're written, like `tail -f`, until interrupted.
+pub fn run_daemon_logs(lines: usize) -> io::Result<()> {
+    let file = File::open(daemon::LOG_PATH).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "couldn't open {} ({}); is the daemon set up with --daemon?",
+                daemon::LOG_PATH,
+                e
+            ),
+        )
+    })?;
+    let mut reader = BufReader::new(file);
+    let mut stdout = io::stdout();
+    for line in read_last_lines(&mut reader, lines)? {
+        stdout.write_all(line.as_bytes())?;
+    }
+    stdout.flush()?;
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&interrupted))?;
+    let mut line = String::new();
+    while !interrupted.load(Ordering::Relaxed) {
+        // Truncated, so start again from the top
+        if reader.get_ref().metadata()?.len() < reader.stream_position()? {
+            reader.seek(SeekFrom::Start(0))?;
+            line.clear();
+        }
+        // A line without its newline yet is finished on a later read
+        if reader.read_line(&mut line)? == 0 || !line.ends_with('\n') {
+            thread::sleep(LOG_POLL_INTERVAL);
+            continue;
+        }
+        stdout.write_all(line.as_bytes())?;
+        stdout.flush()?;
+        line.clear();
+    }
+    Ok(())
+}
+
+/// Reads `reader` to the end and returns its last `count` lines, newlines
+/// included.
+pub fn read_last_lines(reader: &mut impl BufRead, count: usize) -> io::Result<VecDeque<String>> {
+    let mut lines = VecDeque::new();
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        lines.push_back(std::mem::take(&mut line));
+        if lines.len() > count {
+            lines.pop_front();
+        }
+    }
+    Ok(lines)
+}
+
+fn preview(content: &str) -> String {
+    widgets::display_text(content).replace('\n', "↵")
+}
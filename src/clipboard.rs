@@ -0,0 +1,85 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+/// Text access to a clipboard, so the app can run against the system
+/// clipboard or an in-memory one in tests.
+pub trait ClipboardBackend: Send {
+    fn get_text(&mut self) -> io::Result<String>;
+    fn set_text(&mut self, text: &str) -> io::Result<()>;
+}
+
+/// A clipboard shared between the UI and the monitor thread.
+pub type SharedClipboard = Arc<Mutex<Box<dyn ClipboardBackend>>>;
+
+impl ClipboardBackend for arboard::Clipboard {
+    fn get_text(&mut self) -> io::Result<String> {
+        arboard::Clipboard::get_text(self).map_err(io::Error::other)
+    }
+
+    fn set_text(&mut self, text: &str) -> io::Result<()> {
+        arboard::Clipboard::set_text(self, text).map_err(io::Error::other)
+    }
+}
+
+/// Opens the system clipboard.
+pub fn system() -> io::Result<SharedClipboard> {
+    #[cfg(feature = "integration-tests")]
+    if let Some(path) = std::env::var_os(TEST_CLIPBOARD_VAR) {
+        return Ok(Arc::new(Mutex::new(Box::new(FileClipboard(path.into())))));
+    }
+    let clipboard = arboard::Clipboard::new().map_err(io::Error::other)?;
+    Ok(Arc::new(Mutex::new(Box::new(clipboard))))
+}
+
+/// Names a file for the integration tests' runs of the binary to use as the
+/// clipboard.
+#[cfg(feature = "integration-tests")]
+const TEST_CLIPBOARD_VAR: &str = "MAC_CLIP_TEST_CLIPBOARD";
+
+/// A clipboard kept in a file, which the integration tests read back.
+#[cfg(feature = "integration-tests")]
+struct FileClipboard(std::path::PathBuf);
+
+#[cfg(feature = "integration-tests")]
+impl ClipboardBackend for FileClipboard {
+    fn get_text(&mut self) -> io::Result<String> {
+        std::fs::read_to_string(&self.0)
+    }
+
+    fn set_text(&mut self, text: &str) -> io::Result<()> {
+        std::fs::write(&self.0, text)
+    }
+}
+
+/// In-memory clipboard for tests. Clones share the same contents, so a test
+/// can keep one to inspect what the app wrote.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct MemoryClipboard {
+    pub text: Arc<Mutex<String>>,
+}
+
+#[cfg(test)]
+impl MemoryClipboard {
+    pub fn contents(&self) -> String {
+        self.text.lock().unwrap().clone()
+    }
+
+    pub fn shared(&self) -> SharedClipboard {
+        Arc::new(Mutex::new(Box::new(self.clone())))
+    }
+}
+
+#[cfg(test)]
+impl ClipboardBackend for MemoryClipboard {
+    fn get_text(&mut self) -> io::Result<String> {
+        Ok(self.contents())
+    }
+
+    fn set_text(&mut self, text: &str) -> io::Result<()> {
+        *self.text.lock().unwrap() = text.to_string();
+        Ok(())
+    }
+}
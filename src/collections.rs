@@ -0,0 +1,35 @@
+use crate::ClipboardEntry;
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::PathBuf};
+
+/// A named, ordered set of entries for filling in the same fields again and
+/// again, pasted one at a time with the collection hotkey. The entries are
+/// copies, so they stay put when the history moves on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub name: String,
+    pub entries: Vec<ClipboardEntry>,
+}
+
+/// Reads and writes every collection in a single `collections.json`.
+pub struct CollectionStore {
+    path: PathBuf,
+}
+
+impl CollectionStore {
+    pub fn new(path: PathBuf) -> Self {
+        CollectionStore { path }
+    }
+
+    pub fn load(&self) -> io::Result<Vec<Collection>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let data = fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn save(&self, collections: &[Collection]) -> io::Result<()> {
+        fs::write(&self.path, serde_json::to_string(collections)?)
+    }
+}
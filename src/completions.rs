@@ -0,0 +1,224 @@
+use directories::BaseDirs;
+use std::{fs, io, path::PathBuf, str::FromStr};
+
+/// Command line flags offered by the completion scripts, with a short
+/// description. Flags that take a value end with `=`.
+const FLAGS: &[(&str, &str)] = &[
+    (
+        "--daemon",
+        "Install the LaunchAgent so mac-clip starts at login",
+    ),
+    ("--daemon-logs", "Print the daemon's log as it's written"),
+    ("--status", "Print whether the daemon is running"),
+    (
+        "--profile=",
+        "Use a separate config and history under this name",
+    ),
+    ("--list-profiles", "Print the names of every profile"),
+    (
+        "--lines=",
+        "Lines of the log to print first with --daemon-logs",
+    ),
+    (
+        "--gc",
+        "Remove duplicate, excluded and expired history entries",
+    ),
+    (
+        "--search=",
+        "Print history entries containing the given text",
+    ),
+    ("--include-archives", "Also search archived entries"),
+    (
+        "--format-entry=",
+        "Print a history entry substituted into a template",
+    ),
+    ("--no-escape", "Don't shell-quote values in --format-entry"),
+    (
+        "--copy=",
+        "Copy text and add it to the history; - reads stdin",
+    ),
+    (
+        "--auto-paste-last",
+        "Paste the most recent entry into the focused app",
+    ),
+    ("--tail", "Print clipboard changes as they happen"),
+    ("--json", "Print newline-delimited JSON"),
+    ("--validate-config", "Check config.json for mistakes"),
+    ("--doctor", "Check permissions, storage and setup"),
+    (
+        "--set-pin",
+        "Set the PIN required to open the history window",
+    ),
+    ("--completions=", "Print the completion script for a shell"),
+    (
+        "--install-completion=",
+        "Install the completion script for a shell",
+    ),
+    ("--dry-run", "Show what would be written without writing it"),
+    (
+        "--diff",
+        "Print entries added and removed between two history files",
+    ),
+    ("--format=", "Output of --diff: text or json"),
+    (
+        "--export-bundle=",
+        "Write the config and history to a single file",
+    ),
+    (
+        "--export-plist=",
+        "Write the history to a plist for Automator",
+    ),
+    (
+        "--import-bundle=",
+        "Replace the config and history with a bundle's",
+    ),
+    (
+        "--migrate-from-pasta=",
+        "Add the history of the Pasta clipboard manager",
+    ),
+    (
+        "--reset-telemetry",
+        "Delete usage events waiting to be sent",
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            other => Err(format!(
+                "Unsupported shell {:?}, expected bash, zsh or fish",
+                other
+            )),
+        }
+    }
+}
+
+/// Returns the completion script for `shell`.
+pub fn generate(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => {
+            let words: Vec<&str> = FLAGS
+                .iter()
+                .map(|(flag, _)| flag.trim_end_matches('='))
+                .collect();
+            format!(
+                r#"_mac_clip() {{
+    local cur prev
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    case "$prev" in
+        --completions|--install-completion)
+            COMPREPLY=($(compgen -W "bash zsh fish" -- "$cur"))
+            return
+            ;;
+    esac
+    COMPREPLY=($(compgen -W "{}" -- "$cur"))
+}}
+complete -F _mac_clip mac-clip
+"#,
+                words.join(" ")
+            )
+        }
+        Shell::Zsh => {
+            let args: Vec<String> = FLAGS
+                .iter()
+                .map(|(flag, description)| match flag.strip_suffix('=') {
+                    Some("--completions") | Some("--install-completion") => format!(
+                        "    '{}[{}]:shell:(bash zsh fish)'",
+                        flag.trim_end_matches('='),
+                        description
+                    ),
+                    Some(flag) => format!("    '{}[{}]:value:'", flag, description),
+                    None => format!("    '{}[{}]'", flag, description),
+                })
+                .collect();
+            format!(
+                "#compdef mac-clip\n\n_arguments \\\n{}\n",
+                args.join(" \\\n")
+            )
+        }
+        Shell::Fish => FLAGS
+            .iter()
+            .map(|(flag, description)| {
+                let name = flag.trim_start_matches("--");
+                match name.strip_suffix('=') {
+                    Some(name @ ("completions" | "install-completion")) => format!(
+                        "complete -c mac-clip -l {} -x -a 'bash zsh fish' -d '{}'\n",
+                        name, description
+                    ),
+                    Some(name) => {
+                        format!("complete -c mac-clip -l {} -r -d '{}'\n", name, description)
+                    }
+                    None => format!("complete -c mac-clip -l {} -d '{}'\n", name, description),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Where the completion script for `shell` is installed.
+fn install_path(shell: Shell) -> io::Result<PathBuf> {
+    let home = BaseDirs::new()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Could not determine home directory",
+            )
+        })?
+        .home_dir()
+        .to_path_buf();
+
+    Ok(match shell {
+        Shell::Bash => home.join(".bash_completion.d/mac-clip"),
+        Shell::Zsh => home.join(".zfunc/_mac-clip"),
+        Shell::Fish => home.join(".config/fish/completions/mac-clip.fish"),
+    })
+}
+
+/// The line users need in their shell profile to load the script, if any.
+fn profile_line(shell: Shell) -> Option<&'static str> {
+    match shell {
+        Shell::Bash => {
+            Some("[ -f ~/.bash_completion.d/mac-clip ] && source ~/.bash_completion.d/mac-clip")
+        }
+        Shell::Zsh => Some("fpath=(~/.zfunc $fpath); autoload -Uz compinit && compinit"),
+        Shell::Fish => None,
+    }
+}
+
+/// Writes the completion script for `shell` to its conventional location and
+/// explains how to enable it. With `dry_run`, only prints what would happen.
+pub fn install(shell: Shell, dry_run: bool) -> io::Result<()> {
+    let path = install_path(shell)?;
+    let script = generate(shell);
+
+    if dry_run {
+        println!("Would write {}:\n\n{}", path.display(), script);
+    } else {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, script)?;
+        println!("Installed completions to {}", path.display());
+    }
+
+    match (shell, profile_line(shell)) {
+        (Shell::Zsh, Some(line)) => println!("Add this line to your ~/.zshrc:\n\n    {}", line),
+        (_, Some(line)) => println!("Add this line to your ~/.bashrc:\n\n    {}", line),
+        (_, None) => println!("Fish loads the completions automatically."),
+    }
+
+    Ok(())
+}
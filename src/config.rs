@@ -0,0 +1,837 @@
+use crate::{
+    classify::ContentType,
+    paste::{self, Transform},
+    storage,
+};
+use log::{error, info};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, io, path::Path};
+
+/// Kinds of clipboard content the monitor may record. Only text and raw
+/// binary data are read from the pasteboard today; the other kinds are
+/// accepted so configs written for richer capture stay valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureType {
+    Text,
+    Image,
+    Html,
+    Files,
+    /// Copies without any text, kept as raw data.
+    Binary,
+}
+
+/// How a new copy is matched against existing entries. A duplicate moves to
+/// the top of the history instead of being added again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupMode {
+    /// Record every copy.
+    Off,
+    /// Only skip a copy identical to the newest entry.
+    ExactFront,
+    /// Match identical content anywhere in the history.
+    #[default]
+    ExactAll,
+    /// Like `ExactAll`, but ignoring leading, trailing and repeated
+    /// whitespace.
+    NormalizedAll,
+}
+
+/// What happens when an entry is selected in the history window or the
+/// menu bar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteBehavior {
+    /// Copy the entry, hide the window and paste with a simulated Command+V.
+    #[default]
+    PasteAutomatically,
+    /// Copy the entry and hide the window; the user pastes. Doesn't need the
+    /// Accessibility permission.
+    CopyOnly,
+    /// Copy the entry and leave the window open.
+    CopyAndStayOpen,
+}
+
+/// What selecting an entry of a given kind does, in `default_action_by_kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultAction {
+    /// Whatever `paste_behavior` says.
+    Paste,
+    /// Opens a URL in the browser, or a file with its app.
+    Open,
+    /// Shows a file in Finder.
+    Reveal,
+}
+
+/// What the hotkey does while the screen is being shared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenCaptureGuard {
+    #[default]
+    Off,
+    /// Don't open the window at all.
+    Hide,
+    /// Open it with every entry's content hidden.
+    Mask,
+}
+
+/// What happens to a copy larger than `max_entry_bytes`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LargeContentPolicy {
+    /// Ask in the history window. Skipped when the window isn't open, since
+    /// there's nobody to ask.
+    #[default]
+    Ask,
+    Store,
+    /// Keep the first `max_entry_bytes`.
+    Truncate,
+    Skip,
+}
+
+/// How line breaks in entries are shown in the history list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NewlineDisplay {
+    /// As `↵`, keeping each entry on one line.
+    #[default]
+    Symbol,
+    /// As line breaks, showing up to `max_entry_lines` lines.
+    Actual,
+    Space,
+    /// As `\n`.
+    EscapeSequence,
+}
+
+/// How the history window lays out entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewMode {
+    /// One row per entry, with its actions beside it.
+    #[default]
+    List,
+    /// Tiles of `grid_columns` per row, showing the start of each entry.
+    Grid,
+}
+
+/// How `history` files are encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageFormat {
+    #[default]
+    Json,
+    /// Smaller and faster to read and write, but not human-readable.
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+/// Where the history window opens, unless `remember_window_position` has a
+/// spot to reopen it at.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowAnchor {
+    /// The middle of the main screen.
+    #[default]
+    Center,
+    /// With its top-left corner at the mouse pointer.
+    Cursor,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Where "Share" uploads an entry to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareService {
+    /// A secret GitHub Gist, using the `github` token.
+    #[default]
+    Gist,
+    /// An unlisted pastebin.com paste, using the `pastebin` API key.
+    Pastebin,
+    /// `share_url`, with the `custom` token if there is one.
+    Custom,
+}
+
+/// A user-defined rule classifying content matching `pattern`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentTypePattern {
+    pub pattern: String,
+    pub content_type: ContentType,
+}
+
+/// Transforms applied to text pasted into the app with `app_bundle_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartPasteRule {
+    pub app_bundle_id: String,
+    pub transforms: Vec<Transform>,
+}
+
+/// A regex substitution applied to text pasted into the app with
+/// `app_bundle_id`. `replacement` can refer to groups as `$1` or `${name}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegexTransformRule {
+    pub app_bundle_id: String,
+    pub pattern: String,
+    pub replacement: String,
+}
+
+/// A username and password for HTTP basic authentication.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// What each row of the history list shows besides the content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RowDetails {
+    /// When the entry was copied.
+    pub timestamp: bool,
+    /// The app it was copied from.
+    pub source_app: bool,
+    pub char_count: bool,
+    /// The content type badge.
+    pub content_type: bool,
+}
+
+impl Default for RowDetails {
+    fn default() -> Self {
+        RowDetails {
+            timestamp: false,
+            source_app: false,
+            char_count: false,
+            content_type: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Regular expressions for clipboard content that should never be kept.
+    pub exclusion_patterns: Vec<String>,
+    /// Bundle identifiers of apps whose clipboard changes are never recorded.
+    pub ignore_apps: Vec<String>,
+    /// Content matching any of these (e.g. one-time codes) expires after
+    /// `sensitive_ttl_secs`.
+    pub sensitive_patterns: Vec<String>,
+    pub sensitive_ttl_secs: u64,
+    /// How many unpinned entries the history keeps.
+    pub max_history_size: usize,
+    /// How many entries the list shows before a "Show more" button, so a
+    /// long history stays quick to open. Search still covers every entry.
+    pub display_limit: Option<usize>,
+    /// Copies larger than this are handled by `large_content_policy`.
+    pub max_entry_bytes: usize,
+    pub large_content_policy: LargeContentPolicy,
+    /// Binary copies larger than this many KiB are skipped.
+    pub max_binary_entry_kb: usize,
+    /// How long a new clipboard value must stay unchanged before it's
+    /// recorded; 0 records every change the monitor sees.
+    pub clipboard_settle_ms: u64,
+    /// How long after launch to register the hotkey and start watching the
+    /// clipboard. At login the pasteboard can still hold content from before
+    /// the restart, which the monitor would otherwise record as a new copy.
+    pub startup_hotkey_delay_ms: u64,
+    pub startup_monitor_delay_ms: u64,
+    /// Unpinned entries older than this are removed when a copy is added
+    /// and by the GC pass.
+    pub max_age_days: Option<u64>,
+    /// Entries older than this are moved into compressed monthly archives.
+    pub archive_after_days: Option<u32>,
+    pub dedup_mode: DedupMode,
+    /// Copies beyond this many a minute are skipped, so an app rewriting the
+    /// clipboard in a loop can't push everything else out of the history.
+    pub max_copies_per_minute: Option<u32>,
+    /// Tags new text entries with the language they're written in. Off by
+    /// default, as detection takes a noticeable amount of CPU.
+    pub detect_language: bool,
+    /// Checked before the built-in content type heuristics.
+    pub content_type_patterns: Vec<ContentTypePattern>,
+    /// Searches are cut off at this many characters, so a pasted wall of text
+    /// can't make every keystroke scan the history for it.
+    pub max_search_length: usize,
+    /// Placed between entries when copying several at once.
+    pub multi_copy_separator: String,
+    /// Splits a text file imported from the Settings tab into entries.
+    pub import_delimiter: String,
+    /// Whether "Copy escaped" keeps the surrounding double quotes.
+    pub json_escape_wrap_quotes: bool,
+    /// Offers a rendered view of Markdown entries in the full view.
+    pub render_markdown: bool,
+    /// Keeps the history window above other windows while it's shown.
+    pub always_on_top: bool,
+    /// Keeps the history out of screen shares and recordings.
+    pub screen_capture_guard: ScreenCaptureGuard,
+    /// Reopens the history window where it was last moved to instead of
+    /// centered.
+    pub remember_window_position: bool,
+    pub window_anchor: WindowAnchor,
+    /// Gap in points between a window at one of the `window_anchor`
+    /// corners and the edges of the screen.
+    pub window_margin: u32,
+    /// Keeps windows at the top corners below the notch when the menu bar
+    /// hides itself.
+    pub notch_aware: bool,
+    /// Keeps the open window just below the mouse pointer as it moves.
+    pub cursor_follow: bool,
+    /// Warns when the clipboard changes without a key press or click just
+    /// before it, which can mean malware swapped what was copied.
+    pub warn_on_spontaneous_changes: bool,
+    pub paste_behavior: PasteBehavior,
+    /// Overrides `paste_behavior` for entries of these kinds. Selecting an
+    /// entry with Option held always pastes.
+    pub default_action_by_kind: HashMap<ContentType, DefaultAction>,
+    pub share_service: ShareService,
+    /// Endpoint for the `custom` share service.
+    pub share_url: Option<String>,
+    /// Tokens for the share services, keyed by `github`, `pastebin` or
+    /// `custom`.
+    pub api_tokens: HashMap<String, String>,
+    /// Reactivates the app that was frontmost when the hotkey was pressed
+    /// before pasting, so the paste doesn't land in another window.
+    pub refocus_previous_app: bool,
+    /// Adjusts pasted text for the app it's pasted into, following
+    /// `smart_paste_rules`.
+    pub smart_paste: bool,
+    pub smart_paste_rules: Vec<SmartPasteRule>,
+    /// Applied when pasting into the rule's app, whether or not
+    /// `smart_paste` is on.
+    pub regex_transform_rules: Vec<RegexTransformRule>,
+    /// How many pinned and recent entries the menu bar icon lists; 0 hides
+    /// the icon.
+    pub tray_entries: usize,
+    pub row_details: RowDetails,
+    /// Copying the same thing twice in quick succession records it even
+    /// if it would be skipped otherwise. macOS only.
+    pub double_copy_capture: bool,
+    /// Holding Control + Option and tapping V cycles through the history,
+    /// pasting the highlighted entry when the keys are released.
+    pub hold_to_cycle: bool,
+    /// How many of the newest entries get a Command + Option + digit hotkey
+    /// that pastes them, up to `MAX_QUICK_ACCESS_SLOTS`; 0 registers none.
+    pub quick_access_slots: usize,
+    pub newline_display: NewlineDisplay,
+    /// Lines shown per entry when `newline_display` is `actual`.
+    pub max_entry_lines: usize,
+    /// Switched with the button beside the window's title.
+    pub view_mode: ViewMode,
+    /// Tiles per row in the grid view.
+    pub grid_columns: usize,
+    /// A thin bar beside the history showing how far down it's scrolled.
+    /// Left out, it's shown once the list has more than
+    /// `SCROLL_INDICATOR_MIN_ENTRIES` entries.
+    pub show_scroll_indicator: Option<bool>,
+    /// Height in pixels of the pane under the list that previews the
+    /// highlighted entry, within `PREVIEW_HEIGHTS`. Dragging the divider
+    /// above the pane changes it.
+    pub preview_height: u32,
+    /// Records which features are used, never what's copied, and sends
+    /// the counts to `telemetry_url` once a day.
+    pub telemetry_opt_in: bool,
+    pub telemetry_url: Option<String>,
+    /// A WebDAV server the history is kept in sync with, in
+    /// `<webdav_url>/mac-clip/history.json`.
+    pub webdav_url: Option<String>,
+    pub webdav_credentials: Option<Credentials>,
+    pub webdav_sync_interval_secs: u64,
+    /// Asks for a PIN before showing the history window.
+    pub require_pin: bool,
+    /// bcrypt hash of the PIN, set with `mac-clip --set-pin`.
+    pub pin_hash: Option<String>,
+    /// Which clipboard content types get recorded.
+    pub capture_types: Vec<CaptureType>,
+    /// Encoding of the history file. Switching converts the existing file
+    /// on the next load or save.
+    pub storage_format: StorageFormat,
+    /// Locks the history file while reading and writing it and merges in
+    /// entries written by another machine, for a data directory synced by
+    /// iCloud Drive or Dropbox.
+    pub sync_safe_storage: bool,
+    /// Keeps only previews of long entries in memory and reads the full
+    /// content from disk when it's needed.
+    pub low_memory_mode: bool,
+    /// Also records the X11 `PRIMARY` (mouse selection) clipboard. Linux only.
+    pub monitor_primary_selection: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            exclusion_patterns: Vec::new(),
+            ignore_apps: Vec::new(),
+            sensitive_patterns: vec![r"^\s*\d{6}\s*$".to_string()],
+            sensitive_ttl_secs: 60,
+            max_history_size: crate::MAX_HISTORY_SIZE,
+            display_limit: None,
+            max_entry_bytes: 1024 * 1024,
+            max_binary_entry_kb: 1024,
+            large_content_policy: LargeContentPolicy::default(),
+            clipboard_settle_ms: 0,
+            startup_hotkey_delay_ms: 0,
+            startup_monitor_delay_ms: 0,
+            max_age_days: None,
+            archive_after_days: None,
+            dedup_mode: DedupMode::default(),
+            max_copies_per_minute: None,
+            detect_language: false,
+            content_type_patterns: Vec::new(),
+            max_search_length: 100,
+            multi_copy_separator: "\n---\n".to_string(),
+            import_delimiter: "\n".to_string(),
+            json_escape_wrap_quotes: true,
+            render_markdown: false,
+            always_on_top: false,
+            screen_capture_guard: ScreenCaptureGuard::Off,
+            remember_window_position: false,
+            window_anchor: WindowAnchor::default(),
+            window_margin: 10,
+            notch_aware: true,
+            cursor_follow: false,
+            warn_on_spontaneous_changes: false,
+            paste_behavior: PasteBehavior::default(),
+            default_action_by_kind: HashMap::new(),
+            share_service: ShareService::default(),
+            share_url: None,
+            api_tokens: HashMap::new(),
+            refocus_previous_app: false,
+            smart_paste: false,
+            smart_paste_rules: paste::default_rules(),
+            regex_transform_rules: Vec::new(),
+            tray_entries: 10,
+            row_details: RowDetails::default(),
+            double_copy_capture: false,
+            hold_to_cycle: false,
+            quick_access_slots: 9,
+            newline_display: NewlineDisplay::Symbol,
+            max_entry_lines: 3,
+            view_mode: ViewMode::List,
+            show_scroll_indicator: None,
+            preview_height: 150,
+            grid_columns: 3,
+            telemetry_opt_in: false,
+            telemetry_url: None,
+            webdav_url: None,
+            webdav_credentials: None,
+            webdav_sync_interval_secs: 5 * 60,
+            require_pin: false,
+            pin_hash: None,
+            capture_types: vec![CaptureType::Text],
+            storage_format: StorageFormat::Json,
+            sync_safe_storage: false,
+            low_memory_mode: false,
+            monitor_primary_selection: false,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config of `profile`, or of the default profile if `None`.
+    pub fn load_profile(profile: Option<&str>) -> Config {
+        match storage::data_dir() {
+            Ok(dir) => Config::load(&storage::config_path(&dir, profile)),
+            Err(e) => {
+                error!("Failed to find the data directory: {}", e);
+                Config::default()
+            }
+        }
+    }
+
+    /// Loads the config at `path`, falling back to the defaults if it's
+    /// missing or can't be read or parsed.
+    pub fn load(path: &Path) -> Config {
+        Config::try_load(path).unwrap_or_else(|e| {
+            error!("Failed to load config {}: {}", path.display(), e);
+            Config::default()
+        })
+    }
+
+    /// Loads the config at `path`, or the defaults if there's no file yet.
+    /// Unlike [`load`](Self::load), a file that can't be read or parsed is
+    /// an error, for callers that would otherwise replace it with defaults.
+    pub fn try_load(path: &Path) -> io::Result<Config> {
+        if !path.exists() {
+            info!("No config file found, using defaults");
+            return Ok(Config::default());
+        }
+        let data = fs::read_to_string(path)?;
+        serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Lists the top-level fields that differ from `other` with their old and
+    /// new values as JSON. The PIN hash and API tokens are never shown.
+    pub fn changes_from(&self, other: &Config) -> Vec<(String, String, String)> {
+        let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+            (serde_json::to_value(other), serde_json::to_value(self))
+        else {
+            return Vec::new();
+        };
+        new.into_iter()
+            .filter(|(key, value)| old.get(key) != Some(value))
+            .map(|(key, value)| {
+                let old_value = old.get(&key).map(|v| v.to_string()).unwrap_or_default();
+                if key == "pin_hash" || key == "api_tokens" || key == "webdav_credentials" {
+                    (key, "…".to_string(), "…".to_string())
+                } else {
+                    (key, old_value, value.to_string())
+                }
+            })
+            .collect()
+    }
+
+    /// Reads `path` and reports syntax errors, unknown fields and anything
+    /// [`validate`] finds, for `mac-clip --validate-config`.
+    pub fn check_file(path: &Path) -> io::Result<Vec<ConfigIssue>> {
+        let data = fs::read_to_string(path)?;
+        let value: serde_json::Value = match serde_json::from_str(&data) {
+            Ok(value) => value,
+            Err(e) => return Ok(vec![ConfigIssue::error("$", e.to_string())]),
+        };
+
+        let mut issues = unknown_fields(&value);
+        match serde_path_to_error::deserialize::<_, Config>(value) {
+            Ok(config) => issues.extend(validate(&config)),
+            Err(e) => issues.push(ConfigIssue::error(
+                &format!("$.{}", e.path()),
+                e.inner().to_string(),
+            )),
+        }
+        Ok(issues)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn captures(&self, capture_type: CaptureType) -> bool {
+        self.capture_types.contains(&capture_type)
+    }
+
+    pub fn exclusion_regexes(&self) -> Vec<Regex> {
+        compile_patterns(&self.exclusion_patterns, "exclusion")
+    }
+
+    pub fn sensitive_regexes(&self) -> Vec<Regex> {
+        compile_patterns(&self.sensitive_patterns, "sensitive")
+    }
+
+    pub fn content_type_regexes(&self) -> Vec<(Regex, ContentType)> {
+        self.content_type_patterns
+            .iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(re) => Some((re, rule.content_type)),
+                Err(e) => {
+                    error!(
+                        "Ignoring invalid content type pattern {:?}: {}",
+                        rule.pattern, e
+                    );
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+fn compile_patterns(patterns: &[String], kind: &str) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|pattern| match Regex::new(pattern) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                error!("Ignoring invalid {} pattern {:?}: {}", kind, pattern, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in the config, located by its JSON path, e.g.
+/// `$.exclusion_patterns[1]`.
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn error(path: &str, message: impl Into<String>) -> Self {
+        ConfigIssue {
+            severity: Severity::Error,
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(path: &str, message: impl Into<String>) -> Self {
+        ConfigIssue {
+            severity: Severity::Warning,
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.path, self.message)
+    }
+}
+
+/// Top-level keys that aren't config fields, usually typos. They'd
+/// otherwise be silently ignored.
+fn unknown_fields(value: &serde_json::Value) -> Vec<ConfigIssue> {
+    let Some(object) = value.as_object() else {
+        return vec![ConfigIssue::error("$", "config must be a JSON object")];
+    };
+    let known = serde_json::to_value(Config::default()).unwrap_or_default();
+    object
+        .keys()
+        .filter(|key| known.get(key.as_str()).is_none())
+        .map(|key| ConfigIssue::error(&format!("$.{}", key), "unknown field"))
+        .collect()
+}
+
+/// Checks values that parse but can't work or probably aren't intended.
+pub fn validate(config: &Config) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+
+    let pattern_lists = [
+        ("exclusion_patterns", &config.exclusion_patterns),
+        ("sensitive_patterns", &config.sensitive_patterns),
+    ];
+    for (field, patterns) in pattern_lists {
+        for (i, pattern) in patterns.iter().enumerate() {
+            if let Err(e) = Regex::new(pattern) {
+                issues.push(ConfigIssue::error(
+                    &format!("$.{}[{}]", field, i),
+                    e.to_string(),
+                ));
+            }
+        }
+    }
+    for (i, rule) in config.content_type_patterns.iter().enumerate() {
+        if let Err(e) = Regex::new(&rule.pattern) {
+            issues.push(ConfigIssue::error(
+                &format!("$.content_type_patterns[{}].pattern", i),
+                e.to_string(),
+            ));
+        }
+    }
+    for (i, rule) in config.regex_transform_rules.iter().enumerate() {
+        if let Err(e) = Regex::new(&rule.pattern) {
+            issues.push(ConfigIssue::error(
+                &format!("$.regex_transform_rules[{}].pattern", i),
+                e.to_string(),
+            ));
+        }
+    }
+
+    if config.sensitive_ttl_secs == 0 {
+        issues.push(ConfigIssue::warning(
+            "$.sensitive_ttl_secs",
+            "sensitive entries will be removed as soon as they're copied",
+        ));
+    }
+    if config.share_service == ShareService::Custom && config.share_url.is_none() {
+        issues.push(ConfigIssue::warning(
+            "$.share_url",
+            "share_service is \"custom\" but no share_url is set, so sharing will fail",
+        ));
+    }
+    if config.max_history_size == 0 {
+        issues.push(ConfigIssue::warning(
+            "$.max_history_size",
+            "only pinned entries will be kept",
+        ));
+    }
+    for (kind, action) in &config.default_action_by_kind {
+        let fits = match action {
+            DefaultAction::Paste => true,
+            DefaultAction::Open => matches!(kind, ContentType::Url | ContentType::FilePath),
+            DefaultAction::Reveal => *kind == ContentType::FilePath,
+        };
+        if !fits {
+            let name = serde_json::json!(kind);
+            issues.push(ConfigIssue::warning(
+                &format!(
+                    "$.default_action_by_kind.{}",
+                    name.as_str().unwrap_or_default()
+                ),
+                "only URLs and file paths can be opened, and only file paths revealed",
+            ));
+        }
+    }
+    if config.clipboard_settle_ms > 2000 {
+        issues.push(ConfigIssue::warning(
+            "$.clipboard_settle_ms",
+            "anything replaced within this time is never recorded; a few hundred milliseconds is usually enough",
+        ));
+    }
+    for (path, delay) in [
+        ("$.startup_hotkey_delay_ms", config.startup_hotkey_delay_ms),
+        (
+            "$.startup_monitor_delay_ms",
+            config.startup_monitor_delay_ms,
+        ),
+    ] {
+        if delay > 10_000 {
+            issues.push(ConfigIssue::warning(
+                path,
+                "mac-clip won't respond for this long after launch; a few seconds is usually enough",
+            ));
+        }
+    }
+    if config.max_binary_entry_kb > 4 * 1024 {
+        issues.push(ConfigIssue::warning(
+            "$.max_binary_entry_kb",
+            "copies over 4096 KiB are never read from the pasteboard",
+        ));
+    }
+    if config.max_age_days == Some(0) {
+        issues.push(ConfigIssue::warning(
+            "$.max_age_days",
+            "every unpinned entry will be removed by the next GC pass",
+        ));
+    }
+    if config.archive_after_days == Some(0) {
+        issues.push(ConfigIssue::warning(
+            "$.archive_after_days",
+            "every unpinned entry will be archived on the next pass",
+        ));
+    }
+    if let (Some(max_age), Some(archive_after)) = (config.max_age_days, config.archive_after_days) {
+        if max_age <= u64::from(archive_after) {
+            issues.push(ConfigIssue::warning(
+                "$.archive_after_days",
+                "entries are removed by max_age_days before they're old enough to archive",
+            ));
+        }
+    }
+    if config.require_pin && config.pin_hash.is_none() {
+        issues.push(ConfigIssue::warning(
+            "$.require_pin",
+            "no PIN is set, so the window won't be locked; run mac-clip --set-pin",
+        ));
+    }
+    if config.import_delimiter.is_empty() {
+        issues.push(ConfigIssue::error(
+            "$.import_delimiter",
+            "can't be empty; use \"\\n\" for one entry per line",
+        ));
+    }
+    if config.max_copies_per_minute == Some(0) {
+        issues.push(ConfigIssue::warning(
+            "$.max_copies_per_minute",
+            "no copies will be recorded; leave it out to record every copy",
+        ));
+    }
+    if config.quick_access_slots > crate::MAX_QUICK_ACCESS_SLOTS {
+        issues.push(ConfigIssue::warning(
+            "$.quick_access_slots",
+            format!(
+                "only the first {} entries get a hotkey",
+                crate::MAX_QUICK_ACCESS_SLOTS
+            ),
+        ));
+    }
+    if config.max_search_length == 0 {
+        issues.push(ConfigIssue::warning(
+            "$.max_search_length",
+            "nothing can be typed into the search field",
+        ));
+    }
+    if config.display_limit == Some(0) {
+        issues.push(ConfigIssue::warning(
+            "$.display_limit",
+            "the list will be empty until \"Show more\" is clicked",
+        ));
+    }
+    if config.newline_display == NewlineDisplay::Actual && config.max_entry_lines == 0 {
+        issues.push(ConfigIssue::warning(
+            "$.max_entry_lines",
+            "entries will still show their first line",
+        ));
+    }
+    if config.grid_columns == 0 {
+        issues.push(ConfigIssue::warning(
+            "$.grid_columns",
+            "the grid view will show one tile per row",
+        ));
+    }
+    if !crate::PREVIEW_HEIGHTS.contains(&config.preview_height) {
+        issues.push(ConfigIssue::warning(
+            "$.preview_height",
+            format!(
+                "the preview pane will be between {} and {} pixels high",
+                crate::PREVIEW_HEIGHTS.start(),
+                crate::PREVIEW_HEIGHTS.end()
+            ),
+        ));
+    }
+    if config.telemetry_opt_in && config.telemetry_url.is_none() {
+        issues.push(ConfigIssue::warning(
+            "$.telemetry_url",
+            "usage events are recorded but never sent without a telemetry_url",
+        ));
+    }
+    match &config.webdav_url {
+        Some(url) if !url.starts_with("https://") && !url.starts_with("http://") => {
+            issues.push(ConfigIssue::error(
+                "$.webdav_url",
+                "must start with https:// or http://",
+            ));
+        }
+        Some(url) if url.starts_with("http://") && config.webdav_credentials.is_some() => {
+            issues.push(ConfigIssue::warning(
+                "$.webdav_url",
+                "credentials and history would be sent unencrypted; use https://",
+            ));
+        }
+        None if config.webdav_credentials.is_some() => {
+            issues.push(ConfigIssue::warning(
+                "$.webdav_credentials",
+                "unused without a webdav_url",
+            ));
+        }
+        _ => {}
+    }
+    if config.webdav_url.is_some() && config.webdav_sync_interval_secs < 30 {
+        issues.push(ConfigIssue::warning(
+            "$.webdav_sync_interval_secs",
+            "uploads this often may be throttled by the server; a few minutes is usually enough",
+        ));
+    }
+    if config.capture_types.is_empty() {
+        issues.push(ConfigIssue::warning(
+            "$.capture_types",
+            "nothing will be recorded",
+        ));
+    }
+    for (i, app) in config.ignore_apps.iter().enumerate() {
+        if app.trim().is_empty() {
+            issues.push(ConfigIssue::warning(
+                &format!("$.ignore_apps[{}]", i),
+                "empty bundle identifier",
+            ));
+        }
+    }
+
+    issues
+}
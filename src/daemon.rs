@@ -2,6 +2,30 @@ use std::fs;
 use std::path::PathBuf;
 use directories::BaseDirs;
 
+pub const LAUNCH_AGENT_LABEL: &str = "com.mac-clip.daemon";
+
+/// Where launchd writes the daemon's stderr, which is where its log goes.
+pub const LOG_PATH: &str = "/tmp/mac-clip.stderr.log";
+
+/// Where `setup_daemon` writes the LaunchAgent plist.
+pub fn plist_path() -> Option<PathBuf> {
+    BaseDirs::new().map(|base_dirs| {
+        base_dirs
+            .home_dir()
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", LAUNCH_AGENT_LABEL))
+    })
+}
+
+/// Whether launchd has the LaunchAgent loaded.
+pub fn is_loaded() -> bool {
+    std::process::Command::new("launchctl")
+        .args(["list", LAUNCH_AGENT_LABEL])
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
 pub fn setup_daemon() -> std::io::Result<()> {
     if let Some(base_dirs) = BaseDirs::new() {
         // Get the LaunchAgents directory
@@ -20,7 +44,7 @@ pub fn setup_daemon() -> std::io::Result<()> {
 <plist version="1.0">
 <dict>
     <key>Label</key>
-    <string>com.mac-clip.daemon</string>
+    <string>{}</string>
     <key>ProgramArguments</key>
     <array>
         <string>{}</string>
@@ -32,12 +56,12 @@ pub fn setup_daemon() -> std::io::Result<()> {
     <key>StandardOutPath</key>
     <string>/tmp/mac-clip.stdout.log</string>
     <key>StandardErrorPath</key>
-    <string>/tmp/mac-clip.stderr.log</string>
+    <string>{}</string>
 </dict>
-</plist>"#, cargo_bin_path);
+</plist>"#, LAUNCH_AGENT_LABEL, cargo_bin_path, LOG_PATH);
 
         // Write the plist file
-        let plist_path = launch_agents_dir.join("com.mac-clip.daemon.plist");
+        let plist_path = launch_agents_dir.join(format!("{}.plist", LAUNCH_AGENT_LABEL));
         fs::write(&plist_path, plist_content)?;
 
         // Load the launch agent
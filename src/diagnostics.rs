@@ -0,0 +1,95 @@
+//! `mac-clip --status`: whether the daemon is up, and what it has recorded.
+
+use crate::{daemon, instance, storage};
+use std::io;
+
+/// What `--status` reports, gathered without touching the running instance.
+pub struct DaemonStatus {
+    pub launch_agent_loaded: bool,
+    /// PID of the mac-clip holding the instance lock, if it's alive.
+    pub pid: Option<u32>,
+    pub entries: usize,
+    /// Unix time of the newest entry.
+    pub last_capture: Option<u64>,
+}
+
+impl DaemonStatus {
+    /// Reads the history of `profile`, or of the default profile if `None`.
+    pub fn gather(profile: Option<&str>) -> io::Result<DaemonStatus> {
+        let history =
+            storage::load_history(&storage::history_path(&storage::profile_dir(profile)?));
+        Ok(DaemonStatus {
+            launch_agent_loaded: daemon::is_loaded(),
+            pid: running_pid(),
+            entries: history.len(),
+            last_capture: history.iter().map(|entry| entry.timestamp).max(),
+        })
+    }
+
+    /// Whether the LaunchAgent is loaded and its process is alive.
+    pub fn healthy(&self) -> bool {
+        self.launch_agent_loaded && self.pid.is_some()
+    }
+
+    /// The report `--status` prints, as of `now`.
+    pub fn summary(&self, now: u64) -> Vec<String> {
+        let agent = if self.launch_agent_loaded {
+            "loaded"
+        } else {
+            "not loaded (run mac-clip --daemon to install it)"
+        };
+        let process = match self.pid {
+            Some(pid) => format!("running (PID {})", pid),
+            None => "not running".to_string(),
+        };
+        let last_capture = match self.last_capture {
+            Some(timestamp) => describe_age(now.saturating_sub(timestamp)),
+            None => "never".to_string(),
+        };
+        vec![
+            format!("LaunchAgent: {}", agent),
+            format!("Process: {}", process),
+            format!("History: {} entries", self.entries),
+            format!("Last capture: {}", last_capture),
+        ]
+    }
+}
+
+/// PID of the running mac-clip, from its instance lock. Shared with
+/// `--doctor`.
+pub fn running_pid() -> Option<u32> {
+    let path = storage::runtime_dir().ok()?.join(instance::LOCK_FILE);
+    instance::running_pid(&path)
+}
+
+/// How long ago something `secs` seconds old happened, e.g. "5 minutes ago".
+pub fn describe_age(secs: u64) -> String {
+    let (count, unit) = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86_399 => (secs / 3600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// Prints the status and returns the exit code: 0 if the daemon is running,
+/// 1 otherwise.
+pub fn run_status(profile: Option<&str>) -> i32 {
+    let status = match DaemonStatus::gather(profile) {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("Failed to find the data directory: {}", e);
+            return 1;
+        }
+    };
+    for line in status.summary(storage::unix_now()) {
+        println!("{}", line);
+    }
+    i32::from(!status.healthy())
+}
@@ -0,0 +1,90 @@
+//! Line-by-line differences between two texts, for comparing entries.
+
+/// Cap on the lines × lines table of the longest-common-subsequence search.
+/// Beyond it the differing middle is shown as removed and then added.
+const MAX_TABLE_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A line in both texts.
+    Equal(String),
+    /// A line only in the old text.
+    Removed(String),
+    /// A line only in the new text.
+    Added(String),
+}
+
+/// The changes that turn `old` into `new`, in order.
+pub fn diff_lines(old: &str, new: &str) -> Vec<Change> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut changes: Vec<Change> = old[..prefix]
+        .iter()
+        .map(|line| Change::Equal(line.to_string()))
+        .collect();
+    changes.extend(diff_middle(old_middle, new_middle));
+    changes.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| Change::Equal(line.to_string())),
+    );
+    changes
+}
+
+fn diff_middle(old: &[&str], new: &[&str]) -> Vec<Change> {
+    if (old.len() + 1).saturating_mul(new.len() + 1) > MAX_TABLE_CELLS {
+        return old
+            .iter()
+            .map(|line| Change::Removed(line.to_string()))
+            .chain(new.iter().map(|line| Change::Added(line.to_string())))
+            .collect();
+    }
+
+    // common[i][j] is the length of the longest common subsequence of
+    // old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut common = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i * width + j] = if old[i] == new[j] {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(Change::Equal(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if common[(i + 1) * width + j] >= common[i * width + j + 1] {
+            changes.push(Change::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    changes.extend(
+        old[i..]
+            .iter()
+            .map(|line| Change::Removed(line.to_string())),
+    );
+    changes.extend(new[j..].iter().map(|line| Change::Added(line.to_string())));
+    changes
+}
@@ -0,0 +1,275 @@
+//! `mac-clip --doctor`: checks the things mac-clip needs to work and prints
+//! what to do about anything that's wrong.
+
+use crate::{
+    clipboard,
+    config::{Config, PasteBehavior},
+    daemon, diagnostics, history_hotkey, macos, storage, HOTKEY_LABEL,
+};
+use global_hotkey::GlobalHotKeyManager;
+use std::{fmt, fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Ok => "ok  ",
+            Status::Warning => "warn",
+            Status::Failed => "FAIL",
+        })
+    }
+}
+
+struct Check {
+    status: Status,
+    name: &'static str,
+    detail: String,
+    /// What the user can do about a warning or failure.
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Check {
+            status: Status::Ok,
+            name,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warning(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            status: Status::Warning,
+            name,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn failed(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            status: Status::Failed,
+            name,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Runs every check against `profile`, prints the report and returns the
+/// exit code: 1 if any check failed, 0 otherwise.
+pub fn run(profile: Option<&str>) -> i32 {
+    let config = Config::load_profile(profile);
+    let mut checks = vec![check_clipboard()];
+    checks.extend(check_permissions(&config));
+    checks.push(check_launch_agent());
+    checks.push(check_hotkey());
+    checks.extend(check_storage(profile));
+
+    for check in &checks {
+        println!("[{}] {}: {}", check.status, check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            println!("       → {}", fix);
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Failed).count();
+    let warnings = checks
+        .iter()
+        .filter(|c| c.status == Status::Warning)
+        .count();
+    println!();
+    if failed + warnings == 0 {
+        println!("Everything looks good.");
+    } else {
+        println!("{} failed, {} warnings", failed, warnings);
+    }
+    i32::from(failed > 0)
+}
+
+fn check_clipboard() -> Check {
+    const NAME: &str = "Clipboard";
+    let clipboard = match clipboard::system() {
+        Ok(clipboard) => clipboard,
+        Err(e) => {
+            return Check::failed(
+                NAME,
+                format!("can't open the clipboard: {}", e),
+                "Make sure mac-clip runs in a logged-in GUI session",
+            )
+        }
+    };
+    let result = clipboard
+        .lock()
+        .map_err(|e| e.to_string())
+        .and_then(|mut clipboard| clipboard.get_text().map_err(|e| e.to_string()));
+    match result {
+        Ok(text) => Check::ok(
+            NAME,
+            format!("readable ({} characters)", text.chars().count()),
+        ),
+        // An empty or non-text clipboard can't be read as text either
+        Err(e) => Check::warning(
+            NAME,
+            format!("opened, but couldn't read text: {}", e),
+            "Copy some text and run --doctor again",
+        ),
+    }
+}
+
+fn check_permissions(config: &Config) -> Vec<Check> {
+    let mut checks = Vec::new();
+    if let Some(trusted) = macos::accessibility_trusted() {
+        checks.push(if trusted {
+            Check::ok("Accessibility", "granted")
+        } else if config.paste_behavior != PasteBehavior::PasteAutomatically {
+            Check::ok(
+                "Accessibility",
+                "not granted, which is fine since paste_behavior doesn't paste for you",
+            )
+        } else {
+            Check::failed(
+                "Accessibility",
+                "not granted, so pasting into other apps won't work",
+                "Allow mac-clip (or your terminal) in System Settings → Privacy & Security → Accessibility, or set paste_behavior to \"copy_only\" and paste yourself",
+            )
+        });
+    }
+    if cfg!(target_os = "macos") {
+        checks.push(match macos::input_monitoring_granted() {
+            Some(true) => Check::ok("Input Monitoring", "granted"),
+            Some(false) => Check::warning(
+                "Input Monitoring",
+                "denied, so the global hotkey may not fire",
+                "Allow mac-clip (or your terminal) in System Settings → Privacy & Security → Input Monitoring",
+            ),
+            None => Check::warning(
+                "Input Monitoring",
+                "not requested yet",
+                "macOS asks the first time mac-clip runs; allow it when prompted",
+            ),
+        });
+    }
+    checks
+}
+
+fn check_launch_agent() -> Check {
+    const NAME: &str = "LaunchAgent";
+    let Some(path) = daemon::plist_path() else {
+        return Check::failed(
+            NAME,
+            "can't determine the home directory",
+            "Check that $HOME is set",
+        );
+    };
+    match (path.exists(), daemon::is_loaded()) {
+        (true, true) => match diagnostics::running_pid() {
+            Some(pid) => Check::ok(
+                NAME,
+                format!("installed and running as PID {} ({})", pid, path.display()),
+            ),
+            None => Check::warning(
+                NAME,
+                format!("loaded from {} but not running", path.display()),
+                "See why it stopped with: mac-clip --daemon-logs",
+            ),
+        },
+        (true, false) => Check::warning(
+            NAME,
+            format!("installed at {} but not loaded", path.display()),
+            format!("Run: launchctl load -w {}", path.display()),
+        ),
+        (false, _) => Check::warning(
+            NAME,
+            "not installed, so mac-clip won't start at login",
+            "Run: mac-clip --daemon",
+        ),
+    }
+}
+
+fn check_hotkey() -> Check {
+    const NAME: &str = "Hotkey";
+    let manager = match GlobalHotKeyManager::new() {
+        Ok(manager) => manager,
+        Err(e) => {
+            return Check::failed(
+                NAME,
+                format!("{}: can't create the hotkey manager: {}", HOTKEY_LABEL, e),
+                "Grant Input Monitoring and try again",
+            )
+        }
+    };
+    let hotkey = history_hotkey();
+    match manager.register(hotkey) {
+        Ok(()) => {
+            let _ = manager.unregister(hotkey);
+            Check::ok(NAME, format!("{} can be registered", HOTKEY_LABEL))
+        }
+        Err(e) => Check::warning(
+            NAME,
+            format!("{} couldn't be registered: {}", HOTKEY_LABEL, e),
+            "This is expected if mac-clip is already running; otherwise another app uses the same shortcut",
+        ),
+    }
+}
+
+fn check_storage(profile: Option<&str>) -> Vec<Check> {
+    let dir = match storage::profile_dir(profile) {
+        Ok(dir) => dir,
+        Err(e) => {
+            return vec![Check::failed(
+                "Storage",
+                format!("can't create the data directory: {}", e),
+                "Check the permissions of ~/Library/Application Support",
+            )]
+        }
+    };
+
+    let probe = dir.join(".doctor");
+    let writable = fs::write(&probe, b"").and_then(|()| fs::remove_file(&probe));
+    let storage = match writable {
+        Ok(()) => Check::ok("Storage", format!("{} is writable", dir.display())),
+        Err(e) => Check::failed(
+            "Storage",
+            format!("{} isn't writable: {}", dir.display(), e),
+            format!("Fix the permissions of {}", dir.display()),
+        ),
+    };
+
+    vec![storage, check_history(&storage::history_path(&dir))]
+}
+
+fn check_history(path: &Path) -> Check {
+    const NAME: &str = "History";
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return Check::ok(NAME, "no history saved yet"),
+    };
+    let entries = storage::load_history(path);
+    if entries.is_empty() && size > 2 {
+        return Check::failed(
+            NAME,
+            format!("{} ({} bytes) couldn't be read", path.display(), size),
+            format!(
+                "Move {} aside; mac-clip starts a new history without it",
+                path.display()
+            ),
+        );
+    }
+    Check::ok(
+        NAME,
+        format!(
+            "{} entries, {:.1} KiB ({})",
+            entries.len(),
+            size as f64 / 1024.0,
+            path.display()
+        ),
+    )
+}
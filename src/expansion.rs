@@ -0,0 +1,31 @@
+//! Text expansion: copying an entry's `snippet_trigger`, e.g. "/addr", puts
+//! the entry itself on the clipboard instead.
+
+use crate::ClipboardEntry;
+use std::collections::HashMap;
+
+/// Snippets by their trigger, built from the entries that have one.
+#[derive(Debug, Default)]
+pub struct ExpansionEngine {
+    snippets: HashMap<String, String>,
+}
+
+impl ExpansionEngine {
+    /// `load` gives an entry's full content, for entries kept on disk.
+    pub fn new<'a>(
+        entries: impl IntoIterator<Item = &'a ClipboardEntry>,
+        load: impl Fn(&ClipboardEntry) -> String,
+    ) -> Self {
+        let snippets = entries
+            .into_iter()
+            .filter_map(|entry| Some((entry.snippet_trigger()?.to_string(), load(entry))))
+            .collect();
+        ExpansionEngine { snippets }
+    }
+
+    /// The snippet `content` triggers, if it's exactly a trigger apart from
+    /// surrounding whitespace.
+    pub fn expand(&self, content: &str) -> Option<&str> {
+        self.snippets.get(content.trim()).map(String::as_str)
+    }
+}
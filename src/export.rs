@@ -0,0 +1,32 @@
+//! `--export-plist`: the history as a property list, which Automator and
+//! AppleScript read natively.
+
+use crate::{classify::ContentType, ClipboardEntry};
+use plist::{Dictionary, Value};
+use std::{
+    io,
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// Writes the text entries to `path` as an XML plist: an array of
+/// dictionaries with `content`, `timestamp` (a date) and `tags` keys, in
+/// history order. Entries don't have tags as such, so `tags` holds the
+/// label when there is one. Binary entries are left out, as their content
+/// is only a preview.
+pub fn export_plist(entries: &[ClipboardEntry], path: &Path) -> io::Result<()> {
+    let items = entries
+        .iter()
+        .filter(|entry| entry.content_type != ContentType::Binary)
+        .map(|entry| {
+            let mut item = Dictionary::new();
+            item.insert("content".to_string(), entry.content.clone().into());
+            let time = UNIX_EPOCH + Duration::from_secs(entry.timestamp);
+            item.insert("timestamp".to_string(), plist::Date::from(time).into());
+            let tags = entry.label().map(|label| label.into()).into_iter();
+            item.insert("tags".to_string(), Value::Array(tags.collect()));
+            Value::Dictionary(item)
+        })
+        .collect();
+    plist::to_file_xml(path, &Value::Array(items)).map_err(io::Error::other)
+}
@@ -0,0 +1,34 @@
+//! Actions for entries that hold a file path.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Turns copied text into a path, expanding a leading `~`. Returns `None` if
+/// nothing exists there any more.
+pub fn existing_path(content: &str) -> Option<PathBuf> {
+    let content = content.trim();
+    let path = match content.strip_prefix("~/") {
+        Some(rest) => directories::BaseDirs::new()?.home_dir().join(rest),
+        None if content == "~" => directories::BaseDirs::new()?.home_dir().to_path_buf(),
+        None => PathBuf::from(content),
+    };
+    path.exists().then_some(path)
+}
+
+/// Opens the file with its default app.
+pub fn open(path: &Path) -> io::Result<()> {
+    open::that(path)
+}
+
+/// Shows the file selected in a Finder window.
+pub fn reveal(path: &Path) -> io::Result<()> {
+    let status = Command::new("open").arg("-R").arg(path).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("open -R exited with {}", status)))
+    }
+}
@@ -0,0 +1,244 @@
+//! Checks a new copy goes through before it's stored. Each filter lets the
+//! entry through, drops it with a reason for the log, or changes it for
+//! the filters after it.
+
+use crate::{
+    classify::ContentType,
+    config::{CaptureType, Config, DedupMode},
+    monitor::Selection,
+    storage, ClipboardEntry,
+};
+use regex::Regex;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// The window `max_copies_per_minute` counts copies in.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+pub enum FilterResult {
+    Allow,
+    Deny(String),
+    Transform(Box<ClipboardEntry>),
+}
+
+pub trait Filter {
+    fn apply(&self, entry: &ClipboardEntry) -> FilterResult;
+}
+
+impl<F: Filter + ?Sized> Filter for &F {
+    fn apply(&self, entry: &ClipboardEntry) -> FilterResult {
+        (**self).apply(entry)
+    }
+}
+
+/// Runs an entry through its filters in order, stopping at the first that
+/// denies it.
+#[derive(Default)]
+pub struct FilterPipeline<'a> {
+    filters: Vec<Box<dyn Filter + 'a>>,
+}
+
+impl<'a> FilterPipeline<'a> {
+    pub fn with(mut self, filter: impl Filter + 'a) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// The entry as the last filter left it, or why it was denied.
+    pub fn run(&self, mut entry: ClipboardEntry) -> Result<ClipboardEntry, String> {
+        for filter in &self.filters {
+            match filter.apply(&entry) {
+                FilterResult::Allow => {}
+                FilterResult::Deny(reason) => return Err(reason),
+                FilterResult::Transform(changed) => entry = *changed,
+            }
+        }
+        Ok(entry)
+    }
+}
+
+/// Removes NUL characters, which some apps end the text they copy with, and
+/// drops copies with nothing visible left: empty, or binary data some apps
+/// put on the pasteboard as text.
+pub struct SanitizeFilter;
+
+impl Filter for SanitizeFilter {
+    fn apply(&self, entry: &ClipboardEntry) -> FilterResult {
+        if entry
+            .content
+            .chars()
+            .all(|c| c.is_whitespace() || c.is_control())
+        {
+            return FilterResult::Deny("nothing but whitespace".to_string());
+        }
+        if entry.content.contains('\0') {
+            return FilterResult::Transform(Box::new(ClipboardEntry {
+                content: entry.content.replace('\0', ""),
+                ..entry.clone()
+            }));
+        }
+        FilterResult::Allow
+    }
+}
+
+/// Drops text when `capture_types` leaves it out. The text monitor still
+/// runs for double copies, and the binary one only runs if binary copies
+/// are captured.
+pub struct CaptureTypeFilter<'a> {
+    pub config: &'a Config,
+}
+
+impl Filter for CaptureTypeFilter<'_> {
+    fn apply(&self, entry: &ClipboardEntry) -> FilterResult {
+        // The primary selection has its own setting
+        if entry.content_type != ContentType::Binary
+            && entry.selection == Selection::Clipboard
+            && !self.config.captures(CaptureType::Text)
+        {
+            return FilterResult::Deny("text isn't captured".to_string());
+        }
+        FilterResult::Allow
+    }
+}
+
+/// Drops copies made in one of `ignore_apps`.
+pub struct IgnoredAppFilter<'a> {
+    pub ignore_apps: &'a [String],
+}
+
+impl Filter for IgnoredAppFilter<'_> {
+    fn apply(&self, entry: &ClipboardEntry) -> FilterResult {
+        match &entry.source_app {
+            Some(app) if self.ignore_apps.contains(app) => {
+                FilterResult::Deny(format!("from ignored app {}", app))
+            }
+            _ => FilterResult::Allow,
+        }
+    }
+}
+
+/// Drops text matching one of `exclusion_patterns`.
+pub struct ExclusionPatternFilter<'a> {
+    pub patterns: &'a [Regex],
+}
+
+impl Filter for ExclusionPatternFilter<'_> {
+    fn apply(&self, entry: &ClipboardEntry) -> FilterResult {
+        if entry.content_type != ContentType::Binary
+            && self.patterns.iter().any(|re| re.is_match(&entry.content))
+        {
+            return FilterResult::Deny("matches an exclusion pattern".to_string());
+        }
+        FilterResult::Allow
+    }
+}
+
+/// Drops binary copies over `max_binary_entry_kb`. Text over
+/// `max_entry_bytes` is left to `large_content_policy`, which can ask.
+pub struct MaxSizeFilter {
+    pub max_binary_bytes: usize,
+}
+
+impl Filter for MaxSizeFilter {
+    fn apply(&self, entry: &ClipboardEntry) -> FilterResult {
+        let size: usize = entry.flavors.values().map(Vec::len).sum();
+        if entry.content_type == ContentType::Binary && size > self.max_binary_bytes {
+            return FilterResult::Deny(format!(
+                "{} binary copy larger than max_binary_entry_kb",
+                crate::format_size(size)
+            ));
+        }
+        FilterResult::Allow
+    }
+}
+
+/// Drops text copied again while it's still the newest entry. Duplicates
+/// further down are moved to the top when the entry is stored instead.
+pub struct DuplicateFilter<'a> {
+    pub entries: &'a VecDeque<ClipboardEntry>,
+    pub mode: DedupMode,
+}
+
+impl Filter for DuplicateFilter<'_> {
+    fn apply(&self, entry: &ClipboardEntry) -> FilterResult {
+        if entry.content_type != ContentType::Binary
+            && find_duplicate(self.entries, self.mode, &entry.content) == Some(0)
+        {
+            return FilterResult::Deny("same as the newest entry".to_string());
+        }
+        FilterResult::Allow
+    }
+}
+
+/// Finds an existing entry that counts as a copy of `content` under `mode`.
+pub fn find_duplicate(
+    entries: &VecDeque<ClipboardEntry>,
+    mode: DedupMode,
+    content: &str,
+) -> Option<usize> {
+    let hash = storage::content_hash(content);
+    match mode {
+        DedupMode::Off => None,
+        DedupMode::ExactFront => entries
+            .front()
+            .filter(|e| e.content_hash() == hash)
+            .map(|_| 0),
+        DedupMode::ExactAll => entries.iter().position(|e| e.content_hash() == hash),
+        DedupMode::NormalizedAll => {
+            // Offloaded entries only have a preview in memory, so they're
+            // compared exactly
+            let normalized = normalize_whitespace(content);
+            entries.iter().position(|e| {
+                e.content_hash() == hash
+                    || (e.body.is_none() && normalize_whitespace(&e.content) == normalized)
+            })
+        }
+    }
+}
+
+/// Collapses runs of whitespace and trims the ends, for comparing entries
+/// that differ only in spacing.
+fn normalize_whitespace(content: &str) -> String {
+    content.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Drops copies beyond `max_copies_per_minute`, so an app rewriting the
+/// clipboard in a loop can't flush out the history. Only copies it lets
+/// through count, so it belongs last in a pipeline.
+pub struct RateLimiterFilter {
+    pub max_per_minute: Option<u32>,
+    recent: RefCell<VecDeque<Instant>>,
+}
+
+impl RateLimiterFilter {
+    pub fn new(max_per_minute: Option<u32>) -> Self {
+        RateLimiterFilter {
+            max_per_minute,
+            recent: RefCell::default(),
+        }
+    }
+}
+
+impl Filter for RateLimiterFilter {
+    fn apply(&self, _entry: &ClipboardEntry) -> FilterResult {
+        let Some(max) = self.max_per_minute else {
+            return FilterResult::Allow;
+        };
+        let now = Instant::now();
+        let mut recent = self.recent.borrow_mut();
+        while recent
+            .front()
+            .is_some_and(|&at| now.duration_since(at) >= RATE_WINDOW)
+        {
+            recent.pop_front();
+        }
+        if recent.len() >= max as usize {
+            return FilterResult::Deny(format!("over {} copies a minute", max));
+        }
+        recent.push_back(now);
+        FilterResult::Allow
+    }
+}
@@ -0,0 +1,257 @@
+//! Reading and writing the history file, optionally in a way that copes with
+//! the data directory being synced between machines by iCloud Drive,
+//! Dropbox and the like.
+
+use crate::{config::StorageFormat, storage, ClipboardEntry, EntryId};
+use fs2::FileExt;
+use log::{error, info};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// An encoding of the history on disk.
+pub trait HistoryFormat {
+    fn encode(&self, entries: &VecDeque<ClipboardEntry>) -> io::Result<Vec<u8>>;
+    fn decode(&self, data: &[u8]) -> io::Result<VecDeque<ClipboardEntry>>;
+}
+
+pub struct Json;
+
+impl HistoryFormat for Json {
+    fn encode(&self, entries: &VecDeque<ClipboardEntry>) -> io::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(entries)?)
+    }
+
+    fn decode(&self, data: &[u8]) -> io::Result<VecDeque<ClipboardEntry>> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+/// Fields are stored by name, as in JSON, so files written before a field
+/// was added still load.
+pub struct MessagePack;
+
+impl HistoryFormat for MessagePack {
+    fn encode(&self, entries: &VecDeque<ClipboardEntry>) -> io::Result<Vec<u8>> {
+        rmp_serde::to_vec_named(entries).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn decode(&self, data: &[u8]) -> io::Result<VecDeque<ClipboardEntry>> {
+        rmp_serde::from_slice(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl StorageFormat {
+    pub fn encoder(self) -> &'static dyn HistoryFormat {
+        match self {
+            StorageFormat::Json => &Json,
+            StorageFormat::MessagePack => &MessagePack,
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            StorageFormat::Json => "history.json",
+            StorageFormat::MessagePack => "history.msgpack",
+        }
+    }
+
+    fn other(self) -> StorageFormat {
+        match self {
+            StorageFormat::Json => StorageFormat::MessagePack,
+            StorageFormat::MessagePack => StorageFormat::Json,
+        }
+    }
+}
+
+/// Decodes a history in either format. A JSON history is an array, so
+/// starts with `[`, which MessagePack never does.
+pub fn decode(data: &[u8]) -> io::Result<VecDeque<ClipboardEntry>> {
+    let format = match data.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'[') | None => StorageFormat::Json,
+        Some(_) => StorageFormat::MessagePack,
+    };
+    format.encoder().decode(data)
+}
+
+pub struct HistoryStore {
+    dir: PathBuf,
+    format: StorageFormat,
+    /// Lock around reads and writes, merge in changes written by another
+    /// machine, and replace the file atomically.
+    sync_safe: bool,
+    /// When the file was last modified as of our last read or write, to
+    /// notice it being replaced by a sync.
+    last_modified: Option<SystemTime>,
+}
+
+impl HistoryStore {
+    pub fn new(dir: &Path, format: StorageFormat, sync_safe: bool) -> Self {
+        HistoryStore {
+            dir: dir.to_path_buf(),
+            format,
+            sync_safe,
+            last_modified: None,
+        }
+    }
+
+    /// The history file in the configured format.
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(self.format.file_name())
+    }
+
+    pub fn set_sync_safe(&mut self, sync_safe: bool) {
+        self.sync_safe = sync_safe;
+    }
+
+    /// Takes effect on the next save, which also removes the file in the
+    /// old format.
+    pub fn set_format(&mut self, format: StorageFormat) {
+        self.format = format;
+    }
+
+    /// Reads the history. If there's only a file in the other format, e.g.
+    /// after `storage_format` was changed, it's read and converted.
+    pub fn load(&mut self) -> VecDeque<ClipboardEntry> {
+        let old_path = self.dir.join(self.format.other().file_name());
+        if !self.path().exists() && old_path.exists() {
+            let mut entries = storage::load_history(&old_path);
+            info!(
+                "Converting {} to {}",
+                old_path.display(),
+                self.path().display()
+            );
+            if let Err(e) = self.save(&mut entries) {
+                error!("Failed to convert {}: {}", old_path.display(), e);
+            }
+            return entries;
+        }
+
+        let _lock = self
+            .lock(false)
+            .map_err(|e| error!("Failed to lock the history for reading: {}", e));
+        self.last_modified = self.modified();
+        storage::load_history(&self.path())
+    }
+
+    /// Writes `entries`. In sync-safe mode, entries another machine wrote
+    /// since our last read or write are merged into `entries` first; returns
+    /// whether that happened.
+    pub fn save(&mut self, entries: &mut VecDeque<ClipboardEntry>) -> io::Result<bool> {
+        let path = self.path();
+        let merged = if self.sync_safe {
+            let _lock = self.lock(true)?;
+            let modified = self.modified();
+            let mut merged = false;
+            if modified.is_some() && modified != self.last_modified {
+                let added = merge(entries, storage::load_history(&path));
+                info!("History changed elsewhere, merged in {} entries", added);
+                merged = added > 0;
+            }
+
+            // Sync clients may upload the file at any moment, so never let
+            // them see it half-written
+            let temp = self.dir.join(format!("{}.tmp", self.format.file_name()));
+            storage::save_history(&temp, entries, self.format)?;
+            fs::rename(&temp, &path)?;
+            merged
+        } else {
+            storage::save_history(&path, entries, self.format)?;
+            false
+        };
+        self.last_modified = self.modified();
+
+        let old_path = self.dir.join(self.format.other().file_name());
+        if old_path.exists() {
+            fs::remove_file(&old_path)?;
+        }
+        Ok(merged)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(self.path()).and_then(|m| m.modified()).ok()
+    }
+
+    /// Takes an advisory lock on a file next to the history, if sync-safe
+    /// mode is on. Released when the returned file is dropped.
+    fn lock(&self, exclusive: bool) -> io::Result<Option<File>> {
+        if !self.sync_safe {
+            return Ok(None);
+        }
+        let file = File::create(self.dir.join("history.lock"))?;
+        if exclusive {
+            file.lock_exclusive()?;
+        } else {
+            file.lock_shared()?;
+        }
+        Ok(Some(file))
+    }
+}
+
+/// Like [`merge`], for a history that may have been edited on both sides:
+/// entries are also matched by id, and an entry in both keeps whichever
+/// copy was copied last. Entries whose ids are in `deleted` are dropped
+/// from both, so a deletion on either side sticks. Returns how many entries
+/// were added, replaced or dropped.
+pub fn merge_newer(
+    ours: &mut VecDeque<ClipboardEntry>,
+    theirs: VecDeque<ClipboardEntry>,
+    deleted: &HashMap<EntryId, u64>,
+) -> usize {
+    let before = ours.len();
+    ours.retain(|e| !deleted.contains_key(&e.id));
+    let theirs = theirs.into_iter().filter(|e| !deleted.contains_key(&e.id));
+    let mut by_id: HashMap<EntryId, usize> =
+        ours.iter().enumerate().map(|(i, e)| (e.id, i)).collect();
+    let known: HashSet<(u64, u64)> = ours
+        .iter()
+        .map(|e| (e.content_hash(), e.timestamp))
+        .collect();
+    let mut changed = before - ours.len();
+    for entry in theirs {
+        match by_id.get(&entry.id) {
+            Some(&i) if entry.timestamp > ours[i].timestamp => {
+                ours[i] = entry;
+                changed += 1;
+            }
+            Some(_) => {}
+            None if known.contains(&(entry.content_hash(), entry.timestamp)) => {}
+            None => {
+                by_id.insert(entry.id, ours.len());
+                ours.push_back(entry);
+                changed += 1;
+            }
+        }
+    }
+    if changed > 0 {
+        ours.make_contiguous().sort_by_key(|e| Reverse(e.timestamp));
+    }
+    changed
+}
+
+/// Adds the entries of `theirs` missing from `ours`, matching entries by
+/// content and timestamp, and keeps the history newest first. Entries in
+/// both keep our copy, so the last writer wins on pins, labels and titles.
+/// Returns how many entries were added.
+pub fn merge(ours: &mut VecDeque<ClipboardEntry>, theirs: VecDeque<ClipboardEntry>) -> usize {
+    let known: HashSet<(u64, u64)> = ours
+        .iter()
+        .map(|e| (e.content_hash(), e.timestamp))
+        .collect();
+    let before = ours.len();
+    ours.extend(
+        theirs
+            .into_iter()
+            .filter(|e| !known.contains(&(e.content_hash(), e.timestamp))),
+    );
+    let added = ours.len() - before;
+    if added > 0 {
+        ours.make_contiguous().sort_by_key(|e| Reverse(e.timestamp));
+    }
+    added
+}
@@ -0,0 +1,126 @@
+//! Making sure only one mac-clip watches the clipboard, so a manual launch
+//! next to the LaunchAgent doesn't record every copy twice.
+
+use fs2::FileExt;
+use log::{error, warn};
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+    process, thread,
+    time::Duration,
+};
+
+/// The lock's file name in the runtime directory.
+pub const LOCK_FILE: &str = "mac-clip.lock";
+
+/// How many times, and how far apart, a held lock is read for the PID its
+/// owner may not have written yet.
+const PID_READ_ATTEMPTS: u32 = 5;
+const PID_READ_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Another live mac-clip holds the lock. The PID is missing when the
+    /// lock is held but its owner hasn't written it yet.
+    AlreadyRunning(Option<u32>),
+    Io(io::Error),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::AlreadyRunning(Some(pid)) => {
+                write!(f, "Another instance is running (PID {})", pid)
+            }
+            LockError::AlreadyRunning(None) => write!(f, "Another instance is running"),
+            LockError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<io::Error> for LockError {
+    fn from(e: io::Error) -> Self {
+        LockError::Io(e)
+    }
+}
+
+/// An exclusive lock on `mac-clip.lock`, which holds the owner's PID.
+/// Released and removed when dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Takes the lock at `path` for this process. A lock whose PID is no
+    /// longer running is taken over; the OS normally drops the locks of dead
+    /// processes, but not on every filesystem. A held lock without a PID
+    /// belongs to an instance that's still starting up, so it's left alone.
+    pub fn acquire(path: &Path) -> Result<InstanceLock, LockError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let mut attempt = 1;
+        while file.try_lock_exclusive().is_err() {
+            let mut contents = String::new();
+            file.rewind()?;
+            file.read_to_string(&mut contents)?;
+            match contents.trim().parse::<u32>() {
+                Ok(pid) if is_running(pid) => return Err(LockError::AlreadyRunning(Some(pid))),
+                Ok(pid) => {
+                    warn!("Taking over the lock left by PID {}, which has exited", pid);
+                    break;
+                }
+                // The owner writes its PID right after locking
+                Err(_) if attempt < PID_READ_ATTEMPTS => {
+                    attempt += 1;
+                    thread::sleep(PID_READ_INTERVAL);
+                }
+                Err(_) => return Err(LockError::AlreadyRunning(None)),
+            }
+        }
+
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", process::id())?;
+        file.sync_all()?;
+        Ok(InstanceLock {
+            file,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // Removed before unlocking so a new instance can't lock the old file
+        // and then have it deleted from under it
+        if let Err(e) = fs::remove_file(&self.path) {
+            error!("Failed to remove {}: {}", self.path.display(), e);
+        }
+        let _ = self.file.unlock();
+    }
+}
+
+/// PID of the live process holding the lock at `path`, if any.
+pub fn running_pid(path: &Path) -> Option<u32> {
+    let pid = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    is_running(pid).then_some(pid)
+}
+
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks whether the process exists; EPERM means it does
+    // but belongs to someone else
+    let signalled = unsafe { libc::kill(pid, 0) } == 0;
+    signalled || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
@@ -0,0 +1,598 @@
+//! Small wrappers around the macOS APIs mac-clip uses directly. Every
+//! function has a fallback for other platforms so callers don't need their
+//! own `cfg` checks.
+
+use std::collections::BTreeMap;
+
+#[cfg(target_os = "macos")]
+use cocoa::base::{id, nil, BOOL, NO, YES};
+#[cfg(target_os = "macos")]
+use objc::{class, msg_send, sel, sel_impl};
+
+/// Returns the bundle identifier of the frontmost application.
+#[cfg(target_os = "macos")]
+pub fn frontmost_app_bundle_id() -> Option<String> {
+    unsafe {
+        let pool: id = msg_send![class!(NSAutoreleasePool), new];
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app: id = msg_send![workspace, frontmostApplication];
+        let bundle_id = if app == nil {
+            None
+        } else {
+            let bundle_id: id = msg_send![app, bundleIdentifier];
+            nsstring_to_string(bundle_id)
+        };
+        let _: () = msg_send![pool, drain];
+        bundle_id
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn frontmost_app_bundle_id() -> Option<String> {
+    None
+}
+
+/// Returns the process ID of the frontmost application.
+#[cfg(target_os = "macos")]
+pub fn frontmost_app_pid() -> Option<i32> {
+    unsafe {
+        let pool: id = msg_send![class!(NSAutoreleasePool), new];
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app: id = msg_send![workspace, frontmostApplication];
+        let pid = if app == nil {
+            None
+        } else {
+            let pid: i32 = msg_send![app, processIdentifier];
+            Some(pid)
+        };
+        let _: () = msg_send![pool, drain];
+        pid
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn frontmost_app_pid() -> Option<i32> {
+    None
+}
+
+/// Returns the bundle identifier of the running application with `pid`.
+#[cfg(target_os = "macos")]
+pub fn app_bundle_id(pid: i32) -> Option<String> {
+    unsafe {
+        let pool: id = msg_send![class!(NSAutoreleasePool), new];
+        let app: id = msg_send![
+            class!(NSRunningApplication),
+            runningApplicationWithProcessIdentifier: pid
+        ];
+        let bundle_id = if app == nil {
+            None
+        } else {
+            let bundle_id: id = msg_send![app, bundleIdentifier];
+            nsstring_to_string(bundle_id)
+        };
+        let _: () = msg_send![pool, drain];
+        bundle_id
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn app_bundle_id(_pid: i32) -> Option<String> {
+    None
+}
+
+/// Brings the application with `pid` to the front. Returns `false` if it's no
+/// longer running or refused to activate.
+#[cfg(target_os = "macos")]
+pub fn activate_app(pid: i32) -> bool {
+    // NSApplicationActivateIgnoringOtherApps
+    const ACTIVATE_IGNORING_OTHER_APPS: usize = 1 << 1;
+    unsafe {
+        let pool: id = msg_send![class!(NSAutoreleasePool), new];
+        let app: id = msg_send![
+            class!(NSRunningApplication),
+            runningApplicationWithProcessIdentifier: pid
+        ];
+        let activated = if app == nil {
+            false
+        } else {
+            let activated: BOOL = msg_send![app, activateWithOptions: ACTIVATE_IGNORING_OTHER_APPS];
+            activated == YES
+        };
+        let _: () = msg_send![pool, drain];
+        activated
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn activate_app(_pid: i32) -> bool {
+    false
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventSourceSecondsSinceLastEventType(state_id: i32, event_type: u32) -> f64;
+    fn CGSessionCopyCurrentDictionary() -> id;
+}
+
+/// Seconds since the user last pressed a key or clicked a mouse button.
+#[cfg(target_os = "macos")]
+pub fn seconds_since_user_input() -> Option<f64> {
+    // kCGEventSourceStateCombinedSessionState
+    const COMBINED_SESSION_STATE: i32 = 0;
+    // kCGEventLeftMouseDown, kCGEventRightMouseDown, kCGEventKeyDown
+    const INPUT_EVENT_TYPES: [u32; 3] = [1, 3, 10];
+
+    INPUT_EVENT_TYPES
+        .iter()
+        .map(|&event_type| unsafe {
+            CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, event_type)
+        })
+        .reduce(f64::min)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn seconds_since_user_input() -> Option<f64> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXIsProcessTrusted() -> bool;
+    fn AXIsProcessTrustedWithOptions(options: id) -> bool;
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOHIDCheckAccess(request_type: u32) -> u32;
+}
+
+/// Whether mac-clip may send keystrokes to other apps (System Settings →
+/// Privacy & Security → Accessibility), which pasting needs.
+#[cfg(target_os = "macos")]
+pub fn accessibility_trusted() -> Option<bool> {
+    Some(unsafe { AXIsProcessTrusted() })
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn accessibility_trusted() -> Option<bool> {
+    None
+}
+
+/// Whether Control and Option are both held down, whichever app is
+/// frontmost.
+#[cfg(target_os = "macos")]
+pub fn control_option_held() -> Option<bool> {
+    const CONTROL: u64 = 1 << 18;
+    const OPTION: u64 = 1 << 19;
+    let flags: u64 = unsafe { msg_send![class!(NSEvent), modifierFlags] };
+    Some(flags & (CONTROL | OPTION) == CONTROL | OPTION)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn control_option_held() -> Option<bool> {
+    None
+}
+
+/// Whether Option or Shift is held down, whichever app is frontmost.
+#[cfg(target_os = "macos")]
+pub fn option_or_shift_held() -> Option<bool> {
+    const SHIFT: u64 = 1 << 17;
+    const OPTION: u64 = 1 << 19;
+    let flags: u64 = unsafe { msg_send![class!(NSEvent), modifierFlags] };
+    Some(flags & (SHIFT | OPTION) != 0)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn option_or_shift_held() -> Option<bool> {
+    None
+}
+
+/// The Accessibility list in System Settings → Privacy & Security.
+pub const ACCESSIBILITY_SETTINGS_URL: &str =
+    "x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility";
+
+/// Like [`accessibility_trusted`], but if mac-clip isn't trusted yet, macOS
+/// adds it to the Accessibility list and asks the user to allow it. macOS
+/// only asks once, however often this is called.
+#[cfg(target_os = "macos")]
+pub fn request_accessibility() -> Option<bool> {
+    unsafe {
+        let prompt: id = msg_send![
+            class!(NSString),
+            stringWithUTF8String: c"AXTrustedCheckOptionPrompt".as_ptr()
+        ];
+        let yes: id = msg_send![class!(NSNumber), numberWithBool: YES];
+        let options: id = msg_send![class!(NSDictionary), dictionaryWithObject: yes forKey: prompt];
+        Some(AXIsProcessTrustedWithOptions(options))
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn request_accessibility() -> Option<bool> {
+    None
+}
+
+/// Whether mac-clip may observe keyboard input (Input Monitoring). `None` if
+/// the user hasn't been asked yet.
+#[cfg(target_os = "macos")]
+pub fn input_monitoring_granted() -> Option<bool> {
+    // kIOHIDRequestTypeListenEvent
+    const LISTEN_EVENT: u32 = 1;
+    // kIOHIDAccessTypeGranted, kIOHIDAccessTypeDenied
+    match unsafe { IOHIDCheckAccess(LISTEN_EVENT) } {
+        0 => Some(true),
+        1 => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn input_monitoring_granted() -> Option<bool> {
+    None
+}
+
+/// A screen's usable area (without the menu bar and Dock) in logical points,
+/// measured from the top-left corner of the main screen like window positions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenFrame {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// How far the camera notch reaches into the usable area. Usually 0, as
+    /// the menu bar covers it, unless the menu bar hides itself.
+    pub notch: f64,
+}
+
+/// Returns the usable area of every connected screen, main screen first.
+#[cfg(target_os = "macos")]
+pub fn screen_frames() -> Vec<ScreenFrame> {
+    use cocoa::foundation::NSRect;
+
+    #[repr(C)]
+    struct NSEdgeInsets {
+        top: f64,
+        left: f64,
+        bottom: f64,
+        right: f64,
+    }
+
+    unsafe {
+        let pool: id = msg_send![class!(NSAutoreleasePool), new];
+        let screens: id = msg_send![class!(NSScreen), screens];
+        let count: usize = if screens == nil {
+            0
+        } else {
+            msg_send![screens, count]
+        };
+
+        let mut frames = Vec::with_capacity(count);
+        let mut main_height = 0.0;
+        for i in 0..count {
+            let screen: id = msg_send![screens, objectAtIndex: i];
+            if i == 0 {
+                let frame: NSRect = msg_send![screen, frame];
+                main_height = frame.size.height;
+            }
+            // AppKit measures from the bottom-left of the main screen
+            let visible: NSRect = msg_send![screen, visibleFrame];
+            // safeAreaInsets is macOS 12 and later
+            let responds: BOOL = msg_send![screen, respondsToSelector: sel!(safeAreaInsets)];
+            let notch = if responds == NO {
+                0.0
+            } else {
+                let frame: NSRect = msg_send![screen, frame];
+                let insets: NSEdgeInsets = msg_send![screen, safeAreaInsets];
+                let hidden_top =
+                    (frame.origin.y + frame.size.height) - (visible.origin.y + visible.size.height);
+                (insets.top - hidden_top).max(0.0)
+            };
+            frames.push(ScreenFrame {
+                x: visible.origin.x,
+                y: main_height - (visible.origin.y + visible.size.height),
+                width: visible.size.width,
+                height: visible.size.height,
+                notch,
+            });
+        }
+        let _: () = msg_send![pool, drain];
+        frames
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn screen_frames() -> Vec<ScreenFrame> {
+    Vec::new()
+}
+
+/// Returns where the mouse pointer is, measured like `ScreenFrame`.
+#[cfg(target_os = "macos")]
+pub fn cursor_position() -> Option<(f64, f64)> {
+    use cocoa::foundation::{NSPoint, NSRect};
+
+    unsafe {
+        let pool: id = msg_send![class!(NSAutoreleasePool), new];
+        let location: NSPoint = msg_send![class!(NSEvent), mouseLocation];
+        let screens: id = msg_send![class!(NSScreen), screens];
+        let count: usize = if screens == nil {
+            0
+        } else {
+            msg_send![screens, count]
+        };
+        let position = if count == 0 {
+            None
+        } else {
+            let main: id = msg_send![screens, objectAtIndex: 0usize];
+            let frame: NSRect = msg_send![main, frame];
+            Some((location.x, frame.size.height - location.y))
+        };
+        let _: () = msg_send![pool, drain];
+        position
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn cursor_position() -> Option<(f64, f64)> {
+    None
+}
+
+/// Pasteboard contents keyed by type identifier, e.g. `public.rtf`.
+pub type Flavors = BTreeMap<String, Vec<u8>>;
+
+/// Copies that carry more than this many bytes across all flavors keep only
+/// their plain text.
+#[cfg(target_os = "macos")]
+const MAX_FLAVOR_BYTES: usize = 4 * 1024 * 1024;
+
+/// Reads every representation currently on the general pasteboard.
+#[cfg(target_os = "macos")]
+pub fn read_pasteboard_flavors() -> Flavors {
+    let mut flavors = Flavors::new();
+    unsafe {
+        let pool: id = msg_send![class!(NSAutoreleasePool), new];
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let types: id = msg_send![pasteboard, types];
+        let count: usize = if types == nil {
+            0
+        } else {
+            msg_send![types, count]
+        };
+        for i in 0..count {
+            let flavor: id = msg_send![types, objectAtIndex: i];
+            let data: id = msg_send![pasteboard, dataForType: flavor];
+            let Some(name) = nsstring_to_string(flavor) else {
+                continue;
+            };
+            if data == nil {
+                continue;
+            }
+            let length: usize = msg_send![data, length];
+            let bytes: *const u8 = msg_send![data, bytes];
+            if !bytes.is_null() {
+                flavors.insert(name, std::slice::from_raw_parts(bytes, length).to_vec());
+            }
+        }
+        let _: () = msg_send![pool, drain];
+    }
+
+    if flavors.values().map(Vec::len).sum::<usize>() > MAX_FLAVOR_BYTES {
+        return Flavors::new();
+    }
+    flavors
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn read_pasteboard_flavors() -> Flavors {
+    Flavors::new()
+}
+
+/// Counts changes to the general pasteboard, including copies of the same
+/// text it already holds.
+#[cfg(target_os = "macos")]
+pub fn pasteboard_change_count() -> Option<isize> {
+    unsafe {
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let count: isize = msg_send![pasteboard, changeCount];
+        Some(count)
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn pasteboard_change_count() -> Option<isize> {
+    None
+}
+
+/// Replaces the general pasteboard contents with `flavors`. Returns `false`
+/// if there was nothing to write or any flavor was rejected, in which case
+/// callers should fall back to plain text.
+#[cfg(target_os = "macos")]
+pub fn write_pasteboard_flavors(flavors: &Flavors) -> bool {
+    if flavors.is_empty() {
+        return false;
+    }
+    unsafe {
+        let pool: id = msg_send![class!(NSAutoreleasePool), new];
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let _: isize = msg_send![pasteboard, clearContents];
+        let mut written = true;
+        for (flavor, bytes) in flavors {
+            let Ok(flavor) = std::ffi::CString::new(flavor.as_str()) else {
+                continue;
+            };
+            let flavor: id = msg_send![class!(NSString), stringWithUTF8String: flavor.as_ptr()];
+            let data: id =
+                msg_send![class!(NSData), dataWithBytes: bytes.as_ptr() length: bytes.len()];
+            let ok: BOOL = msg_send![pasteboard, setData: data forType: flavor];
+            written &= ok == YES;
+        }
+        let _: () = msg_send![pool, drain];
+        written
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn write_pasteboard_flavors(_flavors: &Flavors) -> bool {
+    false
+}
+
+/// The macOS version, e.g. "Version 14.5 (Build 23F79)".
+#[cfg(target_os = "macos")]
+pub fn os_version() -> Option<String> {
+    unsafe {
+        let pool: id = msg_send![class!(NSAutoreleasePool), new];
+        let process_info: id = msg_send![class!(NSProcessInfo), processInfo];
+        let version: id = msg_send![process_info, operatingSystemVersionString];
+        let version = nsstring_to_string(version);
+        let _: () = msg_send![pool, drain];
+        version
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn os_version() -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+static WAKE_HANDLER: std::sync::OnceLock<Box<dyn Fn() + Send + Sync>> = std::sync::OnceLock::new();
+
+#[cfg(target_os = "macos")]
+extern "C" fn did_wake(_this: &objc::runtime::Object, _cmd: objc::runtime::Sel, _notification: id) {
+    if let Some(handler) = WAKE_HANDLER.get() {
+        handler();
+    }
+}
+
+/// Calls `on_wake` on the main thread each time the Mac wakes from sleep,
+/// for the rest of the process. Returns `false` if that couldn't be set up,
+/// or was already.
+#[cfg(target_os = "macos")]
+pub fn observe_wake(on_wake: impl Fn() + Send + Sync + 'static) -> bool {
+    if WAKE_HANDLER.set(Box::new(on_wake)).is_err() {
+        return false;
+    }
+    let Some(mut decl) = objc::declare::ClassDecl::new("MacClipWakeObserver", class!(NSObject))
+    else {
+        return false;
+    };
+    unsafe {
+        decl.add_method(
+            sel!(didWake:),
+            did_wake as extern "C" fn(&objc::runtime::Object, objc::runtime::Sel, id),
+        );
+        let observer: id = msg_send![decl.register(), new];
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let center: id = msg_send![workspace, notificationCenter];
+        let name: id = msg_send![
+            class!(NSString),
+            stringWithUTF8String: c"NSWorkspaceDidWakeNotification".as_ptr()
+        ];
+        let _: () = msg_send![
+            center,
+            addObserver: observer
+            selector: sel!(didWake:)
+            name: name
+            object: nil
+        ];
+    }
+    true
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn observe_wake(_on_wake: impl Fn() + Send + Sync + 'static) -> bool {
+    false
+}
+
+/// Whether the screen is being shared, e.g. through Screen Sharing or
+/// remote management, according to the login session.
+#[cfg(target_os = "macos")]
+pub fn screen_being_shared() -> Option<bool> {
+    unsafe {
+        let session = CGSessionCopyCurrentDictionary();
+        if session == nil {
+            return None;
+        }
+        let key: id = msg_send![
+            class!(NSString),
+            stringWithUTF8String: c"CGSSessionScreenIsShared".as_ptr()
+        ];
+        let shared: id = msg_send![session, objectForKey: key];
+        let shared = shared != nil && {
+            let value: BOOL = msg_send![shared, boolValue];
+            value == YES
+        };
+        let _: () = msg_send![session, release];
+        Some(shared)
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn screen_being_shared() -> Option<bool> {
+    None
+}
+
+/// Asks the window server to leave mac-clip's windows out of screenshots,
+/// recordings and shares made by other apps (`NSWindowSharingNone`).
+#[cfg(target_os = "macos")]
+pub fn exclude_windows_from_capture() {
+    const WINDOW_SHARING_NONE: usize = 0;
+    unsafe {
+        let app: id = msg_send![class!(NSApplication), sharedApplication];
+        let windows: id = msg_send![app, windows];
+        let count: usize = msg_send![windows, count];
+        for i in 0..count {
+            let window: id = msg_send![windows, objectAtIndex: i];
+            let _: () = msg_send![window, setSharingType: WINDOW_SHARING_NONE];
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn exclude_windows_from_capture() {}
+
+/// Asks for a file with the standard open panel. Blocks until it's closed,
+/// so call it on the main thread. `None` if it was cancelled.
+#[cfg(target_os = "macos")]
+pub fn pick_file() -> Option<std::path::PathBuf> {
+    const MODAL_RESPONSE_OK: isize = 1;
+    unsafe {
+        let panel: id = msg_send![class!(NSOpenPanel), openPanel];
+        let _: () = msg_send![panel, setCanChooseFiles: YES];
+        let _: () = msg_send![panel, setCanChooseDirectories: NO];
+        let _: () = msg_send![panel, setAllowsMultipleSelection: NO];
+        let response: isize = msg_send![panel, runModal];
+        if response != MODAL_RESPONSE_OK {
+            return None;
+        }
+        let url: id = msg_send![panel, URL];
+        if url == nil {
+            return None;
+        }
+        let path: id = msg_send![url, path];
+        nsstring_to_string(path).map(std::path::PathBuf::from)
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn pick_file() -> Option<std::path::PathBuf> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn nsstring_to_string(string: id) -> Option<String> {
+    if string == nil {
+        return None;
+    }
+    let bytes: *const std::os::raw::c_char = msg_send![string, UTF8String];
+    if bytes.is_null() {
+        return None;
+    }
+    Some(
+        std::ffi::CStr::from_ptr(bytes)
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
@@ -1,113 +1,754 @@
-use arboard::Clipboard;
 use enigo::{Enigo, Key, KeyboardControllable};
 use global_hotkey::{
     hotkey::{Code, HotKey, Modifiers},
     GlobalHotKeyEvent, GlobalHotKeyManager,
 };
 use iced::{
-    alignment, executor,
-    widget::{button, column, container, scrollable, text, Row, Space},
+    alignment, executor, keyboard, subscription,
+    widget::{
+        button, canvas, checkbox, column, container, mouse_area, pick_list, progress_bar, row,
+        scrollable, text, text_input, Row, Space,
+    },
     window::{self, Position},
     Application, Command, Element, Length, Settings, Subscription, Theme,
 };
-use log::{error, info};
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::VecDeque,
+    borrow::Cow,
+    collections::{HashSet, VecDeque},
     env,
-    fs,
-    path::PathBuf,
-    sync::{Arc, Mutex},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, TryLockError,
+    },
     thread,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant},
 };
 use tokio::sync::{mpsc, watch};
 const MAX_HISTORY_SIZE: usize = 50;
+const HOTKEY_LABEL: &str = "Command + Option + V";
+const CYCLE_HOTKEY_LABEL: &str = "Control + Option + V";
+const COLLECTION_HOTKEY_LABEL: &str = "Control + Option + N";
+const FREEZE_HOTKEY_LABEL: &str = "Command + Option + L";
+/// Quick access hotkeys there are keys for: the ten digits, with and
+/// without Shift.
+const MAX_QUICK_ACCESS_SLOTS: usize = 20;
+/// The heights `preview_height` can take, in pixels.
+const PREVIEW_HEIGHTS: RangeInclusive<u32> = 50..=400;
+/// Entries the list needs before the scroll indicator is shown, unless
+/// `show_scroll_indicator` says otherwise.
+const SCROLL_INDICATOR_MIN_ENTRIES: usize = 20;
+/// The digit keys of the quick access hotkeys, in slot order.
+const QUICK_ACCESS_KEYS: [(Code, char); 10] = [
+    (Code::Digit1, '1'),
+    (Code::Digit2, '2'),
+    (Code::Digit3, '3'),
+    (Code::Digit4, '4'),
+    (Code::Digit5, '5'),
+    (Code::Digit6, '6'),
+    (Code::Digit7, '7'),
+    (Code::Digit8, '8'),
+    (Code::Digit9, '9'),
+    (Code::Digit0, '0'),
+];
 const WINDOW_WIDTH: u32 = 400;
 const WINDOW_HEIGHT: u32 = 500;
-const CLIPBOARD_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+pub(crate) const CLIPBOARD_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+const CONFIG_RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const EXPIRY_SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+const TITLE_INPUT_ID: &str = "entry-title";
+const SEARCH_INPUT_ID: &str = "search";
+/// Recent searches offered under an empty search field.
+const SEARCH_HISTORY_SIZE: usize = 10;
+/// Percentage of `max_search_length` past which the search counter turns
+/// red.
+const SEARCH_LENGTH_WARNING_PERCENT: usize = 80;
+/// Time for a reactivated app to take focus before the paste keystroke.
+const REFOCUS_DELAY: Duration = Duration::from_millis(50);
+const REPLAY_PASTE_DELAY: Duration = Duration::from_millis(100);
+/// How soon after a key press or click a clipboard change still counts as
+/// user initiated. Includes the monitor's polling delay.
+const USER_CHANGE_WINDOW: Duration =
+    Duration::from_millis(100 + CLIPBOARD_CHECK_INTERVAL.as_millis() as u64);
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+/// How often the window moves after the pointer with `cursor_follow`.
+const CURSOR_FOLLOW_INTERVAL: Duration = Duration::from_millis(16);
+/// Shown instead of an entry's content while `capture_masked`.
+const MASKED_PREVIEW: &str = "••••••••";
+/// Entries added per step of a text file import, between progress updates.
+const IMPORT_BATCH_SIZE: usize = 100;
+/// How quickly the same thing must be copied again for `double_copy_capture`.
+const DOUBLE_COPY_INTERVAL: Duration = Duration::from_millis(400);
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+const GC_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Retries for registering the hotkey again after a wake.
+const HOTKEY_REGISTER_ATTEMPTS: u32 = 5;
+const HOTKEY_REGISTER_RETRY_DELAY: Duration = Duration::from_secs(2);
+/// How often to check whether the `hold_to_cycle` modifiers are released.
+const CYCLE_RELEASE_CHECK_INTERVAL: Duration = Duration::from_millis(30);
+/// How often to check whether queued telemetry is due to be sent.
+const TELEMETRY_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
+mod archive;
+mod auth;
+mod binary;
+mod bodies;
+mod bundle;
+mod classify;
+mod cli;
+mod clipboard;
+mod collections;
+mod completions;
+mod config;
 mod daemon;
+mod diagnostics;
+mod diff;
+mod doctor;
+mod expansion;
+mod export;
+mod files;
+mod filter;
+mod history;
+mod instance;
+mod language;
+mod macos;
+mod markdown;
+mod monitor;
+mod pasta;
+mod paste;
+mod qr;
+mod query;
+mod search_index;
+mod sessions;
+mod settings;
+mod share;
+mod stats;
+mod storage;
+mod style;
+mod telemetry;
+#[cfg(test)]
+mod tests;
+mod transform;
+mod tray;
+mod webdav;
+mod widgets;
+mod window_state;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use auth::{PinLock, PinOutcome};
+use bodies::BodyStore;
+use classify::{Classifier, ContentType};
+use clipboard::SharedClipboard;
+use collections::{Collection, CollectionStore};
+use config::{
+    CaptureType, Config, DefaultAction, LargeContentPolicy, NewlineDisplay, PasteBehavior,
+    RowDetails, ScreenCaptureGuard, ViewMode,
+};
+use diff::Change;
+use expansion::ExpansionEngine;
+use filter::{
+    CaptureTypeFilter, DuplicateFilter, ExclusionPatternFilter, FilterPipeline, IgnoredAppFilter,
+    MaxSizeFilter, RateLimiterFilter, SanitizeFilter,
+};
+use history::HistoryStore;
+use instance::InstanceLock;
+use macos::Flavors;
+use monitor::{HeartbeatChange, HeartbeatMonitor, Selection, WatchdogThread};
+use search_index::SearchIndex;
+use sessions::{Session, SessionStore};
+use settings::AgeLimit;
+use telemetry::TelemetryRecorder;
+use transform::RegexTransforms;
+use tray::{Tray, TrayAction};
+use uuid::Uuid;
+use webdav::{RemoteHistory, Tombstones, WebDavSync};
+use window_state::WindowPosition;
+
+/// Identifies an entry independently of its position in the history, which
+/// shifts as entries are added and removed. `Default` generates a new one,
+/// so entries saved before ids existed get one when they're loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+struct EntryId(Uuid);
+
+impl Default for EntryId {
+    fn default() -> Self {
+        EntryId(Uuid::new_v4())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct ClipboardEntry {
+    #[serde(default)]
+    id: EntryId,
     content: String,
     timestamp: u64,
+    #[serde(default)]
+    pinned: bool,
+    /// Bundle identifier of the app that was frontmost when this was copied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_app: Option<String>,
+    /// Sensitive entries are purged once this Unix timestamp passes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    /// Every pasteboard representation captured with the copy, restored on
+    /// paste so apps get rich text or HTML instead of just `content`.
+    #[serde(
+        default,
+        skip_serializing_if = "Flavors::is_empty",
+        with = "storage::flavors_base64"
+    )]
+    flavors: Flavors,
+    #[serde(default, skip_serializing_if = "Selection::is_clipboard")]
+    selection: Selection,
+    /// Set when the full content lives in the bodies directory (low-memory
+    /// mode); `content` then only holds a preview.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<u64>,
+    #[serde(default)]
+    content_type: ContentType,
+    /// A short description set by the user, e.g. "API key for staging".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    /// Shown instead of the content preview when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    /// For binary entries, the MIME type of the data `content` previews.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mime_type: Option<String>,
+    /// Copying this text, e.g. "/addr", puts the entry on the clipboard
+    /// instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snippet_trigger: Option<String>,
+    /// ISO 639-1 code of the language the text is in, with
+    /// `detect_language`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+}
+
+impl ClipboardEntry {
+    /// Hash of the full content, even when only a preview is in memory.
+    fn content_hash(&self) -> u64 {
+        self.body
+            .unwrap_or_else(|| storage::content_hash(&self.content))
+    }
+
+    /// The label with surrounding whitespace removed, if there's anything
+    /// left of it.
+    fn label(&self) -> Option<&str> {
+        self.label
+            .as_deref()
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+    }
+
+    /// The snippet trigger without surrounding whitespace, if there's
+    /// anything left of it.
+    fn snippet_trigger(&self) -> Option<&str> {
+        self.snippet_trigger
+            .as_deref()
+            .map(str::trim)
+            .filter(|trigger| !trigger.is_empty())
+    }
+
+    /// The text searches are matched against: the content plus the title
+    /// and label.
+    fn search_text(&self) -> Cow<'_, str> {
+        match (self.title.as_deref(), self.label()) {
+            (None, None) => Cow::Borrowed(&self.content),
+            (title, label) => Cow::Owned(
+                [title, label, Some(self.content.as_str())]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     ClipboardUpdated(String),
-    SelectEntry(usize),
+    SelectEntry(EntryId),
     HotkeyPressed,
     EventReceived(Event),
     ToggleWindow,
+    OpenFullView(EntryId),
+    CloseFullView,
+    ToggleMarkdown,
+    CopyEntry(EntryId),
+    CopyJsonEscaped(EntryId),
+    /// Copies the whole entry as pretty-printed JSON, for logs and scripts.
+    CopyEntryAsJson(EntryId),
+    /// Shows the entry as a QR code and saves it as a PNG.
+    ShowQrCode(EntryId),
+    CloseQrCode,
+    TogglePin(EntryId),
+    DeleteEntry(EntryId),
+    MoveSelection(isize),
+    ActivateSelection,
+    DeleteSelected,
+    CopyTimestamp(EntryId),
+    /// Copies the timestamp of the entry in the full view, or else the
+    /// highlighted one.
+    CopySelectedTimestamp,
+    SearchChanged(String),
+    /// Searches for one of the recent searches again.
+    SearchHistorySelected(String),
+    ClearSearchHistory,
+    /// The search grew past `SEARCH_LENGTH_WARNING_PERCENT` of its maximum.
+    SearchLengthWarning,
+    /// Shows another `display_limit` entries of the list.
+    ShowMoreEntries,
+    HistoryScrolled(scrollable::Viewport),
+    /// The divider above the preview pane was dragged to make the pane this
+    /// many pixels high.
+    PreviewResized(f32),
+    /// The divider was let go, so the new height can be saved.
+    PreviewResizeFinished,
+    CopyAllVisible,
+    ToggleExpiry(EntryId),
+    SweepExpired,
+    PinDigitEntered(char),
+    PinBackspace,
+    PinSubmit,
+    /// Whether the submitted PIN matched, checked off the UI thread.
+    PinChecked(bool),
+    ShowTab(Tab),
+    /// Shows the history with the first entry in a size histogram bucket
+    /// highlighted.
+    JumpToSizeBucket(usize),
+    SaveSession,
+    ReplaySession(usize),
+    SessionReplayed,
+    CollectionNameChanged(String),
+    /// Saves the ticked entries, in the order they were ticked, as a
+    /// collection.
+    SaveCollection,
+    /// Makes the collection hotkey paste this collection's entries from the
+    /// first, or stops it if the collection is already being pasted.
+    ToggleCollection(usize),
+    DeleteCollection(usize),
+    CollectionItemPasted,
+    QuickAccessPasted,
+    Shutdown,
+    RunGc,
+    InitTray,
+    OpenFilePath(String),
+    RevealInFinder(String),
+    /// Opens a new message to an email entry in the mail app.
+    ComposeEmail(EntryId),
+    /// Copies the domain of an email entry.
+    CopyEmailDomain(EntryId),
+    DismissToast(u64),
+    DismissSpontaneousChange,
+    OpenAccessibilitySettings,
+    DismissAccessibilityNotice,
+    WindowMoved(i32, i32),
+    /// Moves the window a step after the mouse pointer.
+    FollowCursor,
+    ModifiersChanged(keyboard::Modifiers),
+    ReloadConfig,
+    SetLabel(EntryId, String),
+    SetSnippetTrigger(EntryId, String),
+    PreviewClicked(EntryId),
+    TitleDraftChanged(String),
+    SetEntryTitle(EntryId, String),
+    ShareEntry(EntryId),
+    EntryShared(Result<String, String>),
+    ToggleChecked(EntryId),
+    CompareEntries(EntryId, EntryId),
+    ResolveLargeCopy(LargeContentPolicy),
+    SetAgeLimit(AgeLimit),
+    CustomAgeLimitChanged(String),
+    SetRowDetails(RowDetails),
+    /// Switches the history between the list and the grid.
+    ToggleViewMode,
+    /// Freezes the history, or unfreezes it and adds the copies made
+    /// meanwhile.
+    ToggleFreeze,
+    /// Asks for a text file to import.
+    PickImportFile,
+    ImportFileSelected(PathBuf),
+    /// Entries imported so far and in total.
+    ImportProgress(usize, usize),
+    /// How many entries were imported.
+    ImportComplete(usize),
+    /// Registers the hotkey, again after a wake or late after a startup
+    /// delay; the number of earlier failed attempts.
+    ReregisterHotkey(u32),
+    /// The `hold_to_cycle` modifiers were let go of.
+    CycleReleased,
+    SendTelemetry,
+    TelemetrySent(Result<usize, String>),
+    /// Fetches the history from the WebDAV server, then uploads the merged
+    /// history if anything changed locally.
+    SyncWebDav,
+    WebDavDownloaded(Result<Option<RemoteHistory>, String>),
+    WebDavUploaded(Result<(), String>),
+}
+
+impl Message {
+    /// Whether this shows, pastes or changes entries, which the PIN lock
+    /// screen has to stop. The list's buttons are hidden behind it, but keys,
+    /// hotkeys and the menu bar icon still send these.
+    fn uses_history(&self) -> bool {
+        matches!(
+            self,
+            Message::SelectEntry(_)
+                | Message::OpenFullView(_)
+                | Message::CopyEntry(_)
+                | Message::CopyJsonEscaped(_)
+                | Message::CopyEntryAsJson(_)
+                | Message::ShowQrCode(_)
+                | Message::TogglePin(_)
+                | Message::DeleteEntry(_)
+                | Message::MoveSelection(_)
+                | Message::ActivateSelection
+                | Message::DeleteSelected
+                | Message::CopyTimestamp(_)
+                | Message::CopySelectedTimestamp
+                | Message::CopyAllVisible
+                | Message::ToggleExpiry(_)
+                | Message::JumpToSizeBucket(_)
+                | Message::SaveSession
+                | Message::ReplaySession(_)
+                | Message::SaveCollection
+                | Message::ToggleCollection(_)
+                | Message::DeleteCollection(_)
+                | Message::OpenFilePath(_)
+                | Message::RevealInFinder(_)
+                | Message::ComposeEmail(_)
+                | Message::CopyEmailDomain(_)
+                | Message::SetLabel(..)
+                | Message::SetSnippetTrigger(..)
+                | Message::PreviewClicked(_)
+                | Message::SetEntryTitle(..)
+                | Message::ShareEntry(_)
+                | Message::ToggleChecked(_)
+                | Message::CompareEntries(..)
+                | Message::PickImportFile
+                | Message::ImportFileSelected(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    History,
+    Sessions,
+    Stats,
+    Settings,
 }
 
 #[derive(Debug, Clone)]
 enum Event {
-    ClipboardChanged(String),
+    ClipboardChanged {
+        content: String,
+        source_app: Option<String>,
+        flavors: Flavors,
+        selection: Selection,
+        /// False if the user hadn't typed or clicked just before the change,
+        /// which may mean another app replaced the clipboard behind their
+        /// back.
+        user_initiated: bool,
+        /// Copied twice in quick succession, which records it regardless of
+        /// `capture_types`, `ignore_apps` and `exclusion_patterns`.
+        double_copied: bool,
+    },
     HotkeyTriggered,
+    CycleHotkeyTriggered,
+    /// Something without text was copied.
+    BinaryCopied {
+        flavors: Flavors,
+        source_app: Option<String>,
+    },
+    CollectionHotkeyTriggered,
+    /// The quick access hotkey of the entry at this history index.
+    QuickAccessTriggered(usize),
+    FreezeHotkeyTriggered,
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    TrayAction(TrayAction),
+    /// The Mac woke from sleep.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    Woke,
+}
+
+/// A text file being imported from the Settings tab, a batch at a time so
+/// the window can show progress.
+struct TextImport {
+    /// Entries still to add, last in the file first, so adding each to the
+    /// front leaves them in the file's order.
+    pending: Vec<String>,
+    done: usize,
+    total: usize,
+    /// Entries added or moved to the front so far. Excluded ones, and
+    /// repeats of the newest entry, aren't.
+    imported: usize,
+}
+
+/// A clipboard change held back until the user decides what to do with it.
+struct LargeCopy {
+    content: String,
+    source_app: Option<String>,
+    flavors: Flavors,
+    selection: Selection,
 }
 
 struct MacClip {
     entries: VecDeque<ClipboardEntry>,
-    clipboard: Arc<Mutex<Clipboard>>,
-    storage_path: PathBuf,
-    hotkey_manager: Arc<GlobalHotKeyManager>,
+    /// Lowercased search text of `entries`, updated along with them.
+    search_index: SearchIndex,
+    clipboard: SharedClipboard,
+    history: HistoryStore,
+    config_path: PathBuf,
+    config: Config,
+    exclusions: Vec<regex::Regex>,
+    sensitive: Vec<regex::Regex>,
+    classifier: Classifier,
+    /// Counts recent copies for `max_copies_per_minute`.
+    rate_limiter: RateLimiterFilter,
+    regex_transforms: RegexTransforms,
+    /// Kept alive so the hotkey stays registered; `None` when built without
+    /// the event loop's background threads.
+    hotkey_manager: Option<Arc<GlobalHotKeyManager>>,
+    /// The quick access hotkeys registered, to unregister them when
+    /// `quick_access_slots` changes.
+    quick_access_hotkeys: Vec<HotKey>,
+    /// The history is kept as it is for reference: new copies wait in
+    /// `pending_entries` and nothing is trimmed or aged out.
+    freeze_history: bool,
+    /// Copies made while the history is frozen, newest first.
+    pending_entries: VecDeque<ClipboardEntry>,
+    /// How far down the history is scrolled, from 0 to 1.
+    scroll_progress: f32,
     event_rx: watch::Receiver<Option<Event>>,
     tx: mpsc::UnboundedSender<Event>,
     last_clipboard_content: String,
     window_visible: bool,
+    /// Process ID of the app that was frontmost when the hotkey opened the
+    /// window.
+    previous_app: Option<i32>,
+    full_view: Option<usize>,
+    search_query: String,
+    /// This session's last searches, newest first.
+    search_history: VecDeque<String>,
+    /// Which of `search_history` the search field shows while going through
+    /// it with the arrow keys.
+    search_history_position: Option<usize>,
+    /// Rows added past `display_limit` with "Show more", until the search
+    /// changes.
+    shown_beyond_limit: usize,
+    /// History index of the entry highlighted by keyboard navigation.
+    selected: Option<usize>,
+    /// History indices of the entries ticked in the list, in the order they
+    /// were ticked.
+    checked: Vec<usize>,
+    /// The two entries shown side by side, and the diff between them.
+    comparison_view: Option<(usize, usize)>,
+    comparison: Vec<Change>,
+    /// The entry shown as a QR code, over everything else.
+    qr_code: Option<qr::QrView>,
+    tab: Tab,
+    pin_lock: PinLock,
+    /// Whether the PIN has been entered since the window was last shown.
+    unlocked: bool,
+    pin_message: Option<String>,
+    body_store: BodyStore,
+    /// Full content of the entry in the full view, loaded from disk if the
+    /// entry was offloaded.
+    full_view_entry: Option<ClipboardEntry>,
+    /// Whether a label or snippet trigger was edited in the full view and
+    /// not saved yet.
+    label_edited: bool,
+    /// The snippets of entries with a `snippet_trigger`, rebuilt whenever the
+    /// history is saved.
+    expansions: ExpansionEngine,
+    /// The entry whose title is being edited, with the text typed so far.
+    title_edit: Option<(usize, String)>,
+    /// The last click on the full view's content, for spotting double-clicks.
+    last_preview_click: Option<(usize, Instant)>,
+    /// Whether the full view shows Markdown rendered rather than raw.
+    markdown_rendered: bool,
+    session_store: SessionStore,
+    sessions: Vec<Session>,
+    collection_store: CollectionStore,
+    collections: Vec<Collection>,
+    /// Name typed for the next collection.
+    collection_name: String,
+    /// The collection the collection hotkey is pasting from and the index of
+    /// the entry it pastes next.
+    active_collection: Option<(usize, usize)>,
+    shutdown_requested: Arc<AtomicBool>,
+    /// Set by SIGHUP to reload `config.json`.
+    reload_requested: Arc<AtomicBool>,
+    /// Menu bar icon, created once the event loop is running.
+    tray: Option<Tray>,
+    /// Short-lived message shown in the window, with an id so an old
+    /// dismissal doesn't hide a newer message.
+    toast: Option<(u64, String)>,
+    next_toast_id: u64,
+    /// When the clipboard last changed without the user typing or clicking,
+    /// shown as a warning until dismissed.
+    spontaneous_change: Option<u64>,
+    /// Pasting needs the Accessibility permission, which mac-clip doesn't
+    /// have; shown as setup guidance until granted or dismissed.
+    accessibility_missing: bool,
+    /// Cycling through the list with `hold_to_cycle`; the highlighted entry
+    /// is pasted once the modifiers are released.
+    cycling: bool,
+    /// Where `cursor_follow` has moved the window to, unrounded so slow
+    /// steps add up.
+    follow_position: Option<(f64, f64)>,
+    /// Held modifier keys; Option makes selecting an entry paste it
+    /// whatever `default_action_by_kind` says.
+    modifiers: keyboard::Modifiers,
+    /// Entry contents are hidden because the window was opened while the
+    /// screen was being shared, with `screen_capture_guard` set to `mask`.
+    capture_masked: bool,
+    /// A copy over `max_entry_bytes` waiting for the user to say whether to
+    /// keep it.
+    large_copy: Option<LargeCopy>,
+    /// The Settings tab's `max_age_days` choice, and the number typed for a
+    /// custom one, which only reaches the config once it's valid.
+    age_limit: AgeLimit,
+    custom_age_limit: String,
+    text_import: Option<TextImport>,
+    window_state_path: PathBuf,
+    window_position: Option<WindowPosition>,
+    archive_dir: PathBuf,
+    telemetry: TelemetryRecorder,
+    webdav: Option<WebDavSync>,
+    /// The history changed since it was last uploaded to WebDAV.
+    webdav_pending: bool,
+    /// Entries deleted here or on another machine, uploaded with the
+    /// history so a sync doesn't bring them back.
+    tombstones: Tombstones,
+    tombstones_path: PathBuf,
+    /// The ids in the history when it was last saved, to tell which
+    /// entries have been deleted since.
+    saved_ids: HashSet<EntryId>,
+    /// Held for as long as the app runs; `None` in tests.
+    instance_lock: Option<InstanceLock>,
+}
+
+#[derive(Default)]
+struct Flags {
+    /// Held for as long as the app runs.
+    instance_lock: Option<InstanceLock>,
+    /// From `--profile`; `None` is the default profile.
+    profile: Option<String>,
 }
 
 impl Application for MacClip {
     type Message = Message;
     type Theme = Theme;
     type Executor = executor::Default;
-    type Flags = ();
+    type Flags = Flags;
 
-    fn new(_flags: ()) -> (Self, Command<Message>) {
+    fn new(flags: Flags) -> (Self, Command<Message>) {
         env_logger::init();
         info!("Initializing Mac-Clip");
 
-        let storage_dir = directories::ProjectDirs::from("com", "mac-clip", "mac-clip")
-            .expect("Failed to get project directory")
-            .data_dir()
-            .to_path_buf();
-
-        fs::create_dir_all(&storage_dir).expect("Failed to create storage directory");
-        let storage_path = storage_dir.join("history.json");
+        let profile = flags.profile.as_deref();
+        let data_dir = storage::data_dir().expect("Failed to create storage directory");
+        let storage_dir =
+            storage::profile_dir(profile).expect("Failed to create profile directory");
+        let config_path = storage::config_path(&data_dir, profile);
+        let clipboard = clipboard::system().expect("Failed to initialize clipboard");
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = watch::channel(None);
+        let mut app = MacClip::with_storage(&storage_dir, config_path, clipboard, tx, event_rx);
+        app.instance_lock = flags.instance_lock;
 
-        let entries = if storage_path.exists() {
-            info!("Loading clipboard history from {}", storage_path.display());
-            let data = fs::read_to_string(&storage_path).expect("Failed to read history file");
-            serde_json::from_str(&data).unwrap_or_else(|_| VecDeque::new())
-        } else {
-            info!("No existing clipboard history found");
-            VecDeque::new()
-        };
+        // Ask for the permission pasting needs up front, rather than have
+        // the first paste silently do nothing
+        if app.config.paste_behavior == PasteBehavior::PasteAutomatically
+            && macos::request_accessibility() == Some(false)
+        {
+            info!("Asked for the Accessibility permission");
+            app.accessibility_missing = true;
+        }
 
-        let clipboard = Arc::new(Mutex::new(
-            Clipboard::new().expect("Failed to initialize clipboard"),
-        ));
         let hotkey_manager =
             Arc::new(GlobalHotKeyManager::new().expect("Failed to initialize hotkey manager"));
 
-        let hotkey = HotKey::new(Some(Modifiers::META | Modifiers::ALT), Code::KeyV);
-        hotkey_manager
-            .register(hotkey)
-            .expect("Failed to register hotkey");
-        info!("Registered global hotkey: Command + Option + V");
+        let hotkey_delay = Duration::from_millis(app.config.startup_hotkey_delay_ms);
+        if hotkey_delay.is_zero() {
+            hotkey_manager
+                .register(history_hotkey())
+                .expect("Failed to register hotkey");
+            info!("Registered global hotkey: {}", HOTKEY_LABEL);
+            if app.config.hold_to_cycle {
+                register_cycle_hotkey(&hotkey_manager);
+            }
+            register_freeze_hotkey(&hotkey_manager);
+        } else {
+            info!("Registering the global hotkey in {:?}", hotkey_delay);
+        }
+        app.hotkey_manager = Some(hotkey_manager);
+        if hotkey_delay.is_zero() {
+            app.register_quick_access_hotkeys();
+        }
+
+        let tx_wake = app.tx.clone();
+        if macos::observe_wake(move || {
+            let _ = tx_wake.send(Event::Woke);
+        }) {
+            info!("Watching for wake from sleep");
+        }
 
-        let (tx, mut rx) = mpsc::unbounded_channel();
-        let (event_tx, event_rx) = watch::channel(None);
-        let tx_clone = tx.clone();
+        // Flag the app for a clean shutdown when launchd stops the daemon
+        if let Err(e) = signal_hook::flag::register(
+            signal_hook::consts::SIGTERM,
+            Arc::clone(&app.shutdown_requested),
+        ) {
+            error!("Failed to install SIGTERM handler: {}", e);
+        }
+        if let Err(e) = signal_hook::flag::register(
+            signal_hook::consts::SIGHUP,
+            Arc::clone(&app.reload_requested),
+        ) {
+            error!("Failed to install SIGHUP handler: {}", e);
+        }
 
-        // Event processor thread
+        let tx_clone = app.tx.clone();
+
+        // Event processor thread, which also checks on the clipboard monitor
         let event_tx_clone = event_tx.clone();
+        let clipboard_heartbeat = HeartbeatMonitor::default();
+        let mut heartbeat = clipboard_heartbeat.clone();
+        let clipboard_clone = Arc::clone(&app.clipboard);
         thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.spawn(async move {
+                loop {
+                    tokio::time::sleep(monitor::HEARTBEAT_INTERVAL).await;
+                    match heartbeat.check() {
+                        Some(HeartbeatChange::Stalled) => {
+                            let lock_held =
+                                matches!(clipboard_clone.try_lock(), Err(TryLockError::WouldBlock));
+                            error!(
+                                "The clipboard monitor hasn't polled for {}s{}",
+                                monitor::HEARTBEAT_INTERVAL.as_secs(),
+                                if lock_held {
+                                    " and the clipboard lock is held, so it's probably deadlocked"
+                                } else {
+                                    ""
+                                }
+                            );
+                        }
+                        Some(HeartbeatChange::Recovered) => {
+                            info!("The clipboard monitor is polling again");
+                        }
+                        None => {}
+                    }
+                }
+            });
             rt.block_on(async {
                 while let Some(event) = rx.recv().await {
                     let _ = event_tx_clone.send(Some(event));
@@ -118,52 +759,134 @@ impl Application for MacClip {
         // Hotkey listener thread
         std::thread::spawn(move || {
             info!("Starting hotkey listener thread");
+            let cycle_id = cycle_hotkey().id();
+            let collection_id = collection_hotkey().id();
+            let freeze_id = freeze_hotkey().id();
+            let quick_access_ids: Vec<u32> = (0..MAX_QUICK_ACCESS_SLOTS)
+                .map(|slot| quick_access_hotkey(slot).id())
+                .collect();
             for event in GlobalHotKeyEvent::receiver() {
                 if let global_hotkey::HotKeyState::Pressed = event.state {
-                    info!("Hotkey pressed");
-                    let _ = tx_clone.send(Event::HotkeyTriggered);
-                }
-            }
-        });
-
-        // Clipboard monitor thread
-        let clipboard_clone = Arc::clone(&clipboard);
-        let tx_clipboard = tx.clone();
-        thread::spawn(move || {
-            info!("Starting clipboard monitor thread");
-            let mut last_content = String::new();
-            loop {
-                thread::sleep(CLIPBOARD_CHECK_INTERVAL);
-
-                if let Ok(mut clipboard) = clipboard_clone.lock() {
-                    if let Ok(content) = clipboard.get_text() {
-                        if !content.is_empty() && content != last_content {
-                            info!("Detected clipboard change: {}", content);
-                            last_content = content.clone();
-                            let _ = tx_clipboard.send(Event::ClipboardChanged(content));
-                        }
+                    if event.id == cycle_id {
+                        let _ = tx_clone.send(Event::CycleHotkeyTriggered);
+                    } else if event.id == collection_id {
+                        let _ = tx_clone.send(Event::CollectionHotkeyTriggered);
+                    } else if event.id == freeze_id {
+                        let _ = tx_clone.send(Event::FreezeHotkeyTriggered);
+                    } else if let Some(slot) =
+                        quick_access_ids.iter().position(|&id| id == event.id)
+                    {
+                        let _ = tx_clone.send(Event::QuickAccessTriggered(slot));
+                    } else {
+                        info!("Hotkey pressed");
+                        let _ = tx_clone.send(Event::HotkeyTriggered);
                     }
                 }
             }
         });
 
-        let last_clipboard_content = clipboard.lock().unwrap().get_text().unwrap_or_default();
+        // Monitors wait for anything left on the pasteboard from before a
+        // restart to be out of the way
+        let monitor_delay = Duration::from_millis(app.config.startup_monitor_delay_ms);
 
-        info!("Initial clipboard content: {}", last_clipboard_content);
+        // Clipboard monitor thread, also needed to spot double copies when
+        // text isn't captured otherwise
+        if app.config.captures(CaptureType::Text) || app.config.double_copy_capture {
+            let clipboard_clone = Arc::clone(&app.clipboard);
+            let tx_clipboard = app.tx.clone();
+            let settle = Duration::from_millis(app.config.clipboard_settle_ms);
+            let double_copy = app
+                .config
+                .double_copy_capture
+                .then_some(DOUBLE_COPY_INTERVAL);
+            thread::spawn(move || {
+                thread::sleep(monitor_delay);
+                info!("Starting clipboard monitor thread");
+                // Restarted if reading the clipboard ever panics
+                WatchdogThread::new("clipboard monitor").run(move || {
+                    monitor::watch_clipboard(
+                        Arc::clone(&clipboard_clone),
+                        CLIPBOARD_CHECK_INTERVAL,
+                        settle,
+                        double_copy,
+                        &clipboard_heartbeat,
+                        |content, double_copied| {
+                            let source_app = macos::frontmost_app_bundle_id();
+                            let flavors = macos::read_pasteboard_flavors();
+                            let user_initiated =
+                                macos::seconds_since_user_input().is_none_or(|secs| {
+                                    secs <= (USER_CHANGE_WINDOW + settle).as_secs_f64()
+                                });
+                            let _ = tx_clipboard.send(Event::ClipboardChanged {
+                                content,
+                                source_app,
+                                flavors,
+                                selection: Selection::Clipboard,
+                                user_initiated,
+                                double_copied,
+                            });
+                        },
+                    );
+                });
+            });
+        }
 
-        (
-            MacClip {
-                entries,
-                clipboard,
-                storage_path,
-                hotkey_manager,
-                event_rx,
-                tx,
-                last_clipboard_content,
-                window_visible: false,
-            },
-            Command::none(),
-        )
+        if app.config.captures(CaptureType::Binary) {
+            let tx_binary = app.tx.clone();
+            thread::spawn(move || {
+                thread::sleep(monitor_delay);
+                info!("Starting binary clipboard monitor thread");
+                monitor::watch_binary(CLIPBOARD_CHECK_INTERVAL, |flavors| {
+                    let _ = tx_binary.send(Event::BinaryCopied {
+                        flavors,
+                        source_app: macos::frontmost_app_bundle_id(),
+                    });
+                });
+            });
+        }
+
+        #[cfg(target_os = "linux")]
+        if app.config.monitor_primary_selection {
+            let tx_primary = app.tx.clone();
+            thread::spawn(move || {
+                thread::sleep(monitor_delay);
+                info!("Starting primary selection monitor thread");
+                let result =
+                    monitor::watch_primary_selection(CLIPBOARD_CHECK_INTERVAL, |content| {
+                        let _ = tx_primary.send(Event::ClipboardChanged {
+                            content,
+                            source_app: None,
+                            flavors: Flavors::new(),
+                            selection: Selection::Primary,
+                            user_initiated: true,
+                            double_copied: false,
+                        });
+                    });
+                if let Err(e) = result {
+                    error!("Primary selection monitor stopped: {}", e);
+                }
+            });
+        }
+
+        if app.config.tray_entries > 0 {
+            let tx_tray = app.tx.clone();
+            thread::spawn(move || {
+                info!("Starting menu bar listener thread");
+                tray::listen(tx_tray);
+            });
+        }
+
+        // The menu bar icon can only be added once the event loop is running
+        let mut commands = vec![
+            Command::perform(async {}, |_| Message::InitTray),
+            Command::perform(async {}, |_| Message::SyncWebDav),
+        ];
+        if !hotkey_delay.is_zero() {
+            commands.push(Command::perform(tokio::time::sleep(hotkey_delay), |_| {
+                Message::ReregisterHotkey(0)
+            }));
+        }
+        (app, Command::batch(commands))
     }
 
     fn title(&self) -> String {
@@ -171,43 +894,67 @@ impl Application for MacClip {
     }
 
     fn update(&mut self, message: Message) -> Command<Message> {
+        if self.pin_required() {
+            match message {
+                // The keys that work the list work the keypad instead
+                Message::ActivateSelection => return self.update(Message::PinSubmit),
+                Message::DeleteSelected => return self.update(Message::PinBackspace),
+                ref message if message.uses_history() => return Command::none(),
+                _ => {}
+            }
+        }
         match message {
             Message::EventReceived(event) => {
                 match event {
-                    Event::ClipboardChanged(content) => {
-                        info!("Processing clipboard change");
-                        if content.trim().is_empty() {
-                            return Command::none();
+                    Event::ClipboardChanged {
+                        content,
+                        source_app,
+                        flavors,
+                        selection,
+                        user_initiated,
+                        double_copied,
+                    } => {
+                        return self.handle_clipboard_event(
+                            content,
+                            source_app,
+                            flavors,
+                            selection,
+                            user_initiated,
+                            double_copied,
+                        );
+                    }
+                    Event::TrayAction(TrayAction::OpenWindow) => {
+                        if !self.window_visible {
+                            self.window_visible = true;
+                            return Command::perform(async {}, |_| Message::ToggleWindow);
                         }
-
-                        let timestamp = SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs();
-
-                        if self.entries.front().map(|e| &e.content) != Some(&content) {
-                            let entry = ClipboardEntry {
-                                content: content.clone(),
-                                timestamp,
-                            };
-
-                            self.entries.push_front(entry);
-                            if self.entries.len() > MAX_HISTORY_SIZE {
-                                self.entries.pop_back();
-                            }
-
-                            if let Ok(json) = serde_json::to_string(&self.entries) {
-                                if let Err(e) = fs::write(&self.storage_path, json) {
-                                    error!("Failed to save history: {}", e);
-                                }
-                            }
+                    }
+                    Event::TrayAction(TrayAction::Paste(hash)) => {
+                        if let Some(entry) = self.entries.iter().find(|e| e.content_hash() == hash)
+                        {
+                            return self.update(Message::SelectEntry(entry.id));
                         }
                     }
+                    Event::Woke => {
+                        info!("Woke from sleep, registering the hotkey again");
+                        return self.update(Message::ReregisterHotkey(0));
+                    }
                     Event::HotkeyTriggered => {
                         info!("Processing hotkey event");
+                        if !self.window_visible && !self.prepare_to_open() {
+                            return Command::none();
+                        }
                         self.window_visible = !self.window_visible;
                         return Command::perform(async {}, |_| Message::ToggleWindow);
                     }
+                    Event::CycleHotkeyTriggered => return self.cycle_selection(),
+                    Event::BinaryCopied {
+                        flavors,
+                        source_app,
+                    } => self.record_binary_copy(flavors, source_app),
+                    Event::CollectionHotkeyTriggered => return self.paste_next_in_collection(),
+                    Event::QuickAccessTriggered(slot) => return self.paste_quick_access(slot),
+                    Event::FreezeHotkeyTriggered => return self.update(Message::ToggleFreeze),
                 }
                 Command::none()
             }
@@ -217,25 +964,74 @@ impl Application for MacClip {
                 }
                 Command::none()
             }
-            Message::SelectEntry(index) => {
+            Message::SelectEntry(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                self.track("select_entry");
                 info!("Selected entry at index {}", index);
-                if let Some(entry) = self.entries.get(index) {
-                    let content = entry.content.clone();
+                self.remember_search();
+                let entry = &self.entries[index];
+                let action = match self.config.default_action_by_kind.get(&entry.content_type) {
+                    Some(&action) if !self.modifiers.alt() => action,
+                    _ => DefaultAction::Paste,
+                };
+                match action {
+                    DefaultAction::Paste => {}
+                    DefaultAction::Open if entry.content_type == ContentType::FilePath => {
+                        return self.update(Message::OpenFilePath(entry.content.clone()));
+                    }
+                    DefaultAction::Open => {
+                        let target = entry.content.trim().to_string();
+                        if let Err(e) = open::that(&target) {
+                            error!("Failed to open {}: {}", target, e);
+                            return self.show_toast(format!("Couldn't open {}", target));
+                        }
+                        return Command::none();
+                    }
+                    DefaultAction::Reveal => {
+                        return self.update(Message::RevealInFinder(entry.content.clone()));
+                    }
+                }
+                if self.config.paste_behavior == PasteBehavior::CopyAndStayOpen {
+                    if !self.copy_entry_to_clipboard(index) {
+                        return Command::none();
+                    }
+                    info!("Set clipboard content from history");
+                    return self.show_toast("Copied".to_string());
+                }
+
+                if index < self.entries.len() {
                     self.window_visible = false;
+                    self.full_view = None;
 
                     // First update the clipboard content
-                    if let Ok(mut clipboard) = self.clipboard.lock() {
-                        if let Err(e) = clipboard.set_text(&content) {
-                            error!("Failed to set clipboard content: {}", e);
-                        } else {
-                            info!("Set clipboard content from history");
-                            self.last_clipboard_content = content.clone();
-
-                            // Then simulate Command+V to paste
-                            let mut enigo = Enigo::new();
-                            enigo.key_down(Key::Meta);
-                            enigo.key_click(Key::Layout('v'));
-                            enigo.key_up(Key::Meta);
+                    if self.copy_entry_to_clipboard(index) {
+                        info!("Set clipboard content from history");
+
+                        // Make sure the paste lands in the app the history was opened from
+                        if let Some(pid) = self
+                            .previous_app
+                            .filter(|_| self.config.refocus_previous_app)
+                        {
+                            if macos::activate_app(pid) {
+                                thread::sleep(REFOCUS_DELAY);
+                            }
+                        }
+
+                        if self.config.smart_paste || !self.regex_transforms.is_empty() {
+                            self.apply_paste_transforms(index);
+                        }
+
+                        // Then simulate Command+V to paste
+                        if self.config.paste_behavior == PasteBehavior::PasteAutomatically {
+                            if macos::accessibility_trusted() == Some(false) {
+                                // The keystroke would be dropped without a word
+                                warn!("Can't paste without the Accessibility permission");
+                                self.accessibility_missing = true;
+                            } else {
+                                simulate_paste();
+                            }
                         }
                     }
                 }
@@ -245,123 +1041,3906 @@ impl Application for MacClip {
                 self.window_visible = !self.window_visible;
                 Command::perform(async {}, |_| Message::ToggleWindow)
             }
-            Message::ToggleWindow => {
-                if !self.window_visible {
-                    Command::batch(vec![
-                        window::change_mode(window::Mode::Hidden),
-                    ])
-                } else {
-                    Command::batch(vec![
-                        window::change_mode(window::Mode::Windowed),
-                        window::gain_focus(),
-                    ])
+            Message::OpenFullView(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                if self.capture_masked {
+                    return Command::none();
                 }
+                self.track("open_full_view");
+                if let Some(entry) = self.entries.get(index) {
+                    self.full_view_entry = Some(self.body_store.load(entry));
+                    self.full_view = Some(index);
+                    self.markdown_rendered = self.config.render_markdown;
+                }
+                Command::none()
             }
-        }
-    }
-
-    fn view(&self) -> Element<Message> {
-        if !self.window_visible {
-            return container(Space::new(Length::Fill, Length::Fill))
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .into();
-        }
-
-        info!("Rendering window with {} entries", self.entries.len());
-        let mut content = column![].spacing(5).padding(10);
-
-        content = content.push(
-            text("Clipboard History")
-                .size(18)
-                .width(Length::Fill)
-                .horizontal_alignment(alignment::Horizontal::Center),
-        );
-
-        content = content.push(Space::new(Length::Fill, Length::Fixed(5.0)));
-
-        if self.entries.is_empty() {
-            content = content.push(
-                container(
-                    text("No clipboard history yet. Copy some text!")
-                        .width(Length::Fill)
-                        .size(14)
-                        .horizontal_alignment(alignment::Horizontal::Center),
+            Message::ToggleMarkdown => {
+                self.markdown_rendered = !self.markdown_rendered;
+                Command::none()
+            }
+            Message::CloseFullView => {
+                if self.qr_code.take().is_some() {
+                    return Command::none();
+                }
+                // Escape cancels an unfinished title first
+                if self.title_edit.take().is_some() {
+                    return Command::none();
+                }
+                if self.comparison_view.take().is_some() {
+                    self.comparison.clear();
+                    return Command::none();
+                }
+                self.full_view = None;
+                if self.label_edited {
+                    self.label_edited = false;
+                    self.save_history();
+                }
+                Command::none()
+            }
+            Message::PreviewClicked(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                let now = Instant::now();
+                let double_click = self.last_preview_click.is_some_and(|(last, at)| {
+                    last == index && now.duration_since(at) <= DOUBLE_CLICK_INTERVAL
+                });
+                self.last_preview_click = Some((index, now));
+                if double_click {
+                    if let Some(entry) = self.entries.get(index) {
+                        self.title_edit = Some((index, entry.title.clone().unwrap_or_default()));
+                        return text_input::focus(text_input::Id::new(TITLE_INPUT_ID));
+                    }
+                }
+                Command::none()
+            }
+            Message::TitleDraftChanged(title) => {
+                if let Some((_, draft)) = self.title_edit.as_mut() {
+                    *draft = title;
+                }
+                Command::none()
+            }
+            Message::SetEntryTitle(id, title) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                self.track("set_title");
+                self.title_edit = None;
+                if let Some(entry) = self.entries.get_mut(index) {
+                    let title = title.trim();
+                    entry.title = (!title.is_empty()).then(|| title.to_string());
+                    self.search_index.update(index, entry);
+                    self.save_history();
+                }
+                Command::none()
+            }
+            Message::ShareEntry(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                self.track("share_entry");
+                let Some(entry) = self.full_entry(index) else {
+                    return Command::none();
+                };
+                let config = self.config.clone();
+                Command::perform(
+                    async move {
+                        share::share(&config, &entry.content)
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    Message::EntryShared,
                 )
-                .padding(10)
-                .style(iced::theme::Container::Box),
-            );
-        } else {
-            for (i, entry) in self.entries.iter().enumerate() {
-                let entry_text = if entry.content.len() > 50 {
-                    format!("{}...", &entry.content[..50].replace('\n', "↵"))
+            }
+            Message::EntryShared(Ok(url)) => {
+                info!("Shared entry at {}", url);
+                if self.copy_to_clipboard(url) {
+                    self.show_toast("Link copied to the clipboard".to_string())
                 } else {
-                    entry.content.replace('\n', "↵")
+                    self.show_toast("Shared, but the link couldn't be copied".to_string())
+                }
+            }
+            Message::EntryShared(Err(e)) => {
+                error!("Failed to share entry: {}", e);
+                self.show_toast(format!("Couldn't share: {}", e))
+            }
+            Message::SetLabel(id, label) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
                 };
-
-                let entry_row = Row::new().push(
-                    button(
-                        text(&entry_text)
-                            .size(12)
-                            .horizontal_alignment(alignment::Horizontal::Left),
-                    )
-                    .width(Length::Fill)
-                    .padding(8)
-                    .style(iced::theme::Button::Secondary)
-                    .on_press(Message::SelectEntry(i)),
-                );
-
-                content = content.push(entry_row);
+                if let Some(entry) = self.entries.get_mut(index) {
+                    // Kept as typed so spaces between words survive; trimmed
+                    // when shown
+                    entry.label = (!label.trim().is_empty()).then_some(label);
+                    self.search_index.update(index, entry);
+                    self.label_edited = true;
+                }
+                Command::none()
             }
-        }
-
-        container(scrollable(content))
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .center_x()
-            .center_y()
-            .padding(10)
-            .style(iced::theme::Container::Box)
-            .into()
-    }
-
-    fn subscription(&self) -> Subscription<Message> {
-        struct EventReceiver;
-
-        let rx = self.event_rx.clone();
-        iced::subscription::unfold(
-            std::any::TypeId::of::<EventReceiver>(),
-            rx,
-            move |mut rx| async move {
-                if let Ok(Some(event)) = rx.changed().await.map(|_| rx.borrow().clone()) {
-                    (Message::EventReceived(event), rx)
-                } else {
-                    (Message::HotkeyPressed, rx) // Dummy message that won't be used
+            Message::SetSnippetTrigger(id, trigger) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                self.entries[index].snippet_trigger =
+                    (!trigger.trim().is_empty()).then_some(trigger);
+                self.label_edited = true;
+                Command::none()
+            }
+            Message::ToggleChecked(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                match self.checked.iter().position(|&i| i == index) {
+                    Some(position) => {
+                        self.checked.remove(position);
+                    }
+                    None if index < self.entries.len() => self.checked.push(index),
+                    None => {}
                 }
-            },
-        )
-    }
-}
-
-fn main() -> iced::Result {
-    env_logger::init();
-
-    // Check if --daemon flag is provided
-    if env::args().any(|arg| arg == "--daemon") {
-        if let Err(e) = daemon::setup_daemon() {
-            eprintln!("Failed to setup daemon: {}", e);
-        } else {
-            println!("Mac-Clip daemon setup complete. The application will now start automatically when you log in.");
-            return Ok(());
-        }
-    }
-
-    MacClip::run(Settings {
-        window: window::Settings {
-            size: (WINDOW_WIDTH, WINDOW_HEIGHT),
-            position: Position::Centered,
-            visible: false,
-            ..window::Settings::default()
+                Command::none()
+            }
+            Message::CompareEntries(a, b) => {
+                if self.capture_masked {
+                    return self.show_toast("Hidden entries can't be compared".to_string());
+                }
+                let (Some(a), Some(b)) = (self.index_of(a), self.index_of(b)) else {
+                    return Command::none();
+                };
+                self.track("compare_entries");
+                if let (Some(old), Some(new)) = (self.full_entry(a), self.full_entry(b)) {
+                    self.comparison = diff::diff_lines(&old.content, &new.content);
+                    self.comparison_view = Some((a, b));
+                }
+                Command::none()
+            }
+            Message::CopyEntry(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                if self.copy_entry_to_clipboard(index) {
+                    info!("Copied entry {} to clipboard", index);
+                }
+                Command::none()
+            }
+            Message::CopyJsonEscaped(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                self.track("copy_json_escaped");
+                if let Some(entry) = self.full_entry(index) {
+                    let Ok(quoted) = serde_json::to_string(&entry.content) else {
+                        return Command::none();
+                    };
+                    let escaped = if self.config.json_escape_wrap_quotes {
+                        quoted
+                    } else {
+                        quoted[1..quoted.len() - 1].to_string()
+                    };
+                    if self.copy_to_clipboard(escaped) {
+                        info!("Copied entry {} as a JSON string", index);
+                    }
+                }
+                Command::none()
+            }
+            Message::CopyEntryAsJson(id) => {
+                let Some(mut entry) = self.index_of(id).and_then(|index| self.full_entry(index))
+                else {
+                    return Command::none();
+                };
+                self.track("copy_entry_as_json");
+                // The pasteboard flavors only matter for pasting, and would
+                // bury the rest in base64
+                entry.flavors.clear();
+                let json = match serde_json::to_string_pretty(&entry) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        error!("Failed to serialize entry: {}", e);
+                        return Command::none();
+                    }
+                };
+                if self.copy_to_clipboard(json) {
+                    return self.show_toast("Copied as JSON".to_string());
+                }
+                Command::none()
+            }
+            Message::ShowQrCode(id) => {
+                let Some(entry) = self.index_of(id).and_then(|index| self.full_entry(index)) else {
+                    return Command::none();
+                };
+                self.track("show_qr_code");
+                let Some(code) = qr::encode(&entry.content) else {
+                    return self.show_toast("Too long for a QR code".to_string());
+                };
+                let path = env::temp_dir().join(format!("mac-clip-qr-{}.png", entry.id.0));
+                let saved = qr::save_png(&code, &path);
+                self.qr_code = Some(qr::QrView { code });
+                match saved {
+                    Ok(()) => {
+                        self.copy_to_clipboard(path.display().to_string());
+                        self.show_toast("Image path copied to the clipboard".to_string())
+                    }
+                    Err(e) => {
+                        error!("Failed to save QR code to {}: {}", path.display(), e);
+                        Command::none()
+                    }
+                }
+            }
+            Message::CloseQrCode => {
+                self.qr_code = None;
+                Command::none()
+            }
+            Message::TogglePin(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                self.track("toggle_pin");
+                if let Some(entry) = self.entries.get_mut(index) {
+                    entry.pinned = !entry.pinned;
+                    if entry.pinned {
+                        entry.expires_at = None;
+                    }
+                    info!("Entry {} pinned: {}", index, entry.pinned);
+                    self.trim_history();
+                    self.full_view = self.full_view.filter(|&i| i < self.entries.len());
+                    self.selected = self.selected.filter(|&i| i < self.entries.len());
+                    let len = self.entries.len();
+                    self.remap_checked(|i| Some(i).filter(|&i| i < len));
+                    self.save_history();
+                }
+                Command::none()
+            }
+            Message::DeleteEntry(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                self.track("delete_entry");
+                self.delete_entry(index);
+                Command::none()
+            }
+            Message::MoveSelection(delta) => {
+                // Like a shell, Up in an empty search field goes back through
+                // the recent searches
+                if self.selected.is_none()
+                    && ((delta < 0 && self.search_query.is_empty())
+                        || self.search_history_position.is_some())
+                    && !self.search_history.is_empty()
+                {
+                    self.recall_search(delta);
+                    return Command::none();
+                }
+                let visible: Vec<usize> = self.shown_entries().iter().map(|(i, _)| *i).collect();
+                if visible.is_empty() {
+                    self.selected = None;
+                    return Command::none();
+                }
+                let position = match self
+                    .selected
+                    .and_then(|s| visible.iter().position(|&i| i == s))
+                {
+                    Some(position) => position.saturating_add_signed(delta).min(visible.len() - 1),
+                    None if delta < 0 => visible.len() - 1,
+                    None => 0,
+                };
+                self.selected = Some(visible[position]);
+                Command::none()
+            }
+            Message::ActivateSelection => {
+                // Enter keeps a recalled search, so the arrows move through
+                // its results again
+                if self.selected.is_none() && self.search_history_position.take().is_some() {
+                    return Command::none();
+                }
+                match self.selected.and_then(|index| self.entries.get(index)) {
+                    Some(entry) => self.update(Message::SelectEntry(entry.id)),
+                    None => Command::none(),
+                }
+            }
+            Message::DeleteSelected => {
+                if let Some(index) = self.selected {
+                    self.delete_entry(index);
+                }
+                Command::none()
+            }
+            Message::CopyTimestamp(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                let Some(timestamp) = iso_timestamp(self.entries[index].timestamp) else {
+                    return Command::none();
+                };
+                if self.copy_to_clipboard(timestamp) {
+                    info!("Copied the timestamp of entry {}", index);
+                    return self.show_toast("Timestamp copied".to_string());
+                }
+                Command::none()
+            }
+            Message::CopySelectedTimestamp => {
+                match self
+                    .full_view
+                    .or(self.selected)
+                    .and_then(|i| self.entries.get(i))
+                {
+                    Some(entry) => self.update(Message::CopyTimestamp(entry.id)),
+                    None => Command::none(),
+                }
+            }
+            Message::SearchChanged(mut query) => {
+                if self.search_query.is_empty() && !query.is_empty() {
+                    self.track("search");
+                }
+                if let Some((end, _)) = query.char_indices().nth(self.config.max_search_length) {
+                    query.truncate(end);
+                }
+                let crossed_warning = !self.search_length_warning()
+                    && query.chars().count() > self.search_length_warning_at();
+                self.search_query = query;
+                self.search_history_position = None;
+                self.shown_beyond_limit = 0;
+                self.selected = None;
+                if crossed_warning {
+                    return self.update(Message::SearchLengthWarning);
+                }
+                Command::none()
+            }
+            Message::SearchHistorySelected(query) => {
+                self.track("search_history_selected");
+                Command::batch(vec![
+                    self.update(Message::SearchChanged(query)),
+                    text_input::focus(text_input::Id::new(SEARCH_INPUT_ID)),
+                ])
+            }
+            Message::ClearSearchHistory => {
+                self.search_history.clear();
+                self.search_history_position = None;
+                Command::none()
+            }
+            Message::SearchLengthWarning => {
+                let max = self.config.max_search_length;
+                info!("Search is nearing its limit of {} characters", max);
+                self.show_toast(format!("Searches stop at {} characters", max))
+            }
+            Message::HistoryScrolled(viewport) => {
+                // NaN when everything fits
+                let progress = viewport.relative_offset().y;
+                self.scroll_progress = if progress.is_finite() {
+                    progress.clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                Command::none()
+            }
+            Message::PreviewResized(height) => {
+                let (min, max) = (*PREVIEW_HEIGHTS.start(), *PREVIEW_HEIGHTS.end());
+                self.config.preview_height = (height.round() as u32).clamp(min, max);
+                Command::none()
+            }
+            Message::PreviewResizeFinished => {
+                info!("Preview pane resized to {}px", self.config.preview_height);
+                self.save_config();
+                Command::none()
+            }
+            Message::ShowMoreEntries => {
+                self.shown_beyond_limit += self.config.display_limit.unwrap_or(0).max(1);
+                Command::none()
+            }
+            Message::CopyAllVisible => {
+                self.track("copy_all_visible");
+                let combined = self
+                    .visible_entries()
+                    .iter()
+                    .map(|(_, entry)| self.body_store.load(entry).content)
+                    .collect::<Vec<_>>()
+                    .join(&self.config.multi_copy_separator);
+                if self.copy_to_clipboard(combined) {
+                    info!("Copied all visible entries to clipboard");
+                }
+                Command::none()
+            }
+            Message::ToggleExpiry(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                let ttl = self.config.sensitive_ttl_secs;
+                if let Some(entry) = self.entries.get_mut(index) {
+                    entry.expires_at = match entry.expires_at {
+                        Some(_) => None,
+                        None => Some(storage::unix_now() + ttl),
+                    };
+                    entry.pinned &= entry.expires_at.is_none();
+                    self.save_history();
+                }
+                Command::none()
+            }
+            Message::SweepExpired => {
+                let removed = storage::remove_expired(&mut self.entries, storage::unix_now());
+                if removed > 0 {
+                    info!("Removed {} expired entries", removed);
+                    self.search_index = SearchIndex::new(&self.entries);
+                    self.full_view = None;
+                    self.selected = None;
+                    self.remap_checked(|_| None);
+                    self.save_history();
+                }
+                Command::none()
+            }
+            Message::PinDigitEntered(digit) => {
+                if !self.pin_required() {
+                    return Command::none();
+                }
+                let outcome = self.pin_lock.push_digit(digit);
+                self.apply_pin_outcome(outcome)
+            }
+            Message::PinBackspace => {
+                self.pin_lock.backspace();
+                Command::none()
+            }
+            Message::PinSubmit => {
+                if !self.pin_required() {
+                    return Command::none();
+                }
+                let outcome = self.pin_lock.submit();
+                self.apply_pin_outcome(outcome)
+            }
+            Message::PinChecked(matched) => {
+                let outcome = self.pin_lock.finish_check(matched);
+                self.apply_pin_outcome(outcome)
+            }
+            Message::ShowTab(tab) => {
+                self.tab = tab;
+                Command::none()
+            }
+            Message::JumpToSizeBucket(bucket) => {
+                let visible = self.visible_entries();
+                let Some(position) = visible.iter().position(|(_, entry)| {
+                    stats::size_bucket(entry.content.chars().count()) == bucket
+                }) else {
+                    return Command::none();
+                };
+                let (index, shown) = (visible[position].0, self.shown_count(visible.len()));
+                self.selected = Some(index);
+                if let Some(limit) = self.config.display_limit.filter(|_| position >= shown) {
+                    self.shown_beyond_limit = position + 1 - limit;
+                }
+                self.tab = Tab::History;
+                Command::none()
+            }
+            Message::SetAgeLimit(limit) => {
+                self.age_limit = limit;
+                match limit {
+                    AgeLimit::Never => self.set_max_age_days(None),
+                    AgeLimit::Days(days) => self.set_max_age_days(Some(days)),
+                    AgeLimit::Custom => {
+                        if let Some(days) = parse_days(&self.custom_age_limit) {
+                            self.set_max_age_days(Some(days));
+                        }
+                    }
+                }
+                Command::none()
+            }
+            Message::CustomAgeLimitChanged(value) => {
+                if let Some(days) = parse_days(&value) {
+                    self.set_max_age_days(Some(days));
+                }
+                self.custom_age_limit = value;
+                Command::none()
+            }
+            Message::ReregisterHotkey(attempt) => {
+                // The registration can silently stop working over a sleep,
+                // so replace it rather than trusting it
+                self.register_quick_access_hotkeys();
+                let Some(manager) = &self.hotkey_manager else {
+                    return Command::none();
+                };
+                if self.config.hold_to_cycle {
+                    register_cycle_hotkey(manager);
+                }
+                register_freeze_hotkey(manager);
+                let hotkey = history_hotkey();
+                let _ = manager.unregister(hotkey);
+                match manager.register(hotkey) {
+                    Ok(()) => info!("Registered global hotkey again: {}", HOTKEY_LABEL),
+                    // The combo can be unavailable for a moment while the
+                    // system wakes up
+                    Err(e) if attempt < HOTKEY_REGISTER_ATTEMPTS => {
+                        warn!("Couldn't register {} yet ({}), retrying", HOTKEY_LABEL, e);
+                        return Command::perform(
+                            tokio::time::sleep(HOTKEY_REGISTER_RETRY_DELAY),
+                            move |_| Message::ReregisterHotkey(attempt + 1),
+                        );
+                    }
+                    Err(e) => error!("Failed to register {}: {}", HOTKEY_LABEL, e),
+                }
+                Command::none()
+            }
+            Message::CycleReleased => {
+                if !std::mem::take(&mut self.cycling) || !self.window_visible || self.pin_required()
+                {
+                    return Command::none();
+                }
+                self.update(Message::ActivateSelection)
+            }
+            Message::SendTelemetry => {
+                let Some(url) = self
+                    .config
+                    .telemetry_url
+                    .clone()
+                    .filter(|_| self.config.telemetry_opt_in)
+                else {
+                    return Command::none();
+                };
+                match self.telemetry.due_batch(storage::unix_now()) {
+                    Ok(Some(batch)) => Command::perform(
+                        async move {
+                            telemetry::send(&url, &batch)
+                                .await
+                                .map_err(|e| e.to_string())
+                        },
+                        Message::TelemetrySent,
+                    ),
+                    Ok(None) => Command::none(),
+                    Err(e) => {
+                        error!("Failed to read telemetry queue: {}", e);
+                        Command::none()
+                    }
+                }
+            }
+            Message::TelemetrySent(Ok(count)) => {
+                if let Err(e) = self.telemetry.remove_sent(count) {
+                    error!("Failed to remove sent telemetry events: {}", e);
+                }
+                Command::none()
+            }
+            Message::TelemetrySent(Err(e)) => {
+                // Kept for the next attempt
+                warn!("Failed to send telemetry: {}", e);
+                Command::none()
+            }
+            Message::SyncWebDav => {
+                let Some(sync) = self.webdav.clone() else {
+                    return Command::none();
+                };
+                // Always merge in what other machines uploaded first, so an
+                // upload never drops their entries
+                Command::perform(
+                    async move { sync.download().await.map_err(|e| e.to_string()) },
+                    Message::WebDavDownloaded,
+                )
+            }
+            Message::WebDavDownloaded(Ok(remote)) => {
+                if let Some(remote) = remote {
+                    webdav::merge_tombstones(&mut self.tombstones, remote.deleted);
+                    self.save_tombstones();
+                    let changed =
+                        history::merge_newer(&mut self.entries, remote.entries, &self.tombstones);
+                    if changed > 0 {
+                        info!("Merged {} entries from WebDAV", changed);
+                        if self.config.low_memory_mode {
+                            self.body_store.offload_all(&mut self.entries);
+                        }
+                        self.search_index = SearchIndex::new(&self.entries);
+                        self.full_view = None;
+                        self.selected = None;
+                        self.remap_checked(|_| None);
+                        self.trim_history();
+                        self.save_history();
+                    }
+                }
+                let Some(sync) = self.webdav.clone().filter(|_| self.webdav_pending) else {
+                    return Command::none();
+                };
+                self.webdav_pending = false;
+                let remote = RemoteHistory {
+                    entries: self
+                        .entries
+                        .iter()
+                        .map(|entry| self.body_store.load(entry))
+                        .collect(),
+                    deleted: self.tombstones.clone(),
+                };
+                Command::perform(
+                    async move { sync.upload(&remote).await.map_err(|e| e.to_string()) },
+                    Message::WebDavUploaded,
+                )
+            }
+            Message::WebDavDownloaded(Err(e)) => {
+                warn!("Failed to download the history from WebDAV: {}", e);
+                Command::none()
+            }
+            Message::WebDavUploaded(Ok(())) => Command::none(),
+            Message::WebDavUploaded(Err(e)) => {
+                // Tried again on the next sync
+                warn!("Failed to upload the history to WebDAV: {}", e);
+                self.webdav_pending = true;
+                Command::none()
+            }
+            Message::SetRowDetails(details) => {
+                self.config.row_details = details;
+                self.save_config();
+                Command::none()
+            }
+            Message::ToggleViewMode => {
+                self.track("toggle_view_mode");
+                self.config.view_mode = match self.config.view_mode {
+                    ViewMode::List => ViewMode::Grid,
+                    ViewMode::Grid => ViewMode::List,
+                };
+                self.save_config();
+                Command::none()
+            }
+            Message::ToggleFreeze => {
+                if !self.freeze_history {
+                    info!("Freezing the history");
+                    self.track("freeze_history");
+                    self.freeze_history = true;
+                    return self.show_toast(format!(
+                        "History frozen, {} unfreezes it",
+                        FREEZE_HOTKEY_LABEL
+                    ));
+                }
+                match self.unfreeze() {
+                    0 => self.show_toast("History unfrozen".to_string()),
+                    1 => self.show_toast("History unfrozen, added 1 new copy".to_string()),
+                    added => {
+                        self.show_toast(format!("History unfrozen, added {} new copies", added))
+                    }
+                }
+            }
+            Message::PickImportFile => match macos::pick_file() {
+                Some(path) => self.update(Message::ImportFileSelected(path)),
+                None => Command::none(),
+            },
+            Message::ImportFileSelected(path) => {
+                if self.text_import.is_some() {
+                    return Command::none();
+                }
+                let text = match std::fs::read_to_string(&path) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        error!("Failed to read {}: {}", path.display(), e);
+                        return self.show_toast(format!("Couldn't read {}", path.display()));
+                    }
+                };
+                self.track("import_text_file");
+                let mut pending: Vec<String> = text
+                    .split(self.config.import_delimiter.as_str())
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                pending.reverse();
+                let total = pending.len();
+                info!("Importing {} entries from {}", total, path.display());
+                self.text_import = Some(TextImport {
+                    pending,
+                    done: 0,
+                    total,
+                    imported: 0,
+                });
+                Command::perform(async {}, move |_| Message::ImportProgress(0, total))
+            }
+            Message::ImportProgress(done, total) => {
+                let Some(import) = &mut self.text_import else {
+                    return Command::none();
+                };
+                import.done = done;
+                let batch: Vec<String> = import
+                    .pending
+                    .drain(..IMPORT_BATCH_SIZE.min(import.pending.len()))
+                    .collect();
+                let done = done + batch.len();
+
+                let timestamp = storage::unix_now();
+                let mut imported = 0;
+                for content in batch {
+                    if self.exclusions.iter().any(|re| re.is_match(&content)) {
+                        continue;
+                    }
+                    if self.insert_copy(
+                        content,
+                        None,
+                        Flavors::new(),
+                        Selection::Clipboard,
+                        timestamp,
+                    ) {
+                        imported += 1;
+                    }
+                }
+                if imported > 0 {
+                    self.save_history();
+                }
+
+                let Some(import) = &mut self.text_import else {
+                    return Command::none();
+                };
+                import.imported += imported;
+                if import.pending.is_empty() {
+                    let imported = import.imported;
+                    Command::perform(async {}, move |_| Message::ImportComplete(imported))
+                } else {
+                    Command::perform(async {}, move |_| Message::ImportProgress(done, total))
+                }
+            }
+            Message::ImportComplete(imported) => {
+                self.text_import = None;
+                info!("Imported {} entries", imported);
+                self.show_toast(format!("Imported {} entries", imported))
+            }
+            Message::SaveSession => {
+                self.track("save_session");
+                let entries = self
+                    .entries
+                    .iter()
+                    .map(|entry| self.body_store.load(entry))
+                    .collect();
+                match self.session_store.save_today(&entries, storage::unix_now()) {
+                    Ok(session) => {
+                        info!(
+                            "Saved {} with {} entries",
+                            session.name,
+                            session.entries.len()
+                        );
+                        self.sessions.retain(|s| s.name != session.name);
+                        self.sessions.insert(0, session);
+                    }
+                    Err(e) => error!("Failed to save session: {}", e),
+                }
+                Command::none()
+            }
+            Message::ReplaySession(index) => {
+                let Some(session) = self.sessions.get(index) else {
+                    return Command::none();
+                };
+                info!("Replaying {}", session.name);
+                self.track("replay_session");
+                let entries = session.entries.clone();
+                let clipboard = Arc::clone(&self.clipboard);
+                self.window_visible = false;
+
+                Command::batch(vec![
+                    Command::perform(async {}, |_| Message::ToggleWindow),
+                    Command::perform(
+                        tokio::task::spawn_blocking(move || {
+                            for entry in entries {
+                                thread::sleep(REPLAY_PASTE_DELAY);
+                                let set = macos::write_pasteboard_flavors(&entry.flavors)
+                                    || clipboard
+                                        .lock()
+                                        .map(|mut clipboard| {
+                                            clipboard.set_text(&entry.content).is_ok()
+                                        })
+                                        .unwrap_or(false);
+                                if set {
+                                    monitor::note_own_write();
+                                    simulate_paste();
+                                }
+                            }
+                        }),
+                        |_| Message::SessionReplayed,
+                    ),
+                ])
+            }
+            Message::SessionReplayed => {
+                info!("Finished replaying session");
+                Command::none()
+            }
+            Message::CollectionNameChanged(name) => {
+                self.collection_name = name;
+                Command::none()
+            }
+            Message::SaveCollection => {
+                let name = self.collection_name.trim().to_string();
+                if name.is_empty() || self.checked.is_empty() {
+                    return Command::none();
+                }
+                self.track("save_collection");
+                let entries = self
+                    .checked
+                    .iter()
+                    .filter_map(|&i| self.full_entry(i))
+                    .collect();
+                self.collections.retain(|c| c.name != name);
+                self.collections.push(Collection { name, entries });
+                self.active_collection = None;
+                self.collection_name.clear();
+                self.remap_checked(|_| None);
+                self.save_collections();
+                Command::none()
+            }
+            Message::ToggleCollection(index) => {
+                if index >= self.collections.len() {
+                    return Command::none();
+                }
+                if self
+                    .active_collection
+                    .is_some_and(|(active, _)| active == index)
+                {
+                    self.stop_collection();
+                    return Command::none();
+                }
+                self.track("start_collection");
+                self.active_collection = Some((index, 0));
+                if let Some(manager) = &self.hotkey_manager {
+                    let hotkey = collection_hotkey();
+                    let _ = manager.unregister(hotkey);
+                    match manager.register(hotkey) {
+                        Ok(()) => {
+                            info!("Registered collection hotkey: {}", COLLECTION_HOTKEY_LABEL)
+                        }
+                        Err(e) => error!("Failed to register {}: {}", COLLECTION_HOTKEY_LABEL, e),
+                    }
+                }
+                self.show_toast(format!(
+                    "Press {} to paste each entry of {}",
+                    COLLECTION_HOTKEY_LABEL, self.collections[index].name
+                ))
+            }
+            Message::DeleteCollection(index) => {
+                if index >= self.collections.len() {
+                    return Command::none();
+                }
+                match self.active_collection {
+                    Some((active, _)) if active == index => self.stop_collection(),
+                    Some((active, next)) if active > index => {
+                        self.active_collection = Some((active - 1, next));
+                    }
+                    _ => {}
+                }
+                self.collections.remove(index);
+                self.save_collections();
+                Command::none()
+            }
+            Message::CollectionItemPasted | Message::QuickAccessPasted => Command::none(),
+            Message::ReloadConfig => {
+                self.reload_config();
+                Command::none()
+            }
+            Message::Shutdown => {
+                info!("Shutdown requested, saving history");
+                if self.freeze_history {
+                    self.unfreeze();
+                }
+                self.save_history();
+                // exit() skips destructors
+                drop(self.instance_lock.take());
+                std::process::exit(0);
+            }
+            Message::OpenFilePath(content) => match files::existing_path(&content) {
+                Some(path) => {
+                    if let Err(e) = files::open(&path) {
+                        error!("Failed to open {}: {}", path.display(), e);
+                        return self.show_toast(format!("Couldn't open {}", path.display()));
+                    }
+                    Command::none()
+                }
+                None => self.show_toast(format!("{} no longer exists", content.trim())),
+            },
+            Message::RevealInFinder(content) => match files::existing_path(&content) {
+                Some(path) => {
+                    if let Err(e) = files::reveal(&path) {
+                        error!("Failed to reveal {}: {}", path.display(), e);
+                        return self.show_toast(format!("Couldn't reveal {}", path.display()));
+                    }
+                    Command::none()
+                }
+                None => self.show_toast(format!("{} no longer exists", content.trim())),
+            },
+            Message::ComposeEmail(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                self.track("compose_email");
+                let address = self.entries[index].content.trim().to_string();
+                if let Err(e) = open::that(format!("mailto:{}", address)) {
+                    error!("Failed to compose an email to {}: {}", address, e);
+                    return self.show_toast(format!("Couldn't compose an email to {}", address));
+                }
+                Command::none()
+            }
+            Message::CopyEmailDomain(id) => {
+                let Some(index) = self.index_of(id) else {
+                    return Command::none();
+                };
+                self.track("copy_email_domain");
+                let Some(domain) = classify::email_domain(&self.entries[index].content) else {
+                    return Command::none();
+                };
+                let domain = domain.to_string();
+                if self.copy_to_clipboard(domain.clone()) {
+                    return self.show_toast(format!("Copied {}", domain));
+                }
+                Command::none()
+            }
+            Message::WindowMoved(x, y) => {
+                let position = WindowPosition { x, y };
+                // Hidden windows can still report moves, which the user didn't make
+                if self.window_visible
+                    && self.config.remember_window_position
+                    && !self.config.cursor_follow
+                    && self.window_position != Some(position)
+                {
+                    self.window_position = Some(position);
+                    if let Err(e) = window_state::save(&self.window_state_path, position) {
+                        error!("Failed to save window position: {}", e);
+                    }
+                }
+                Command::none()
+            }
+            Message::FollowCursor => {
+                let Some(cursor) = macos::cursor_position() else {
+                    return Command::none();
+                };
+                let size = (WINDOW_WIDTH, WINDOW_HEIGHT);
+                let Some((x, y)) = window_state::follow_cursor(self.follow_position, cursor, size)
+                else {
+                    return Command::none();
+                };
+                self.follow_position = Some((x, y));
+                let position = window_state::clamp(
+                    WindowPosition {
+                        x: x as i32,
+                        y: y as i32,
+                    },
+                    size,
+                    &macos::screen_frames(),
+                );
+                window::move_to(position.x, position.y)
+            }
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+                Command::none()
+            }
+            Message::ResolveLargeCopy(policy) => match self.large_copy.take() {
+                Some(copy) => self.resolve_large_copy(copy, policy),
+                None => Command::none(),
+            },
+            Message::DismissSpontaneousChange => {
+                self.spontaneous_change = None;
+                Command::none()
+            }
+            Message::OpenAccessibilitySettings => {
+                if let Err(e) = open::that(macos::ACCESSIBILITY_SETTINGS_URL) {
+                    error!("Failed to open System Settings: {}", e);
+                    return self.show_toast("Couldn't open System Settings".to_string());
+                }
+                Command::none()
+            }
+            Message::DismissAccessibilityNotice => {
+                self.accessibility_missing = false;
+                Command::none()
+            }
+            Message::DismissToast(id) => {
+                if self
+                    .toast
+                    .as_ref()
+                    .is_some_and(|(current, _)| *current == id)
+                {
+                    self.toast = None;
+                }
+                Command::none()
+            }
+            Message::InitTray => {
+                if self.config.tray_entries > 0 {
+                    self.tray = Tray::new();
+                    self.refresh_tray();
+                }
+                Command::none()
+            }
+            // GC would remove entries the frozen history is meant to keep
+            Message::RunGc if self.freeze_history => Command::none(),
+            Message::RunGc => {
+                let removed = storage::gc_history(&mut self.entries, &self.config);
+                if removed > 0 {
+                    info!("GC removed {} entries", removed);
+                }
+                if removed
+                    + storage::archive_history(
+                        &mut self.entries,
+                        &self.config,
+                        &self.body_store,
+                        &self.archive_dir,
+                    )
+                    > 0
+                {
+                    self.search_index = SearchIndex::new(&self.entries);
+                    self.full_view = None;
+                    self.selected = None;
+                    self.remap_checked(|_| None);
+                    self.save_history();
+                }
+                if let Err(e) = self.body_store.remove_orphans(&self.entries) {
+                    error!("Failed to clean up entry bodies: {}", e);
+                }
+                Command::none()
+            }
+            Message::ToggleWindow => {
+                if !self.window_visible {
+                    self.remember_search();
+                    // Start from the pointer again next time
+                    self.follow_position = None;
+                    // Ask for the PIN again the next time the window opens
+                    self.unlocked = false;
+                    self.pin_lock.clear();
+                    self.pin_message = None;
+                    self.refresh_tray();
+                    Command::batch(vec![
+                        window::change_mode(window::Mode::Hidden),
+                    ])
+                } else {
+                    let level = if self.config.always_on_top {
+                        window::Level::AlwaysOnTop
+                    } else {
+                        window::Level::Normal
+                    };
+                    if self.config.screen_capture_guard != ScreenCaptureGuard::Off {
+                        // Covers recordings and shares the session doesn't report
+                        macos::exclude_windows_from_capture();
+                    }
+                    let mut commands = Vec::new();
+                    if let Some(position) = window_position(&self.config, self.window_position) {
+                        commands.push(window::move_to(position.x, position.y));
+                    }
+                    commands.extend([
+                        window::change_mode(window::Mode::Windowed),
+                        window::change_level(level),
+                        window::gain_focus(),
+                        text_input::focus(text_input::Id::new(SEARCH_INPUT_ID)),
+                    ]);
+                    Command::batch(commands)
+                }
+            }
+        }
+    }
+
+    fn view(&self) -> Element<Message> {
+        if !self.window_visible {
+            return container(Space::new(Length::Fill, Length::Fill))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into();
+        }
+
+        if self.pin_required() {
+            return self.view_pin_entry();
+        }
+
+        if let Some(qr_code) = &self.qr_code {
+            return self.view_qr_code(qr_code);
+        }
+
+        if let Some((a, b)) = self.comparison_view {
+            return self.view_comparison(a, b);
+        }
+
+        if let Some(index) = self.full_view {
+            if let Some(entry) = self.entries.get(index) {
+                let entry = self.full_view_entry.as_ref().unwrap_or(entry);
+                return self.view_full_entry(index, entry);
+            }
+        }
+
+        if self.tab == Tab::Sessions {
+            return self.view_sessions();
+        }
+        if self.tab == Tab::Stats {
+            return self.view_stats();
+        }
+        if self.tab == Tab::Settings {
+            return self.view_settings();
+        }
+
+        info!("Rendering window with {} entries", self.entries.len());
+        let mut content = column![].spacing(5).padding(10);
+
+        content = content.push(self.view_tabs());
+        let layout = match self.config.view_mode {
+            ViewMode::List => "Grid",
+            ViewMode::Grid => "List",
+        };
+        content = content.push(
+            row![
+                text("Clipboard History")
+                    .size(18)
+                    .width(Length::Fill)
+                    .horizontal_alignment(alignment::Horizontal::Center),
+                widgets::action_button(text(layout).size(12), Message::ToggleViewMode),
+            ]
+            .align_items(alignment::Alignment::Center),
+        );
+
+        if self.freeze_history {
+            let waiting = match self.pending_entries.len() {
+                0 => String::new(),
+                1 => ", 1 new copy waiting".to_string(),
+                n => format!(", {} new copies waiting", n),
+            };
+            content = content.push(
+                container(
+                    row![
+                        text(format!("Frozen{}", waiting))
+                            .size(12)
+                            .width(Length::Fill),
+                        widgets::action_button(text("Unfreeze").size(12), Message::ToggleFreeze),
+                    ]
+                    .align_items(alignment::Alignment::Center),
+                )
+                .width(Length::Fill)
+                .padding(6)
+                .style(iced::theme::Container::Box),
+            );
+        }
+
+        if let Some(toast) = self.view_toast() {
+            content = content.push(toast);
+        }
+
+        if self.accessibility_missing {
+            content = content.push(
+                container(
+                    column![
+                        text(
+                            "mac-clip needs the Accessibility permission to paste for you. \
+                             Allow it in System Settings → Privacy & Security → Accessibility, \
+                             then pick an entry again."
+                        )
+                        .size(12),
+                        row![
+                            widgets::action_button(
+                                text("Open System Settings").size(12),
+                                Message::OpenAccessibilitySettings
+                            ),
+                            widgets::action_button(
+                                text("Dismiss").size(12),
+                                Message::DismissAccessibilityNotice
+                            ),
+                        ]
+                        .spacing(5),
+                    ]
+                    .spacing(5),
+                )
+                .padding(6)
+                .style(iced::theme::Container::Box),
+            );
+        }
+
+        if let Some(changed_at) = self.spontaneous_change {
+            let time = chrono::DateTime::from_timestamp(changed_at as i64, 0)
+                .map(|t| {
+                    t.with_timezone(&chrono::Local)
+                        .format("%H:%M:%S")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            content = content.push(
+                container(
+                    row![
+                        text(format!(
+                            "⚠️ Clipboard changed without user interaction at {}. Check the newest entry before pasting it.",
+                            time
+                        ))
+                        .size(12)
+                        .width(Length::Fill),
+                        widgets::action_button(text("Dismiss").size(12), Message::DismissSpontaneousChange),
+                    ]
+                    .spacing(5)
+                    .align_items(alignment::Alignment::Center),
+                )
+                .padding(6)
+                .style(iced::theme::Container::Box),
+            );
+        }
+
+        if let Some(copy) = &self.large_copy {
+            let truncated = format!("Keep first {}", format_size(self.config.max_entry_bytes));
+            content = content.push(
+                container(column![
+                    text(format!(
+                        "You copied {} of text, more than max_entry_bytes. Keep it in the history?",
+                        format_size(copy.content.len())
+                    ))
+                    .size(12),
+                    row![
+                        widgets::action_button(
+                            text("Keep").size(12),
+                            Message::ResolveLargeCopy(LargeContentPolicy::Store)
+                        ),
+                        widgets::action_button(
+                            text(truncated).size(12),
+                            Message::ResolveLargeCopy(LargeContentPolicy::Truncate)
+                        ),
+                        widgets::action_button(
+                            text("Skip").size(12),
+                            Message::ResolveLargeCopy(LargeContentPolicy::Skip)
+                        ),
+                    ]
+                    .spacing(5),
+                ])
+                .padding(6)
+                .style(iced::theme::Container::Box),
+            );
+        }
+
+        let pinned = self.entries.iter().filter(|e| e.pinned).count();
+        if pinned > 0 {
+            // Pins don't count towards the history limit, so say how the
+            // list is split up to explain why it can exceed it.
+            content = content.push(
+                text(format!(
+                    "{} pinned · {}/{} recent",
+                    pinned,
+                    self.entries.len() - pinned,
+                    self.config.max_history_size
+                ))
+                .size(12)
+                .width(Length::Fill)
+                .horizontal_alignment(alignment::Horizontal::Center),
+            );
+        }
+
+        let visible = self.visible_entries();
+        let hidden = visible.len() - self.shown_count(visible.len());
+        let show_scroll_indicator = self
+            .config
+            .show_scroll_indicator
+            .unwrap_or(visible.len() - hidden > SCROLL_INDICATOR_MIN_ENTRIES);
+        let mut search_row = Row::new().spacing(5).push(
+            text_input("Search...", &self.search_query)
+                .id(text_input::Id::new(SEARCH_INPUT_ID))
+                .on_input(Message::SearchChanged)
+                .on_submit(Message::ActivateSelection)
+                .padding(8)
+                .size(12),
+        );
+        let counter = text(format!(
+            "{}/{}",
+            self.search_query.chars().count(),
+            self.config.max_search_length
+        ))
+        .size(12);
+        search_row = search_row.push(if self.search_length_warning() {
+            counter.style(iced::theme::Text::Color(iced::Color::from_rgb8(
+                0xef, 0x44, 0x44,
+            )))
+        } else {
+            counter
+        });
+        if !self.search_query.is_empty() && !visible.is_empty() {
+            search_row = search_row.push(widgets::action_button(
+                text(format!("Copy all ({})", visible.len())).size(12),
+                Message::CopyAllVisible,
+            ));
+        }
+        if let [a, b] = self.checked[..] {
+            let (a, b) = (self.entries[a].id, self.entries[b].id);
+            search_row = search_row.push(widgets::action_button(
+                text("Compare").size(12),
+                Message::CompareEntries(a, b),
+            ));
+        }
+        content = content.push(search_row);
+        if self.search_query.is_empty() && !self.search_history.is_empty() {
+            content = content.push(self.view_search_history());
+        }
+
+        content = content.push(Space::new(Length::Fill, Length::Fixed(5.0)));
+
+        if self.entries.is_empty() {
+            content = content.push(
+                container(
+                    text("No clipboard history yet. Copy some text!")
+                        .width(Length::Fill)
+                        .size(14)
+                        .horizontal_alignment(alignment::Horizontal::Center),
+                )
+                .padding(10)
+                .style(iced::theme::Container::Box),
+            );
+        } else if visible.is_empty() {
+            content = content.push(
+                text("No entries match your search.")
+                    .width(Length::Fill)
+                    .size(14)
+                    .horizontal_alignment(alignment::Horizontal::Center),
+            );
+        } else {
+            let shown = visible.len() - hidden;
+            let shown = visible.into_iter().take(shown).collect();
+            content = content.push(match self.config.view_mode {
+                ViewMode::List => self.view_list(shown),
+                ViewMode::Grid => self.view_grid(shown),
+            });
+
+            if hidden > 0 {
+                content = content.push(
+                    container(widgets::action_button(
+                        text(format!("Show more ({} hidden)", hidden)).size(12),
+                        Message::ShowMoreEntries,
+                    ))
+                    .width(Length::Fill)
+                    .center_x(),
+                );
+            }
+        }
+
+        let preview = self.view_preview();
+        let mut scrollable = scrollable(content).on_scroll(Message::HistoryScrolled);
+        if preview.is_some() {
+            // Leaves the preview pane its height
+            scrollable = scrollable.height(Length::Fill);
+        }
+        let mut history = row![scrollable].spacing(2);
+        if show_scroll_indicator {
+            history = history.push(
+                canvas(widgets::ScrollIndicator {
+                    progress: self.scroll_progress,
+                })
+                .width(Length::Fixed(4.0))
+                .height(Length::Fill),
+            );
+        }
+        let mut page = column![history];
+        if let Some(preview) = preview {
+            page = page
+                .push(
+                    canvas(widgets::PreviewDivider {
+                        height: self.preview_height(),
+                    })
+                    .width(Length::Fill)
+                    .height(Length::Fixed(widgets::DIVIDER_HEIGHT)),
+                )
+                .push(preview);
+        }
+        container(page)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .center_y()
+            .padding(10)
+            .style(iced::theme::Container::Box)
+            .into()
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        struct EventReceiver;
+
+        let rx = self.event_rx.clone();
+        let events = iced::subscription::unfold(
+            std::any::TypeId::of::<EventReceiver>(),
+            rx,
+            move |mut rx| async move {
+                if let Ok(Some(event)) = rx.changed().await.map(|_| rx.borrow().clone()) {
+                    (Message::EventReceived(event), rx)
+                } else {
+                    (Message::HotkeyPressed, rx) // Dummy message that won't be used
+                }
+            },
+        );
+
+        let keys = subscription::events_with(handle_key);
+        let moves = subscription::events_with(|event, _| match event {
+            iced::Event::Window(window::Event::Moved { x, y }) => Some(Message::WindowMoved(x, y)),
+            _ => None,
+        });
+
+        struct ShutdownWatcher;
+
+        let shutdown = iced::subscription::unfold(
+            std::any::TypeId::of::<ShutdownWatcher>(),
+            Arc::clone(&self.shutdown_requested),
+            |flag| async move {
+                while !flag.load(Ordering::SeqCst) {
+                    tokio::time::sleep(SHUTDOWN_CHECK_INTERVAL).await;
+                }
+                (Message::Shutdown, flag)
+            },
+        );
+
+        struct ConfigReloadWatcher;
+
+        let reload = iced::subscription::unfold(
+            std::any::TypeId::of::<ConfigReloadWatcher>(),
+            Arc::clone(&self.reload_requested),
+            |flag| async move {
+                while !flag.swap(false, Ordering::SeqCst) {
+                    tokio::time::sleep(CONFIG_RELOAD_CHECK_INTERVAL).await;
+                }
+                (Message::ReloadConfig, flag)
+            },
+        );
+
+        let gc = iced::time::every(GC_INTERVAL).map(|_| Message::RunGc);
+        let expiry = iced::time::every(EXPIRY_SWEEP_INTERVAL).map(|_| Message::SweepExpired);
+        let telemetry = iced::time::every(TELEMETRY_CHECK_INTERVAL).map(|_| Message::SendTelemetry);
+        let webdav = if self.webdav.is_some() {
+            let interval = Duration::from_secs(self.config.webdav_sync_interval_secs.max(1));
+            iced::time::every(interval).map(|_| Message::SyncWebDav)
+        } else {
+            Subscription::none()
+        };
+
+        let follow = if self.config.cursor_follow && self.window_visible {
+            iced::time::every(CURSOR_FOLLOW_INTERVAL).map(|_| Message::FollowCursor)
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch(vec![
+            events, keys, moves, shutdown, reload, gc, expiry, telemetry, webdav, follow,
+        ])
+    }
+}
+
+/// The global hotkey that shows the history window.
+fn history_hotkey() -> HotKey {
+    HotKey::new(Some(Modifiers::META | Modifiers::ALT), Code::KeyV)
+}
+
+/// The global hotkey that cycles through the history while its modifiers
+/// are held, with `hold_to_cycle`.
+fn cycle_hotkey() -> HotKey {
+    HotKey::new(Some(Modifiers::CONTROL | Modifiers::ALT), Code::KeyV)
+}
+
+/// The global hotkey that pastes the next entry of the active collection,
+/// registered only while a collection is being pasted.
+fn collection_hotkey() -> HotKey {
+    HotKey::new(Some(Modifiers::CONTROL | Modifiers::ALT), Code::KeyN)
+}
+
+/// The global hotkey that pastes the entry at history index `slot`: Command
+/// + Option + 1 to 9 and 0 for the first ten, then the same with Shift.
+fn quick_access_hotkey(slot: usize) -> HotKey {
+    let (code, _) = QUICK_ACCESS_KEYS[slot % QUICK_ACCESS_KEYS.len()];
+    let mut modifiers = Modifiers::META | Modifiers::ALT;
+    if slot >= QUICK_ACCESS_KEYS.len() {
+        modifiers |= Modifiers::SHIFT;
+    }
+    HotKey::new(Some(modifiers), code)
+}
+
+/// How a quick access hotkey is shown on its entry, e.g. "⌥⌘1".
+fn quick_access_label(slot: usize) -> String {
+    let (_, key) = QUICK_ACCESS_KEYS[slot % QUICK_ACCESS_KEYS.len()];
+    let shift = if slot >= QUICK_ACCESS_KEYS.len() {
+        "⇧"
+    } else {
+        ""
+    };
+    format!("{}⌥⌘{}", shift, key)
+}
+
+/// The global hotkey that freezes and unfreezes the history.
+fn freeze_hotkey() -> HotKey {
+    HotKey::new(Some(Modifiers::META | Modifiers::ALT), Code::KeyL)
+}
+
+fn register_freeze_hotkey(manager: &GlobalHotKeyManager) {
+    let hotkey = freeze_hotkey();
+    let _ = manager.unregister(hotkey);
+    match manager.register(hotkey) {
+        Ok(()) => info!("Registered freeze hotkey: {}", FREEZE_HOTKEY_LABEL),
+        Err(e) => error!("Failed to register {}: {}", FREEZE_HOTKEY_LABEL, e),
+    }
+}
+
+fn register_cycle_hotkey(manager: &GlobalHotKeyManager) {
+    let hotkey = cycle_hotkey();
+    let _ = manager.unregister(hotkey);
+    match manager.register(hotkey) {
+        Ok(()) => info!("Registered cycling hotkey: {}", CYCLE_HOTKEY_LABEL),
+        Err(e) => error!("Failed to register {}: {}", CYCLE_HOTKEY_LABEL, e),
+    }
+}
+
+/// Where to show the history window: `saved` moved onto a connected screen
+/// if `remember_window_position` is on and the window has been moved before,
+/// otherwise wherever `window_anchor` puts it. `None` leaves it centered.
+fn window_position(config: &Config, saved: Option<WindowPosition>) -> Option<WindowPosition> {
+    let size = (WINDOW_WIDTH, WINDOW_HEIGHT);
+    let mut screens = macos::screen_frames();
+    if !config.notch_aware {
+        screens.iter_mut().for_each(|screen| screen.notch = 0.0);
+    }
+    match saved.filter(|_| config.remember_window_position) {
+        Some(position) => Some(window_state::clamp(position, size, &screens)),
+        None => window_state::anchored(
+            config.window_anchor,
+            size,
+            config.window_margin,
+            macos::cursor_position(),
+            &screens,
+        ),
+    }
+}
+
+/// The details `shown` for a row of the history list, e.g.
+/// "Oct 16 14:05 · com.apple.Safari · 120 chars", or `None` if there are
+/// none.
+fn entry_details(entry: &ClipboardEntry, shown: RowDetails) -> Option<String> {
+    let mut details = Vec::new();
+    if shown.timestamp {
+        if let Some(time) = chrono::DateTime::from_timestamp(entry.timestamp as i64, 0) {
+            details.push(
+                time.with_timezone(&chrono::Local)
+                    .format("%b %-d %H:%M")
+                    .to_string(),
+            );
+        }
+    }
+    if shown.source_app {
+        if let Some(app) = &entry.source_app {
+            details.push(app.clone());
+        }
+    }
+    if shown.char_count {
+        details.push(format!("{} chars", entry.content.chars().count()));
+    }
+    (!details.is_empty()).then(|| details.join(" · "))
+}
+
+/// `timestamp` in local time as ISO 8601, e.g. "2024-05-01T14:05:09+02:00".
+fn iso_timestamp(timestamp: u64) -> Option<String> {
+    let time = chrono::DateTime::from_timestamp(timestamp as i64, 0)?;
+    Some(
+        time.with_timezone(&chrono::Local)
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+    )
+}
+
+/// A positive whole number of days typed in the Settings tab.
+fn parse_days(value: &str) -> Option<u64> {
+    value.trim().parse().ok().filter(|&days| days > 0)
+}
+
+/// Formats a byte count for messages, e.g. "10.2 MiB".
+fn format_size(bytes: usize) -> String {
+    if bytes < 1024 * 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Simulates Command+V so the current clipboard content is pasted into the
+/// focused app.
+fn simulate_paste() {
+    // Tests drive update() directly and mustn't type into whatever is focused
+    if cfg!(test) || cfg!(feature = "integration-tests") {
+        return;
+    }
+    let mut enigo = Enigo::new();
+    enigo.key_down(Key::Meta);
+    enigo.key_click(Key::Layout('v'));
+    enigo.key_up(Key::Meta);
+}
+
+/// Maps key presses to list navigation. Enter, Backspace and Delete only act
+/// on the list when the search input didn't capture them, so they keep
+/// editing the query while it has focus.
+fn handle_key(event: iced::Event, status: iced::event::Status) -> Option<Message> {
+    let key_code = match event {
+        iced::Event::Keyboard(keyboard::Event::KeyPressed { key_code, .. }) => key_code,
+        iced::Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+            return Some(Message::ModifiersChanged(modifiers));
+        }
+        iced::Event::Keyboard(keyboard::Event::CharacterReceived(c))
+            if c.is_ascii_digit() && status == iced::event::Status::Ignored =>
+        {
+            return Some(Message::PinDigitEntered(c));
+        }
+        _ => return None,
+    };
+    let ignored = status == iced::event::Status::Ignored;
+
+    match key_code {
+        keyboard::KeyCode::Escape => Some(Message::CloseFullView),
+        keyboard::KeyCode::Up => Some(Message::MoveSelection(-1)),
+        keyboard::KeyCode::Down => Some(Message::MoveSelection(1)),
+        keyboard::KeyCode::Enter if ignored => Some(Message::ActivateSelection),
+        keyboard::KeyCode::Backspace | keyboard::KeyCode::Delete if ignored => {
+            Some(Message::DeleteSelected)
+        }
+        keyboard::KeyCode::T if ignored => Some(Message::CopySelectedTimestamp),
+        _ => None,
+    }
+}
+
+impl MacClip {
+    /// Loads the config from `config_path` and the history and sessions from
+    /// `storage_dir` without registering the hotkey or starting any
+    /// background threads. `new()` builds on this, and tests call it directly
+    /// with a temporary directory and an in-memory clipboard.
+    fn with_storage(
+        storage_dir: &Path,
+        config_path: PathBuf,
+        clipboard: SharedClipboard,
+        tx: mpsc::UnboundedSender<Event>,
+        event_rx: watch::Receiver<Option<Event>>,
+    ) -> MacClip {
+        let config = Config::load(&config_path);
+        let exclusions = config.exclusion_regexes();
+        let sensitive = config.sensitive_regexes();
+
+        let session_store = SessionStore::new(storage_dir.join("sessions"));
+        let sessions = session_store.list().unwrap_or_else(|e| {
+            error!("Failed to load sessions: {}", e);
+            Vec::new()
+        });
+
+        let collection_store = CollectionStore::new(storage_dir.join("collections.json"));
+        let collections = collection_store.load().unwrap_or_else(|e| {
+            error!("Failed to load collections: {}", e);
+            Vec::new()
+        });
+
+        let classifier = Classifier::new(&config);
+        let rate_limiter = RateLimiterFilter::new(config.max_copies_per_minute);
+        let regex_transforms = RegexTransforms::new(&config.regex_transform_rules);
+        let max_age_days = config.max_age_days;
+        let webdav = WebDavSync::from_config(&config);
+
+        let mut history =
+            HistoryStore::new(storage_dir, config.storage_format, config.sync_safe_storage);
+        let mut entries = history.load();
+        let loaded_ids: HashSet<EntryId> = entries.iter().map(|e| e.id).collect();
+        for entry in entries
+            .iter_mut()
+            .filter(|e| e.content_type == ContentType::Unknown)
+        {
+            entry.content_type = classifier.classify(&entry.content);
+        }
+        let removed = storage::gc_history(&mut entries, &config);
+        if removed > 0 {
+            info!("GC removed {} entries", removed);
+        }
+        let body_store = BodyStore::new(storage_dir.join("bodies"));
+        let offloaded = if config.low_memory_mode {
+            body_store.offload_all(&mut entries)
+        } else {
+            0
+        };
+        let archive_dir = storage_dir.join("archive");
+        if removed
+            + offloaded
+            + storage::archive_history(&mut entries, &config, &body_store, &archive_dir)
+            > 0
+        {
+            if let Err(e) = history.save(&mut entries) {
+                error!("Failed to save history: {}", e);
+            }
+        }
+        if let Err(e) = body_store.remove_orphans(&entries) {
+            error!("Failed to clean up entry bodies: {}", e);
+        }
+
+        let tombstones_path = storage_dir.join("webdav_deleted.json");
+        let mut tombstones = webdav::load_tombstones(&tombstones_path);
+        if webdav.is_some()
+            && webdav::record_deletions(&mut tombstones, &loaded_ids, &entries, storage::unix_now())
+        {
+            if let Err(e) = webdav::save_tombstones(&tombstones_path, &tombstones) {
+                error!("Failed to save deleted entries: {}", e);
+            }
+        }
+
+        let last_clipboard_content = clipboard
+            .lock()
+            .ok()
+            .and_then(|mut clipboard| clipboard.get_text().ok())
+            .unwrap_or_default();
+        info!("Initial clipboard content: {}", last_clipboard_content);
+
+        MacClip {
+            saved_ids: entries.iter().map(|e| e.id).collect(),
+            tombstones,
+            tombstones_path,
+            search_index: SearchIndex::new(&entries),
+            expansions: ExpansionEngine::new(&entries, |entry| body_store.load(entry).content),
+            entries,
+            clipboard,
+            history,
+            config_path,
+            config,
+            exclusions,
+            sensitive,
+            classifier,
+            rate_limiter,
+            regex_transforms,
+            hotkey_manager: None,
+            quick_access_hotkeys: Vec::new(),
+            freeze_history: false,
+            pending_entries: VecDeque::new(),
+            scroll_progress: 0.0,
+            event_rx,
+            tx,
+            last_clipboard_content,
+            window_visible: false,
+            previous_app: None,
+            full_view: None,
+            search_query: String::new(),
+            search_history: VecDeque::new(),
+            search_history_position: None,
+            shown_beyond_limit: 0,
+            selected: None,
+            checked: Vec::new(),
+            comparison_view: None,
+            comparison: Vec::new(),
+            qr_code: None,
+            tab: Tab::History,
+            pin_lock: PinLock::default(),
+            unlocked: false,
+            pin_message: None,
+            body_store,
+            full_view_entry: None,
+            label_edited: false,
+            title_edit: None,
+            last_preview_click: None,
+            markdown_rendered: false,
+            session_store,
+            sessions,
+            collection_store,
+            collections,
+            collection_name: String::new(),
+            active_collection: None,
+            shutdown_requested: Arc::new(AtomicBool::new(false)),
+            reload_requested: Arc::new(AtomicBool::new(false)),
+            tray: None,
+            toast: None,
+            next_toast_id: 0,
+            spontaneous_change: None,
+            accessibility_missing: false,
+            cycling: false,
+            follow_position: None,
+            modifiers: keyboard::Modifiers::default(),
+            capture_masked: false,
+            large_copy: None,
+            telemetry: TelemetryRecorder::new(storage_dir.join("telemetry.jsonl")),
+            webdav,
+            // Whatever is only here goes up with the first sync
+            webdav_pending: true,
+            age_limit: AgeLimit::from_days(max_age_days),
+            custom_age_limit: max_age_days.map_or_else(String::new, |days| days.to_string()),
+            text_import: None,
+            window_state_path: storage_dir.join("window.json"),
+            window_position: window_state::load(&storage_dir.join("window.json")),
+            archive_dir,
+            instance_lock: None,
+        }
+    }
+
+    /// Whether the lock screen must be passed before showing history.
+    fn pin_required(&self) -> bool {
+        self.config.require_pin && self.config.pin_hash.is_some() && !self.unlocked
+    }
+
+    /// Shows how a PIN attempt went, and starts checking one that's been
+    /// submitted.
+    fn apply_pin_outcome(&mut self, outcome: PinOutcome) -> Command<Message> {
+        self.pin_message = match outcome {
+            PinOutcome::Pending => None,
+            PinOutcome::Check(pin) => {
+                let Some(hash) = self.config.pin_hash.clone() else {
+                    return Command::none();
+                };
+                // bcrypt takes long enough to freeze the window
+                return Command::perform(
+                    tokio::task::spawn_blocking(move || auth::verify(&pin, &hash)),
+                    |matched| Message::PinChecked(matched.unwrap_or(false)),
+                );
+            }
+            PinOutcome::Unlocked => {
+                info!("History unlocked");
+                self.unlocked = true;
+                self.refresh_tray();
+                None
+            }
+            PinOutcome::Rejected => Some("Incorrect PIN".to_string()),
+            PinOutcome::LockedOut => {
+                info!("Too many incorrect PINs, locking out");
+                Some("Too many attempts".to_string())
+            }
+        };
+        Command::none()
+    }
+
+    /// Returns the entry at `index` with its full content, reading it from
+    /// disk if it was offloaded.
+    fn full_entry(&self, index: usize) -> Option<ClipboardEntry> {
+        self.entries
+            .get(index)
+            .map(|entry| self.body_store.load(entry))
+    }
+
+    /// Puts an entry back on the clipboard with all of its captured
+    /// flavors, falling back to plain text.
+    fn copy_entry_to_clipboard(&mut self, index: usize) -> bool {
+        let Some(entry) = self.full_entry(index) else {
+            return false;
+        };
+        let content = entry.content.clone();
+        if macos::write_pasteboard_flavors(&entry.flavors) {
+            monitor::note_own_write();
+            self.last_clipboard_content = content;
+            return true;
+        }
+        if entry.content_type == ContentType::Binary {
+            // The hex dump is no stand-in for the data
+            error!("Failed to put binary data on the clipboard");
+            return false;
+        }
+        self.copy_to_clipboard(content)
+    }
+
+    /// Replaces the clipboard with entry `index` transformed for the app
+    /// it's about to be pasted into, if a regex transform or SmartPaste rule
+    /// matches that app.
+    fn apply_paste_transforms(&mut self, index: usize) {
+        // Our own window has no bundle identifier, so while it's still in
+        // front the target is the app the hotkey was pressed in
+        let Some(bundle_id) = macos::frontmost_app_bundle_id()
+            .or_else(|| self.previous_app.and_then(macos::app_bundle_id))
+        else {
+            return;
+        };
+        let Some(entry) = self
+            .full_entry(index)
+            .filter(|entry| entry.content_type != ContentType::Binary)
+        else {
+            return;
+        };
+        let substituted = paste::substitute(&self.regex_transforms, &bundle_id, &entry.content);
+        let text = substituted.as_deref().unwrap_or(&entry.content);
+        let transformed = self
+            .config
+            .smart_paste
+            .then(|| paste::transform(&self.config.smart_paste_rules, &bundle_id, text))
+            .flatten();
+        if let Some(text) = transformed.or(substituted) {
+            if text != entry.content {
+                info!("Transforming the paste for {}", bundle_id);
+                self.copy_to_clipboard(text);
+            }
+        }
+    }
+
+    /// Sets the clipboard without pasting, remembering the content so the
+    /// monitor doesn't treat it as a new copy. Returns whether it succeeded.
+    fn copy_to_clipboard(&mut self, content: String) -> bool {
+        let Ok(mut clipboard) = self.clipboard.lock() else {
+            return false;
+        };
+        match clipboard.set_text(&content) {
+            Ok(()) => {
+                monitor::note_own_write();
+                self.last_clipboard_content = content;
+                true
+            }
+            Err(e) => {
+                error!("Failed to set clipboard content: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Removes an entry, moving the highlight to the next visible entry if
+    /// the removed one was highlighted.
+    fn delete_entry(&mut self, index: usize) {
+        if index >= self.entries.len() {
+            return;
+        }
+
+        let visible: Vec<usize> = self.visible_entries().iter().map(|(i, _)| *i).collect();
+        let next = visible
+            .iter()
+            .position(|&i| i == index)
+            .and_then(|position| {
+                visible
+                    .get(position + 1)
+                    .or_else(|| position.checked_sub(1).and_then(|p| visible.get(p)))
+                    .copied()
+            });
+
+        self.entries.remove(index);
+        self.search_index.remove(index);
+        info!("Deleted entry at index {}", index);
+
+        let shift = |i: usize| if i > index { i - 1 } else { i };
+        self.selected = match self.selected {
+            Some(i) if i == index => next.map(shift),
+            other => other.map(shift),
+        };
+        self.full_view = match self.full_view {
+            Some(i) if i == index => None,
+            other => other.map(shift),
+        };
+        self.remap_checked(|i| (i != index).then(|| shift(i)));
+
+        self.save_history();
+    }
+
+    /// Entries matching the search query, with their indices. A
+    /// `type:<kind>` term, e.g. `type:url`, limits results to that content
+    /// type and a `language:<code>` term, e.g. `language:fr`, to entries in
+    /// that language; the rest of the query is matched as text.
+    fn visible_entries(&self) -> Vec<(usize, &ClipboardEntry)> {
+        let mut content_type = None;
+        let mut language = None;
+        let mut terms = Vec::new();
+        for term in self.search_query.split_whitespace() {
+            if let Some(code) = term.strip_prefix("language:").filter(|c| !c.is_empty()) {
+                language = Some(code);
+                continue;
+            }
+            match term.strip_prefix("type:").map(str::parse::<ContentType>) {
+                Some(Ok(parsed)) => content_type = Some(parsed),
+                _ => terms.push(term),
+            }
+        }
+        let query = query::parse_lenient(&terms.join(" "));
+
+        debug_assert_eq!(self.search_index.len(), self.entries.len());
+        let mut visible: Vec<_> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| content_type.is_none_or(|t| entry.content_type == t))
+            .filter(|(_, entry)| {
+                language.is_none_or(|code| {
+                    entry
+                        .language
+                        .as_deref()
+                        .is_some_and(|l| l.eq_ignore_ascii_case(code))
+                })
+            })
+            .filter(|(i, _)| {
+                query
+                    .as_ref()
+                    .is_none_or(|q| q.matches_lowercase(self.search_index.get(*i)))
+            })
+            .collect();
+        if let Some(query) = &query {
+            // Entries whose title alone matches come first
+            visible.sort_by_key(|(_, entry)| {
+                !entry.title.as_deref().is_some_and(|t| query.matches(t))
+            });
+        }
+        visible
+    }
+
+    /// How many of `matching` entries the list shows.
+    fn shown_count(&self, matching: usize) -> usize {
+        self.config.display_limit.map_or(matching, |limit| {
+            (limit + self.shown_beyond_limit).min(matching)
+        })
+    }
+
+    /// Adds the current search to the front of `search_history`, unless
+    /// it's empty.
+    fn remember_search(&mut self) {
+        self.search_history_position = None;
+        let query = self.search_query.trim();
+        if query.is_empty() {
+            return;
+        }
+        self.search_history.retain(|recent| recent != query);
+        self.search_history.push_front(query.to_string());
+        self.search_history.truncate(SEARCH_HISTORY_SIZE);
+    }
+
+    /// Shows an older search in the search field for a negative `delta` and a
+    /// newer one for a positive `delta`, clearing it past the newest.
+    fn recall_search(&mut self, delta: isize) {
+        let position = match self.search_history_position {
+            None if delta > 0 => return,
+            None => 0,
+            Some(position) => match position.checked_add_signed(-delta) {
+                Some(position) => position.min(self.search_history.len() - 1),
+                None => {
+                    self.search_history_position = None;
+                    self.search_query.clear();
+                    return;
+                }
+            },
+        };
+        self.search_history_position = Some(position);
+        self.search_query = self.search_history[position].clone();
+        self.shown_beyond_limit = 0;
+    }
+
+    /// Search length past which the counter turns red.
+    fn search_length_warning_at(&self) -> usize {
+        self.config.max_search_length * SEARCH_LENGTH_WARNING_PERCENT / 100
+    }
+
+    fn search_length_warning(&self) -> bool {
+        self.search_query.chars().count() > self.search_length_warning_at()
+    }
+
+    /// The visible entries the list actually shows, within `display_limit`.
+    fn shown_entries(&self) -> Vec<(usize, &ClipboardEntry)> {
+        let mut visible = self.visible_entries();
+        visible.truncate(self.shown_count(visible.len()));
+        visible
+    }
+
+    /// Re-reads `config.json` and applies it to the running app. Settings
+    /// that only take effect when the monitor threads start are reported as
+    /// needing a restart.
+    fn reload_config(&mut self) {
+        // A typo mustn't swap the running config for the defaults, which
+        // would turn off the PIN among everything else
+        let config = match Config::try_load(&self.config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                error!(
+                    "Failed to reload {}, keeping the current config: {}",
+                    self.config_path.display(),
+                    e
+                );
+                return;
+            }
+        };
+        let changes = config.changes_from(&self.config);
+        if changes.is_empty() {
+            info!("Reloaded config, nothing changed");
+            return;
+        }
+        for (field, old, new) in &changes {
+            info!("Config {} changed from {} to {}", field, old, new);
+            if matches!(
+                field.as_str(),
+                "capture_types"
+                    | "monitor_primary_selection"
+                    | "clipboard_settle_ms"
+                    | "startup_hotkey_delay_ms"
+                    | "startup_monitor_delay_ms"
+                    | "hold_to_cycle"
+            ) {
+                warn!("Restart mac-clip for the new {} to take effect", field);
+            }
+        }
+
+        self.exclusions = config.exclusion_regexes();
+        self.sensitive = config.sensitive_regexes();
+        self.classifier = Classifier::new(&config);
+        self.rate_limiter.max_per_minute = config.max_copies_per_minute;
+        self.regex_transforms = RegexTransforms::new(&config.regex_transform_rules);
+        self.history.set_sync_safe(config.sync_safe_storage);
+        self.history.set_format(config.storage_format);
+        self.webdav = WebDavSync::from_config(&config);
+        if !config.telemetry_opt_in && self.config.telemetry_opt_in {
+            match self.telemetry.reset() {
+                Ok(_) => info!("Telemetry turned off, deleted queued events"),
+                Err(e) => error!("Failed to delete queued telemetry events: {}", e),
+            }
+        }
+        if config.low_memory_mode && !self.config.low_memory_mode {
+            self.body_store.offload_all(&mut self.entries);
+            // Offloaded entries are searched by their preview
+            self.search_index = SearchIndex::new(&self.entries);
+        }
+        let quick_access_changed = config.quick_access_slots != self.config.quick_access_slots;
+        if config.max_age_days != self.config.max_age_days {
+            self.age_limit = AgeLimit::from_days(config.max_age_days);
+            if let Some(days) = config.max_age_days {
+                self.custom_age_limit = days.to_string();
+            }
+        }
+        self.config = config;
+        if quick_access_changed {
+            self.register_quick_access_hotkeys();
+        }
+
+        self.remove_aged_entries();
+        self.trim_history();
+        self.full_view = self.full_view.filter(|&i| i < self.entries.len());
+        self.selected = self.selected.filter(|&i| i < self.entries.len());
+        let len = self.entries.len();
+        self.remap_checked(|i| Some(i).filter(|&i| i < len));
+        self.save_history();
+    }
+
+    /// Drops the oldest entries beyond `max_history_size`, unless the
+    /// history is frozen.
+    fn trim_history(&mut self) {
+        if self.freeze_history {
+            return;
+        }
+        for index in storage::trim_history(&mut self.entries, self.config.max_history_size) {
+            self.search_index.remove(index);
+        }
+    }
+
+    fn save_history(&mut self) {
+        self.webdav_pending = true;
+        self.record_deletions();
+        match self.history.save(&mut self.entries) {
+            Ok(true) => {
+                // Entries from another machine were merged in, which moves
+                // everything around
+                self.search_index = SearchIndex::new(&self.entries);
+                self.full_view = None;
+                self.selected = None;
+                self.remap_checked(|_| None);
+                self.trim_history();
+            }
+            Ok(false) => {}
+            Err(e) => error!("Failed to save history: {}", e),
+        }
+        self.expansions =
+            ExpansionEngine::new(&self.entries, |entry| self.body_store.load(entry).content);
+        self.refresh_tray();
+    }
+
+    /// Remembers the entries deleted since the history was last saved, by
+    /// any means, so WebDAV syncs don't bring them back.
+    fn record_deletions(&mut self) {
+        let before = std::mem::replace(
+            &mut self.saved_ids,
+            self.entries.iter().map(|e| e.id).collect(),
+        );
+        if self.webdav.is_some()
+            && webdav::record_deletions(
+                &mut self.tombstones,
+                &before,
+                &self.entries,
+                storage::unix_now(),
+            )
+        {
+            self.save_tombstones();
+        }
+    }
+
+    fn save_tombstones(&self) {
+        if let Err(e) = webdav::save_tombstones(&self.tombstones_path, &self.tombstones) {
+            error!("Failed to save deleted entries: {}", e);
+        }
+    }
+
+    /// Adds a new copy to the history, or moves the entry it duplicates to
+    /// the front.
+    fn record_copy(
+        &mut self,
+        content: String,
+        source_app: Option<String>,
+        flavors: Flavors,
+        selection: Selection,
+    ) {
+        let timestamp = storage::unix_now();
+        if self.freeze_history {
+            self.hold_pending(ClipboardEntry {
+                content,
+                timestamp,
+                source_app,
+                flavors,
+                selection,
+                ..Default::default()
+            });
+            return;
+        }
+        if self.insert_copy(content, source_app, flavors, selection, timestamp) {
+            self.save_history();
+        }
+    }
+
+    /// Keeps a copy made while the history is frozen for when it's
+    /// unfrozen. Only the newest `max_history_size` are kept, since older
+    /// ones would be trimmed straight away.
+    fn hold_pending(&mut self, copy: ClipboardEntry) {
+        info!("History is frozen, keeping the copy for later");
+        self.pending_entries.push_front(copy);
+        self.pending_entries.truncate(self.config.max_history_size);
+    }
+
+    /// Unfreezes the history and adds the copies made while it was frozen,
+    /// as if they had been made now. Returns how many there were.
+    fn unfreeze(&mut self) -> usize {
+        self.freeze_history = false;
+        let pending = std::mem::take(&mut self.pending_entries);
+        info!("Unfreezing the history, adding {} copies", pending.len());
+        // Oldest first, so the newest ends up at the front
+        for copy in pending.iter().rev().cloned() {
+            if copy.content_type == ContentType::Binary {
+                let timestamp = copy.timestamp;
+                self.insert_binary_copy(copy, timestamp);
+            } else {
+                self.insert_copy(
+                    copy.content,
+                    copy.source_app,
+                    copy.flavors,
+                    copy.selection,
+                    copy.timestamp,
+                );
+            }
+        }
+        self.remove_aged_entries();
+        self.trim_history();
+        self.save_history();
+        pending.len()
+    }
+
+    /// [`record_copy`](Self::record_copy) without saving, for adding many
+    /// entries at once. Returns whether the history changed.
+    fn insert_copy(
+        &mut self,
+        content: String,
+        source_app: Option<String>,
+        flavors: Flavors,
+        selection: Selection,
+        timestamp: u64,
+    ) -> bool {
+        if let Some(index) = filter::find_duplicate(&self.entries, self.config.dedup_mode, &content)
+        {
+            if index == 0 {
+                return false;
+            }
+            info!("Moving duplicate entry {} to the front", index);
+            self.move_to_front(index, timestamp);
+        } else {
+            let expires_at = self
+                .sensitive
+                .iter()
+                .any(|re| re.is_match(&content))
+                .then(|| timestamp + self.config.sensitive_ttl_secs);
+            if expires_at.is_some() {
+                info!("Captured sensitive entry, it will expire shortly");
+            }
+
+            let entry = ClipboardEntry {
+                content: content.clone(),
+                timestamp,
+                source_app,
+                expires_at,
+                flavors,
+                selection,
+                content_type: self.classifier.classify(&content),
+                language: self
+                    .config
+                    .detect_language
+                    .then(|| language::detect(&content))
+                    .flatten()
+                    .map(str::to_string),
+                ..Default::default()
+            };
+            self.push_entry(entry);
+        }
+        true
+    }
+
+    /// Adds a new entry to the front of the history.
+    fn push_entry(&mut self, mut entry: ClipboardEntry) {
+        if self.config.low_memory_mode {
+            if let Err(e) = self.body_store.offload(&mut entry) {
+                error!("Failed to offload entry: {}", e);
+            }
+        }
+
+        self.search_index.push_front(&entry);
+        self.entries.push_front(entry);
+        self.trim_history();
+
+        // Keep the full view and highlight on the same entries
+        let len = self.entries.len();
+        self.full_view = self.full_view.map(|i| i + 1).filter(|&i| i < len);
+        self.selected = self.selected.map(|i| i + 1).filter(|&i| i < len);
+        self.remap_checked(|i| Some(i + 1).filter(|&i| i < len));
+        self.remove_aged_entries();
+    }
+
+    /// Records a copy without text as a binary entry previewed by a hex
+    /// dump. Copies of the same data move the existing entry to the front,
+    /// since hex dumps of different images often start alike.
+    fn record_binary_copy(&mut self, flavors: Flavors, source_app: Option<String>) {
+        let Some((flavor, data)) = binary::main_flavor(&flavors) else {
+            return;
+        };
+        let mime_type = binary::mime_type(flavor);
+        let size: usize = flavors.values().map(Vec::len).sum();
+        let copy = ClipboardEntry {
+            content: format!("{} ({})", binary::hex_preview(data), format_size(size)),
+            source_app,
+            flavors,
+            content_type: ContentType::Binary,
+            mime_type: Some(mime_type),
+            ..Default::default()
+        };
+        let mut copy = match self.filters(false).run(copy) {
+            Ok(copy) => copy,
+            Err(reason) => {
+                info!("Skipping binary copy: {}", reason);
+                return;
+            }
+        };
+
+        let timestamp = storage::unix_now();
+        if self.freeze_history {
+            copy.timestamp = timestamp;
+            self.hold_pending(copy);
+            return;
+        }
+        if self.insert_binary_copy(copy, timestamp) {
+            self.save_history();
+        }
+    }
+
+    /// [`record_binary_copy`](Self::record_binary_copy) after the filters,
+    /// without saving. Returns whether the history changed.
+    fn insert_binary_copy(&mut self, mut copy: ClipboardEntry, timestamp: u64) -> bool {
+        match self
+            .entries
+            .iter()
+            .position(|e| e.content_type == ContentType::Binary && e.flavors == copy.flavors)
+        {
+            Some(0) => return false,
+            Some(index) => {
+                info!("Moving duplicate binary entry {} to the front", index);
+                self.move_to_front(index, timestamp);
+            }
+            None => {
+                let size: usize = copy.flavors.values().map(Vec::len).sum();
+                info!(
+                    "Captured a {} {} copy",
+                    format_size(size),
+                    copy.mime_type.as_deref().unwrap_or_default()
+                );
+                copy.timestamp = timestamp;
+                self.push_entry(copy);
+            }
+        }
+        true
+    }
+
+    /// Records that a feature was used, if the user opted in to telemetry.
+    fn track(&self, event: &'static str) {
+        if !self.config.telemetry_opt_in {
+            return;
+        }
+        if let Err(e) = self.telemetry.record(event, storage::unix_now()) {
+            error!("Failed to record telemetry event: {}", e);
+        }
+    }
+
+    /// Gets ready to show the window from a hotkey. False if it has to stay
+    /// hidden because the screen is being shared.
+    fn prepare_to_open(&mut self) -> bool {
+        let shared = self.config.screen_capture_guard != ScreenCaptureGuard::Off
+            && macos::screen_being_shared() == Some(true);
+        if shared && self.config.screen_capture_guard == ScreenCaptureGuard::Hide {
+            info!("Not showing the history while the screen is shared");
+            return false;
+        }
+        self.capture_masked = shared;
+        self.refresh_tray();
+
+        // Still the app the user was in, since the window isn't up yet
+        self.previous_app = macos::frontmost_app_pid();
+        // Drop the setup guidance once the permission is granted
+        if self.accessibility_missing {
+            self.accessibility_missing = macos::accessibility_trusted() == Some(false);
+        }
+        true
+    }
+
+    /// Opens the window on the first press of the cycling hotkey and
+    /// highlights the next entry on each press after that, wrapping around.
+    /// The highlighted entry is pasted when Control and Option are released.
+    fn cycle_selection(&mut self) -> Command<Message> {
+        let shown: Vec<usize> = self.shown_entries().iter().map(|(i, _)| *i).collect();
+        if self.cycling {
+            let next = self
+                .selected
+                .and_then(|s| shown.iter().position(|&i| i == s))
+                .map_or(0, |position| (position + 1) % shown.len());
+            self.selected = shown.get(next).copied();
+            return Command::none();
+        }
+        if self.window_visible || shown.is_empty() || !self.prepare_to_open() {
+            return Command::none();
+        }
+        if self.pin_required() {
+            // Nothing to cycle through until the PIN is entered
+            self.window_visible = true;
+            return Command::perform(async {}, |_| Message::ToggleWindow);
+        }
+        self.track("cycle");
+        self.cycling = true;
+        self.window_visible = true;
+        // The newest entry is most likely still on the clipboard
+        self.selected = shown.get(1).or(shown.first()).copied();
+        Command::batch(vec![
+            Command::perform(async {}, |_| Message::ToggleWindow),
+            Command::perform(
+                async {
+                    while macos::control_option_held() == Some(true) {
+                        tokio::time::sleep(CYCLE_RELEASE_CHECK_INTERVAL).await;
+                    }
+                },
+                |_| Message::CycleReleased,
+            ),
+        ])
+    }
+
+    /// Puts the active collection's next entry on the clipboard and pastes
+    /// it once the hotkey's modifiers are released, stopping after the last.
+    fn paste_next_in_collection(&mut self) -> Command<Message> {
+        let Some((index, next)) = self.active_collection else {
+            return Command::none();
+        };
+        let Some(entry) = self
+            .collections
+            .get(index)
+            .and_then(|c| c.entries.get(next))
+            .cloned()
+        else {
+            self.stop_collection();
+            return Command::none();
+        };
+        let count = self.collections[index].entries.len();
+        info!("Pasting entry {} of {}", next + 1, count);
+        if next + 1 < count {
+            self.active_collection = Some((index, next + 1));
+        } else {
+            self.stop_collection();
+        }
+
+        let set = if macos::write_pasteboard_flavors(&entry.flavors) {
+            monitor::note_own_write();
+            self.last_clipboard_content = entry.content;
+            true
+        } else {
+            self.copy_to_clipboard(entry.content)
+        };
+        if !set {
+            return Command::none();
+        }
+        Command::perform(
+            async {
+                // Command + V with Control and Option still down pastes nothing
+                while macos::control_option_held() == Some(true) {
+                    tokio::time::sleep(CYCLE_RELEASE_CHECK_INTERVAL).await;
+                }
+                simulate_paste();
+            },
+            |_| Message::CollectionItemPasted,
+        )
+    }
+
+    /// How many entries have a quick access hotkey.
+    fn quick_access_slots(&self) -> usize {
+        self.config.quick_access_slots.min(MAX_QUICK_ACCESS_SLOTS)
+    }
+
+    /// Registers a quick access hotkey for each of `quick_access_slots`,
+    /// replacing any registered before.
+    fn register_quick_access_hotkeys(&mut self) {
+        let Some(manager) = &self.hotkey_manager else {
+            return;
+        };
+        for hotkey in self.quick_access_hotkeys.drain(..) {
+            let _ = manager.unregister(hotkey);
+        }
+        for slot in 0..self.quick_access_slots() {
+            let hotkey = quick_access_hotkey(slot);
+            match manager.register(hotkey) {
+                Ok(()) => self.quick_access_hotkeys.push(hotkey),
+                Err(e) => error!("Failed to register {}: {}", quick_access_label(slot), e),
+            }
+        }
+        info!(
+            "Registered {} quick access hotkeys",
+            self.quick_access_hotkeys.len()
+        );
+    }
+
+    /// Pastes the entry at history index `slot` into the frontmost app, once
+    /// Option and Shift are let go so the paste isn't taken for another
+    /// shortcut.
+    fn paste_quick_access(&mut self, slot: usize) -> Command<Message> {
+        let Some(id) = self.entries.get(slot).map(|entry| entry.id) else {
+            return Command::none();
+        };
+        // Straight from the hotkey, so update's PIN check doesn't see it
+        if self.pin_required() {
+            info!("Not pasting entry {} until the PIN is entered", slot);
+            return Command::none();
+        }
+        self.track("quick_access");
+        if self.window_visible {
+            // Pastes into the app the window was opened from
+            return self.update(Message::SelectEntry(id));
+        }
+        if !self.copy_entry_to_clipboard(slot) {
+            return Command::none();
+        }
+        info!("Pasting entry {} with its quick access hotkey", slot);
+        if self.config.paste_behavior != PasteBehavior::PasteAutomatically
+            || macos::accessibility_trusted() == Some(false)
+        {
+            return Command::none();
+        }
+        Command::perform(
+            async {
+                while macos::option_or_shift_held() == Some(true) {
+                    tokio::time::sleep(CYCLE_RELEASE_CHECK_INTERVAL).await;
+                }
+                simulate_paste();
+            },
+            |_| Message::QuickAccessPasted,
+        )
+    }
+
+    fn stop_collection(&mut self) {
+        self.active_collection = None;
+        if let Some(manager) = &self.hotkey_manager {
+            let _ = manager.unregister(collection_hotkey());
+        }
+    }
+
+    fn save_collections(&self) {
+        if let Err(e) = self.collection_store.save(&self.collections) {
+            error!("Failed to save collections: {}", e);
+        }
+    }
+
+    fn index_of(&self, id: EntryId) -> Option<usize> {
+        self.entries.iter().position(|e| e.id == id)
+    }
+
+    /// Drops unpinned entries older than `max_age_days`, unless the history
+    /// is frozen. Returns whether any were removed, leaving the history to be
+    /// saved.
+    fn remove_aged_entries(&mut self) -> bool {
+        if self.freeze_history {
+            return false;
+        }
+        if storage::remove_aged(&mut self.entries, &self.config, storage::unix_now()) == 0 {
+            return false;
+        }
+        self.search_index = SearchIndex::new(&self.entries);
+        self.full_view = None;
+        self.selected = None;
+        self.remap_checked(|_| None);
+        true
+    }
+
+    /// Changes `max_age_days` from the Settings tab, saving it to
+    /// `config.json` and applying it straight away.
+    fn set_max_age_days(&mut self, days: Option<u64>) {
+        if self.config.max_age_days == days {
+            return;
+        }
+        info!("Setting max_age_days to {:?}", days);
+        self.config.max_age_days = days;
+        self.save_config();
+        if self.remove_aged_entries() {
+            self.save_history();
+        }
+    }
+
+    /// Writes a setting changed from the Settings tab to `config.json`.
+    /// Leaves a file that doesn't parse alone, as it may hold settings the
+    /// running config doesn't, e.g. when it failed to load at startup.
+    fn save_config(&self) {
+        if let Err(e) = Config::try_load(&self.config_path) {
+            error!(
+                "Not saving over {}, which can't be read: {}",
+                self.config_path.display(),
+                e
+            );
+            return;
+        }
+        if let Err(e) = self.config.save(&self.config_path) {
+            error!("Failed to save {}: {}", self.config_path.display(), e);
+        }
+    }
+
+    /// Decides whether a clipboard change is recorded: skips blank content,
+    /// ignored apps and exclusions (unless copied twice in quick succession),
+    /// and hands large copies to `large_content_policy`.
+    fn handle_clipboard_event(
+        &mut self,
+        content: String,
+        source_app: Option<String>,
+        flavors: Flavors,
+        selection: Selection,
+        user_initiated: bool,
+        double_copied: bool,
+    ) -> Command<Message> {
+        info!("Processing clipboard change");
+        // mac-clip's own copies happen without a key press too
+        if !user_initiated
+            && self.config.warn_on_spontaneous_changes
+            && content != self.last_clipboard_content
+        {
+            warn!(
+                "Clipboard changed without user interaction (frontmost app: {})",
+                source_app.as_deref().unwrap_or("unknown")
+            );
+            self.spontaneous_change = Some(storage::unix_now());
+        }
+
+        // Expanding would put an entry's content on the clipboard while the
+        // history is locked
+        if selection == Selection::Clipboard && !self.pin_required() {
+            if let Some(snippet) = self.expansions.expand(&content) {
+                info!("Expanding snippet trigger {}", content.trim());
+                self.track("expand_snippet");
+                let snippet = snippet.to_string();
+                self.copy_to_clipboard(snippet);
+                return Command::none();
+            }
+        }
+
+        let forced = double_copied && self.config.double_copy_capture;
+        if forced {
+            info!("Recording a double copy regardless of filters");
+        }
+        let copy = ClipboardEntry {
+            content,
+            source_app,
+            flavors,
+            selection,
+            ..Default::default()
+        };
+        let ClipboardEntry {
+            content,
+            source_app,
+            flavors,
+            selection,
+            ..
+        } = match self.filters(forced).run(copy) {
+            Ok(copy) => copy,
+            Err(reason) => {
+                info!("Skipping clipboard change: {}", reason);
+                return Command::none();
+            }
+        };
+
+        if content.len() > self.config.max_entry_bytes {
+            return self.handle_large_copy(LargeCopy {
+                content,
+                source_app,
+                flavors,
+                selection,
+            });
+        }
+        self.record_copy(content, source_app, flavors, selection);
+        Command::none()
+    }
+
+    /// Applies `large_content_policy` to a copy over `max_entry_bytes`.
+    fn handle_large_copy(&mut self, copy: LargeCopy) -> Command<Message> {
+        let policy = match self.config.large_content_policy {
+            LargeContentPolicy::Ask if self.window_visible => {
+                info!(
+                    "Asking whether to keep a {} copy",
+                    format_size(copy.content.len())
+                );
+                if self.large_copy.replace(copy).is_some() {
+                    warn!("Skipped an earlier large copy that was still waiting for an answer");
+                }
+                return Command::none();
+            }
+            LargeContentPolicy::Ask => {
+                info!("History window isn't open to ask about a large copy");
+                LargeContentPolicy::Skip
+            }
+            policy => policy,
+        };
+        self.resolve_large_copy(copy, policy)
+    }
+
+    fn resolve_large_copy(
+        &mut self,
+        copy: LargeCopy,
+        policy: LargeContentPolicy,
+    ) -> Command<Message> {
+        let size = format_size(copy.content.len());
+        match policy {
+            LargeContentPolicy::Store => {
+                info!("Keeping a {} copy", size);
+                self.record_copy(copy.content, copy.source_app, copy.flavors, copy.selection);
+                Command::none()
+            }
+            LargeContentPolicy::Truncate => {
+                let mut end = self.config.max_entry_bytes;
+                while !copy.content.is_char_boundary(end) {
+                    end -= 1;
+                }
+                warn!(
+                    "Keeping only the first {} of a {} copy",
+                    format_size(end),
+                    size
+                );
+                let mut content = copy.content;
+                content.truncate(end);
+                // The other formats still hold everything
+                self.record_copy(content, copy.source_app, Flavors::new(), copy.selection);
+                self.show_toast(format!(
+                    "Kept the first {} of a {} copy",
+                    format_size(end),
+                    size
+                ))
+            }
+            LargeContentPolicy::Skip | LargeContentPolicy::Ask => {
+                warn!("Skipped a {} copy larger than max_entry_bytes", size);
+                self.show_toast(format!("Didn't keep a {} copy", size))
+            }
+        }
+    }
+
+    /// The checks a new copy has to pass to be stored. Double copies, which
+    /// are recorded regardless of the filters, are only sanitized and
+    /// deduplicated.
+    fn filters(&self, forced: bool) -> FilterPipeline<'_> {
+        let mut pipeline = FilterPipeline::default().with(SanitizeFilter);
+        if !forced {
+            pipeline = pipeline
+                .with(CaptureTypeFilter {
+                    config: &self.config,
+                })
+                .with(IgnoredAppFilter {
+                    ignore_apps: &self.config.ignore_apps,
+                })
+                .with(ExclusionPatternFilter {
+                    patterns: &self.exclusions,
+                })
+                .with(MaxSizeFilter {
+                    max_binary_bytes: self.config.max_binary_entry_kb * 1024,
+                });
+        }
+        pipeline = pipeline.with(DuplicateFilter {
+            entries: &self.entries,
+            mode: self.config.dedup_mode,
+        });
+        if !forced {
+            pipeline = pipeline.with(&self.rate_limiter);
+        }
+        pipeline
+    }
+
+    /// Moves a re-copied entry to the top of the history as if it had just
+    /// been copied, keeping the full view and highlight on the same entries.
+    fn move_to_front(&mut self, index: usize, timestamp: u64) {
+        let Some(mut entry) = self.entries.remove(index) else {
+            return;
+        };
+        entry.timestamp = timestamp;
+        self.entries.push_front(entry);
+        self.search_index.move_to_front(index);
+
+        let shift = |i: usize| match i.cmp(&index) {
+            std::cmp::Ordering::Less => i + 1,
+            std::cmp::Ordering::Equal => 0,
+            std::cmp::Ordering::Greater => i,
+        };
+        self.full_view = self.full_view.map(shift);
+        self.selected = self.selected.map(shift);
+        self.remap_checked(|i| Some(shift(i)));
+    }
+
+    /// Moves the ticked and compared entries' indices after the history
+    /// changed, dropping those `map` returns `None` for. The comparison is
+    /// closed if either of its entries is gone.
+    fn remap_checked(&mut self, map: impl Fn(usize) -> Option<usize>) {
+        self.checked = self.checked.iter().filter_map(|&i| map(i)).collect();
+        self.comparison_view = self
+            .comparison_view
+            .and_then(|(a, b)| Some((map(a)?, map(b)?)));
+        if self.comparison_view.is_none() {
+            self.comparison.clear();
+        }
+    }
+
+    /// Shows `message` in the window for a few seconds.
+    fn show_toast(&mut self, message: String) -> Command<Message> {
+        let id = self.next_toast_id;
+        self.next_toast_id += 1;
+        self.toast = Some((id, message));
+        Command::perform(tokio::time::sleep(TOAST_DURATION), move |_| {
+            Message::DismissToast(id)
+        })
+    }
+
+    fn view_toast(&self) -> Option<Element<'_, Message>> {
+        let (_, message) = self.toast.as_ref()?;
+        Some(
+            container(text(message).size(12))
+                .width(Length::Fill)
+                .padding(6)
+                .style(iced::theme::Container::Box)
+                .into(),
+        )
+    }
+
+    /// Updates the menu bar menu, which lists no entries while the window
+    /// wouldn't show them either.
+    fn refresh_tray(&mut self) {
+        let hidden = self.pin_required() || self.capture_masked;
+        if let Some(tray) = self.tray.as_mut() {
+            tray.refresh(&self.entries, self.config.tray_entries, hidden);
+        }
+    }
+
+    fn view_pin_entry(&self) -> Element<'_, Message> {
+        let status = match self.pin_lock.lockout_remaining() {
+            Some(seconds) => format!("Locked, try again in {}s", seconds),
+            None => self.pin_message.clone().unwrap_or_default(),
+        };
+
+        let digit = |d: char| {
+            button(
+                text(d)
+                    .size(16)
+                    .horizontal_alignment(alignment::Horizontal::Center),
+            )
+            .width(Length::Fixed(60.0))
+            .padding(10)
+            .style(iced::theme::Button::Secondary)
+            .on_press(Message::PinDigitEntered(d))
+        };
+        let keypad = column![
+            row![digit('1'), digit('2'), digit('3')].spacing(5),
+            row![digit('4'), digit('5'), digit('6')].spacing(5),
+            row![digit('7'), digit('8'), digit('9')].spacing(5),
+            row![
+                button(text("Del").size(14))
+                    .width(Length::Fixed(60.0))
+                    .padding(10)
+                    .on_press(Message::PinBackspace),
+                digit('0'),
+                button(text("OK").size(14))
+                    .width(Length::Fixed(60.0))
+                    .padding(10)
+                    .on_press(Message::PinSubmit),
+            ]
+            .spacing(5),
+        ]
+        .spacing(5);
+
+        let content = column![
+            text("Enter PIN").size(18),
+            text("•".repeat(self.pin_lock.entered_len())).size(24),
+            text(status).size(12),
+            keypad,
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .center_y()
+            .padding(10)
+            .style(iced::theme::Container::Box)
+            .into()
+    }
+
+    fn view_tabs(&self) -> Element<'_, Message> {
+        let tab_button = |label, tab| {
+            button(text(label).size(12))
+                .padding(6)
+                .style(if self.tab == tab {
+                    iced::theme::Button::Primary
+                } else {
+                    iced::theme::Button::Text
+                })
+                .on_press(Message::ShowTab(tab))
+        };
+
+        row![
+            tab_button("History", Tab::History),
+            tab_button("Sessions", Tab::Sessions),
+            tab_button("Stats", Tab::Stats),
+            tab_button("Settings", Tab::Settings),
+        ]
+        .spacing(5)
+        .into()
+    }
+
+    fn view_sessions(&self) -> Element<'_, Message> {
+        let mut content = column![
+            self.view_tabs(),
+            text("Sessions")
+                .size(18)
+                .width(Length::Fill)
+                .horizontal_alignment(alignment::Horizontal::Center),
+            button(text("Save session").size(12))
+                .padding(8)
+                .on_press(Message::SaveSession),
+        ]
+        .spacing(5)
+        .padding(10);
+
+        if self.sessions.is_empty() {
+            content = content.push(
+                text("No saved sessions yet.")
+                    .width(Length::Fill)
+                    .size(14)
+                    .horizontal_alignment(alignment::Horizontal::Center),
+            );
+        }
+
+        for (i, session) in self.sessions.iter().enumerate() {
+            content = content.push(
+                button(
+                    text(format!(
+                        "{} ({} entries)",
+                        session.date,
+                        session.entries.len()
+                    ))
+                    .size(12),
+                )
+                .width(Length::Fill)
+                .padding(8)
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::ReplaySession(i)),
+            );
+        }
+
+        content = content.push(
+            text("Collections")
+                .size(18)
+                .width(Length::Fill)
+                .horizontal_alignment(alignment::Horizontal::Center),
+        );
+        let mut save = button(text("Save ticked entries").size(12)).padding(8);
+        if !self.checked.is_empty() && !self.collection_name.trim().is_empty() {
+            save = save.on_press(Message::SaveCollection);
+        }
+        content = content.push(
+            row![
+                text_input("Collection name", &self.collection_name)
+                    .on_input(Message::CollectionNameChanged)
+                    .on_submit(Message::SaveCollection)
+                    .size(12),
+                save,
+            ]
+            .spacing(5),
+        );
+        for (i, collection) in self.collections.iter().enumerate() {
+            let status = match self.active_collection {
+                Some((active, next)) if active == i => format!(
+                    "{} · next {} of {}",
+                    collection.name,
+                    next + 1,
+                    collection.entries.len()
+                ),
+                _ => format!("{} ({} entries)", collection.name, collection.entries.len()),
+            };
+            let action = if self
+                .active_collection
+                .is_some_and(|(active, _)| active == i)
+            {
+                "Stop"
+            } else {
+                "Start"
+            };
+            content = content.push(
+                row![
+                    text(status).size(12).width(Length::Fill),
+                    widgets::action_button(text(action).size(12), Message::ToggleCollection(i)),
+                    widgets::action_button(text("Delete").size(12), Message::DeleteCollection(i)),
+                ]
+                .spacing(5)
+                .align_items(alignment::Alignment::Center),
+            );
+        }
+
+        container(scrollable(content))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(10)
+            .style(iced::theme::Container::Box)
+            .into()
+    }
+
+    fn view_stats(&self) -> Element<'_, Message> {
+        let histogram = stats::SizeHistogram {
+            counts: stats::size_histogram(&self.entries),
+        };
+        column![
+            self.view_tabs(),
+            text("Stats")
+                .size(18)
+                .width(Length::Fill)
+                .horizontal_alignment(alignment::Horizontal::Center),
+            text(format!(
+                "{} entries, {} of content",
+                self.entries.len(),
+                format_size(stats::storage_used(&self.entries))
+            ))
+            .size(14),
+            text("Entry sizes, in characters. Click a bar to see its first entry.").size(12),
+            canvas(histogram)
+                .width(Length::Fill)
+                .height(Length::Fixed(200.0)),
+        ]
+        .spacing(10)
+        .padding(10)
+        .into()
+    }
+
+    fn view_settings(&self) -> Element<'_, Message> {
+        let mut age_limit = row![
+            text("Delete unpinned entries older than").size(14),
+            pick_list(
+                &AgeLimit::CHOICES[..],
+                Some(self.age_limit),
+                Message::SetAgeLimit
+            )
+            .text_size(14),
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center);
+        if self.age_limit == AgeLimit::Custom {
+            age_limit = age_limit.push(
+                text_input("Days", &self.custom_age_limit)
+                    .on_input(Message::CustomAgeLimitChanged)
+                    .size(14)
+                    .width(Length::Fixed(80.0)),
+            );
+        }
+
+        let details = self.config.row_details;
+        let detail = |label, shown, set: fn(RowDetails, bool) -> RowDetails| {
+            checkbox(label, shown, move |shown| {
+                Message::SetRowDetails(set(details, shown))
+            })
+            .size(14)
+            .text_size(14)
+        };
+
+        let import: Element<Message> = match &self.text_import {
+            Some(import) => column![
+                text(format!("Importing {} of {}…", import.done, import.total)).size(14),
+                progress_bar(0.0..=import.total as f32, import.done as f32).height(8.0),
+            ]
+            .spacing(5)
+            .into(),
+            None => row![
+                text("Add entries from a text file").size(14),
+                widgets::action_button(text("Import…").size(14), Message::PickImportFile),
+            ]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center)
+            .into(),
+        };
+
+        let content = column![
+            self.view_tabs(),
+            text("Settings")
+                .size(18)
+                .width(Length::Fill)
+                .horizontal_alignment(alignment::Horizontal::Center),
+            age_limit,
+            text("Show on each entry").size(14),
+            detail("Content type", details.content_type, |d, shown| {
+                RowDetails {
+                    content_type: shown,
+                    ..d
+                }
+            }),
+            detail("Time copied", details.timestamp, |d, shown| RowDetails {
+                timestamp: shown,
+                ..d
+            }),
+            detail("Source app", details.source_app, |d, shown| RowDetails {
+                source_app: shown,
+                ..d
+            }),
+            detail("Character count", details.char_count, |d, shown| {
+                RowDetails {
+                    char_count: shown,
+                    ..d
+                }
+            }),
+            import,
+            text(format!(
+                "Saved to {}; other settings are edited there",
+                self.config_path.display()
+            ))
+            .size(12),
+        ]
+        .spacing(10)
+        .padding(10);
+
+        container(scrollable(content))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(10)
+            .style(iced::theme::Container::Box)
+            .into()
+    }
+
+    /// Shows two entries side by side, with lines only in the first in red
+    /// and lines only in the second in green.
+    fn view_comparison(&self, a: usize, b: usize) -> Element<'_, Message> {
+        let removed = iced::Color::from_rgb8(0xef, 0x44, 0x44);
+        let added = iced::Color::from_rgb8(0x10, 0xb9, 0x81);
+        let side = |line: Option<(&str, &str)>, color: Option<iced::Color>| {
+            let line = line.map_or_else(String::new, |(marker, line)| {
+                format!("{}{}", marker, widgets::display_text(line))
+            });
+            let line = text(line).size(12).width(Length::FillPortion(1));
+            match color {
+                Some(color) => line.style(iced::theme::Text::Color(color)),
+                None => line,
+            }
+        };
+
+        // Masking can start while the comparison is open
+        let masked = self.capture_masked;
+        let mut lines = column![].spacing(2);
+        if masked {
+            lines = lines.push(text(MASKED_PREVIEW).size(12));
+        }
+        for change in self.comparison.iter().filter(|_| !masked) {
+            let (left, right) = match change {
+                Change::Equal(line) => (
+                    side(Some(("  ", line)), None),
+                    side(Some(("  ", line)), None),
+                ),
+                Change::Removed(line) => {
+                    (side(Some(("- ", line)), Some(removed)), side(None, None))
+                }
+                Change::Added(line) => (side(None, None), side(Some(("+ ", line)), Some(added))),
+            };
+            lines = lines.push(row![left, right].spacing(10));
+        }
+
+        let name = |index: usize| {
+            let entry = &self.entries[index];
+            let name = entry.title.as_deref().or(entry.label());
+            let name = match name {
+                None if masked => MASKED_PREVIEW,
+                name => name.unwrap_or(&entry.content),
+            };
+            let name = widgets::display_text(name).replace('\n', "↵");
+            let name = if name.chars().count() > 30 {
+                format!("{}...", name.chars().take(30).collect::<String>())
+            } else {
+                name
+            };
+            text(name).size(14).width(Length::FillPortion(1))
+        };
+
+        let content = column![
+            row![name(a), name(b)].spacing(10),
+            container(scrollable(lines))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .padding(8)
+                .style(iced::theme::Container::Box),
+            row![
+                Space::new(Length::Fill, Length::Shrink),
+                button(text("Close").size(12))
+                    .padding(8)
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::CloseFullView),
+            ],
+        ]
+        .spacing(10)
+        .padding(10);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(10)
+            .style(iced::theme::Container::Box)
+            .into()
+    }
+
+    /// Shows an entry as a QR code in place of the list, for carrying it
+    /// over to a phone.
+    fn view_qr_code<'a>(&'a self, qr_code: &'a qr::QrView) -> Element<'a, Message> {
+        let mut content = column![
+            text("Scan with your phone's camera")
+                .size(18)
+                .width(Length::Fill)
+                .horizontal_alignment(alignment::Horizontal::Center),
+            canvas(qr_code).width(Length::Fill).height(Length::Fill),
+        ]
+        .spacing(10)
+        .padding(10);
+        if let Some(toast) = self.view_toast() {
+            content = content.push(toast);
+        }
+        let content = content.push(row![
+            Space::new(Length::Fill, Length::Shrink),
+            button(text("Close").size(12))
+                .padding(8)
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseQrCode),
+        ]);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(10)
+            .style(iced::theme::Container::Box)
+            .into()
+    }
+
+    /// `preview_height` within `PREVIEW_HEIGHTS`, as config.json can hold
+    /// anything.
+    fn preview_height(&self) -> f32 {
+        let (min, max) = (*PREVIEW_HEIGHTS.start(), *PREVIEW_HEIGHTS.end());
+        self.config.preview_height.clamp(min, max) as f32
+    }
+
+    /// The pane under the list showing more of the highlighted entry, or
+    /// `None` while nothing is highlighted.
+    fn view_preview(&self) -> Option<Element<'_, Message>> {
+        let entry = self.entries.get(self.selected?)?;
+        let preview = if self.capture_masked {
+            MASKED_PREVIEW.to_string()
+        } else {
+            widgets::display_text(&entry.content)
+        };
+        Some(
+            container(scrollable(text(preview).size(12).width(Length::Fill)))
+                .width(Length::Fill)
+                .height(Length::Fixed(self.preview_height()))
+                .padding(8)
+                .style(iced::theme::Container::Box)
+                .into(),
+        )
+    }
+
+    /// The history as rows with every action beside each entry.
+    fn view_list<'a>(&'a self, entries: Vec<(usize, &'a ClipboardEntry)>) -> Element<'a, Message> {
+        let mut list = column![].spacing(5);
+        for (i, entry) in entries {
+            let entry_text = widgets::entry_preview(
+                entry.title.as_deref().unwrap_or(&entry.content),
+                self.config.newline_display,
+                self.config.max_entry_lines,
+            );
+            let id = entry.id;
+            let masked = self.capture_masked;
+            let entry_text = if masked {
+                MASKED_PREVIEW.to_string()
+            } else if self.config.monitor_primary_selection {
+                format!("{} {}", entry.selection.badge(), entry_text)
+            } else {
+                entry_text
+            };
+            let entry_text = match entry.snippet_trigger() {
+                Some(trigger) if !masked => format!("⚡{} {}", trigger, entry_text),
+                _ => entry_text,
+            };
+
+            let pin_action = if entry.pinned { "Unpin" } else { "Pin" };
+            let expiry_action = if entry.expires_at.is_some() {
+                "Keep"
+            } else {
+                "Expire"
+            };
+            let badge = match entry.content_type {
+                ContentType::PlainText | ContentType::Unknown => None,
+                content_type => Some(
+                    text(match (content_type, &entry.mime_type) {
+                        // e.g. "PNG" for image/png
+                        (ContentType::Binary, Some(mime)) => {
+                            mime.rsplit('/').next().unwrap_or(mime).to_uppercase()
+                        }
+                        _ => content_type.label().to_string(),
+                    })
+                    .size(10)
+                    .width(Length::Fixed(36.0))
+                    .style(iced::theme::Text::Color(content_type.color())),
+                ),
+            };
+            let grey = iced::theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5));
+            let mut entry_content = match entry.label() {
+                Some(label) => {
+                    column![text(label).size(12), text(&entry_text).size(10).style(grey),]
+                }
+                None => column![text(&entry_text)
+                    .size(12)
+                    .horizontal_alignment(alignment::Horizontal::Left)],
+            };
+            if let Some(details) = entry_details(entry, self.config.row_details) {
+                entry_content = entry_content.push(text(details).size(10).style(grey));
+            }
+
+            let mut entry_row = Row::new()
+                .spacing(5)
+                .align_items(alignment::Alignment::Center)
+                .push(checkbox("", self.checked.contains(&i), move |_| {
+                    Message::ToggleChecked(id)
+                }));
+            if i < self.quick_access_slots() {
+                entry_row = entry_row.push(text(quick_access_label(i)).size(10).style(grey));
+            }
+            if self.config.row_details.content_type {
+                entry_row = entry_row.push(badge.map_or_else(
+                    || Element::from(Space::with_width(Length::Fixed(36.0))),
+                    Element::from,
+                ));
+            }
+            if let Some(language) = &entry.language {
+                entry_row = entry_row.push(text(language).size(10).style(grey));
+            }
+            let entry_row = entry_row
+                .push(
+                    button(entry_content)
+                        .width(Length::Fill)
+                        .padding(8)
+                        .style(iced::theme::Button::custom(style::EntryButton {
+                            selected: self.selected == Some(i),
+                        }))
+                        .on_press(Message::SelectEntry(id)),
+                )
+                .push(
+                    button(text(pin_action).size(12))
+                        .padding(8)
+                        .style(if entry.pinned {
+                            iced::theme::Button::Primary
+                        } else {
+                            iced::theme::Button::Text
+                        })
+                        .on_press(Message::TogglePin(id)),
+                )
+                .push(widgets::action_button(
+                    text(expiry_action).size(12),
+                    Message::ToggleExpiry(id),
+                ))
+                .push(widgets::action_button(
+                    text("View").size(12),
+                    Message::OpenFullView(id),
+                ))
+                .push(widgets::action_button(
+                    text("Delete").size(12),
+                    Message::DeleteEntry(id),
+                ));
+            let entry_row = if entry.content_type == ContentType::FilePath {
+                entry_row
+                    .push(widgets::action_button(
+                        text("Open").size(12),
+                        Message::OpenFilePath(entry.content.clone()),
+                    ))
+                    .push(widgets::action_button(
+                        text("Reveal").size(12),
+                        Message::RevealInFinder(entry.content.clone()),
+                    ))
+            } else if entry.content_type == ContentType::Email {
+                entry_row
+                    .push(widgets::action_button(
+                        text("Compose").size(12),
+                        Message::ComposeEmail(id),
+                    ))
+                    .push(widgets::action_button(
+                        text("Copy domain").size(12),
+                        Message::CopyEmailDomain(id),
+                    ))
+            } else {
+                entry_row
+            };
+
+            list = list.push(entry_row);
+        }
+        list.into()
+    }
+
+    /// The history as tiles showing the first lines of each entry and where
+    /// it was copied from. Clicking a tile pastes it; the other actions are
+    /// in the full view.
+    fn view_grid<'a>(&'a self, entries: Vec<(usize, &'a ClipboardEntry)>) -> Element<'a, Message> {
+        let columns = self.config.grid_columns.max(1);
+        let grey = iced::theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5));
+        let mut grid = column![].spacing(5);
+        for chunk in entries.chunks(columns) {
+            let mut tiles = Row::new().spacing(5);
+            for &(i, entry) in chunk {
+                let id = entry.id;
+                let masked = self.capture_masked;
+                let thumbnail = if masked {
+                    MASKED_PREVIEW.to_string()
+                } else if let Some(title) = &entry.title {
+                    title.clone()
+                } else {
+                    entry
+                        .content
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .take(2)
+                        .map(|line| widgets::entry_preview(line, NewlineDisplay::Symbol, 1))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                };
+                // The app's name is the end of its bundle identifier, e.g.
+                // "Safari" for com.apple.Safari
+                let app = entry
+                    .source_app
+                    .as_deref()
+                    .and_then(|app| app.rsplit('.').next())
+                    .unwrap_or("");
+                let label = match (entry.content_type, &entry.mime_type) {
+                    (ContentType::Binary, Some(mime)) => {
+                        mime.rsplit('/').next().unwrap_or(mime).to_uppercase()
+                    }
+                    (content_type, _) => content_type.label().to_string(),
+                };
+                let label = match entry.snippet_trigger() {
+                    Some(trigger) if !masked => format!("{} ⚡{}", label, trigger),
+                    _ => label,
+                };
+                let label = match &entry.language {
+                    Some(language) => format!("{} {}", label, language),
+                    None => label,
+                };
+                let label = if i < self.quick_access_slots() {
+                    format!("{} {}", quick_access_label(i), label)
+                } else {
+                    label
+                };
+                let tile = column![
+                    row![
+                        text(label)
+                            .size(10)
+                            .width(Length::Fill)
+                            .style(iced::theme::Text::Color(entry.content_type.color())),
+                        text(app).size(10).style(grey),
+                    ]
+                    .spacing(5),
+                    text(thumbnail).size(12),
+                ]
+                .spacing(4);
+                tiles = tiles.push(
+                    button(tile)
+                        .width(Length::FillPortion(1))
+                        .height(Length::Fixed(72.0))
+                        .padding(8)
+                        .style(iced::theme::Button::custom(style::EntryButton {
+                            selected: self.selected == Some(i),
+                        }))
+                        .on_press(Message::SelectEntry(id)),
+                );
+            }
+            // Keeps the tiles in a short last row as wide as the others
+            for _ in chunk.len()..columns {
+                tiles = tiles.push(Space::with_width(Length::FillPortion(1)));
+            }
+            grid = grid.push(tiles);
+        }
+        grid.into()
+    }
+
+    /// Recent searches under the empty search field, to search again with a
+    /// click.
+    fn view_search_history(&self) -> Element<'_, Message> {
+        let mut recent = column![].spacing(2).push(
+            row![
+                text("Recent searches").size(12).width(Length::Fill),
+                button(text("Clear").size(12))
+                    .padding(4)
+                    .style(iced::theme::Button::Text)
+                    .on_press(Message::ClearSearchHistory),
+            ]
+            .align_items(alignment::Alignment::Center),
+        );
+        for query in &self.search_history {
+            recent = recent.push(
+                button(text(query).size(12))
+                    .width(Length::Fill)
+                    .padding(4)
+                    .style(iced::theme::Button::Text)
+                    .on_press(Message::SearchHistorySelected(query.clone())),
+            );
+        }
+        container(recent)
+            .width(Length::Fill)
+            .padding(5)
+            .style(iced::theme::Container::Box)
+            .into()
+    }
+
+    /// Renders the complete content of a single entry, shown on top of the
+    /// list so long entries can be read before pasting them.
+    fn view_full_entry<'a>(
+        &'a self,
+        index: usize,
+        entry: &'a ClipboardEntry,
+    ) -> Element<'a, Message> {
+        let id = entry.id;
+        let mut actions = row![
+            button(text("Copy").size(12))
+                .padding(8)
+                .on_press(Message::CopyEntry(id)),
+            button(text("Copy escaped").size(12))
+                .padding(8)
+                .on_press(Message::CopyJsonEscaped(id)),
+            button(text("Copy as JSON").size(12))
+                .padding(8)
+                .on_press(Message::CopyEntryAsJson(id)),
+            button(text("Copy & Paste").size(12))
+                .padding(8)
+                .on_press(Message::SelectEntry(id)),
+            button(text("Copy time").size(12))
+                .padding(8)
+                .on_press(Message::CopyTimestamp(id)),
+            button(text("Share").size(12))
+                .padding(8)
+                .on_press(Message::ShareEntry(id)),
+        ]
+        .spacing(5);
+        if entry.content.len() <= qr::MAX_BYTES && entry.content_type != ContentType::Binary {
+            actions = actions.push(
+                button(text("QR code").size(12))
+                    .padding(8)
+                    .on_press(Message::ShowQrCode(id)),
+            );
+        }
+        let actions = actions.push(Space::new(Length::Fill, Length::Shrink)).push(
+            button(text("Close").size(12))
+                .padding(8)
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseFullView),
+        );
+
+        let is_markdown =
+            self.config.render_markdown && markdown::looks_like_markdown(&entry.content);
+        let title: Element<Message> = match &self.title_edit {
+            Some((editing, draft)) if *editing == index => text_input("Title", draft)
+                .id(text_input::Id::new(TITLE_INPUT_ID))
+                .on_input(Message::TitleDraftChanged)
+                .on_submit(Message::SetEntryTitle(id, draft.clone()))
+                .padding(6)
+                .size(16)
+                .into(),
+            _ => text(
+                self.entries
+                    .get(index)
+                    .and_then(|e| e.title.as_deref())
+                    .unwrap_or("Full Content"),
+            )
+            .size(18)
+            .width(Length::Fill)
+            .horizontal_alignment(alignment::Horizontal::Center)
+            .into(),
+        };
+        let mut header = Row::new().push(title);
+        if is_markdown {
+            let label = if self.markdown_rendered {
+                "Raw"
+            } else {
+                "Rendered"
+            };
+            header = header.push(widgets::action_button(
+                text(label).size(12),
+                Message::ToggleMarkdown,
+            ));
+        }
+
+        let body: Element<Message> = if is_markdown && self.markdown_rendered {
+            markdown::render(&entry.content)
+        } else {
+            text(widgets::display_text(&entry.content))
+                .size(13)
+                .width(Length::Fill)
+                .into()
+        };
+
+        let label = self
+            .entries
+            .get(index)
+            .and_then(|e| e.label.as_deref())
+            .unwrap_or_default();
+        let label_input = text_input("Add a label, e.g. \"API key for staging\"", label)
+            .on_input(move |label| Message::SetLabel(id, label))
+            .on_submit(Message::CloseFullView)
+            .padding(8)
+            .size(12);
+        let trigger = self
+            .entries
+            .get(index)
+            .and_then(|e| e.snippet_trigger.as_deref())
+            .unwrap_or_default();
+        let trigger_input = text_input(
+            "Snippet trigger: copy e.g. /addr to get this entry",
+            trigger,
+        )
+        .on_input(move |trigger| Message::SetSnippetTrigger(id, trigger))
+        .on_submit(Message::CloseFullView)
+        .padding(8)
+        .size(12);
+
+        let mut content = column![
+            header.align_items(alignment::Alignment::Center),
+            label_input,
+            trigger_input,
+            // Double-click the content to give the entry a title
+            mouse_area(
+                container(scrollable(body))
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(8)
+                    .style(iced::theme::Container::Box)
+            )
+            .on_press(Message::PreviewClicked(id)),
+        ]
+        .spacing(10)
+        .padding(10);
+        if let Some(toast) = self.view_toast() {
+            content = content.push(toast);
+        }
+        let content = content.push(actions);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(10)
+            .style(iced::theme::Container::Box)
+            .into()
+    }
+}
+
+fn main() -> iced::Result {
+    env_logger::init();
+
+    let args: Vec<String> = env::args().collect();
+
+    if args.iter().any(|arg| arg == "--list-profiles") {
+        match storage::data_dir().and_then(|dir| storage::list_profiles(&dir)) {
+            Ok(profiles) => profiles.iter().for_each(|profile| println!("{}", profile)),
+            Err(e) => eprintln!("Failed to list profiles: {}", e),
+        }
+        return Ok(());
+    }
+
+    let profile = cli::flag_value(&args, "--profile").map(str::to_string);
+    if let Some(name) = profile.as_deref() {
+        if !storage::is_valid_profile_name(name) {
+            eprintln!("Profile names can only have letters, digits, - and _");
+            std::process::exit(1);
+        }
+    }
+
+    if args.iter().any(|arg| arg == "--set-pin") {
+        match storage::data_dir().and_then(|dir| {
+            auth::set_pin_interactive(&storage::config_path(&dir, profile.as_deref()))
+        }) {
+            Ok(()) => println!("PIN set. It will be required to open the history window."),
+            Err(e) => {
+                eprintln!("Failed to set PIN: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--validate-config") {
+        match cli::run_validate_config(profile.as_deref()) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Failed to read config: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if args.iter().any(|arg| arg == "--doctor") {
+        std::process::exit(doctor::run(profile.as_deref()));
+    }
+
+    if args.iter().any(|arg| arg == "--status") {
+        std::process::exit(diagnostics::run_status(profile.as_deref()));
+    }
+
+    if let Some(shell) = cli::flag_value(&args, "--completions") {
+        match shell.parse() {
+            Ok(shell) => print!("{}", completions::generate(shell)),
+            Err(e) => eprintln!("{}", e),
+        }
+        return Ok(());
+    }
+
+    if let Some(shell) = cli::flag_value(&args, "--install-completion") {
+        let dry_run = args.iter().any(|arg| arg == "--dry-run");
+        match shell.parse() {
+            Ok(shell) => {
+                if let Err(e) = completions::install(shell, dry_run) {
+                    eprintln!("Failed to install completions: {}", e);
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--tail") {
+        let json = args.iter().any(|arg| arg == "--json");
+        if let Err(e) = cli::run_tail(json, profile.as_deref()) {
+            eprintln!("Failed to watch clipboard: {}", e);
+        }
+        return Ok(());
+    }
+
+    if let Some(index) = cli::flag_value(&args, "--format-entry") {
+        let template = args
+            .iter()
+            .position(|arg| arg == "--format-entry")
+            .and_then(|i| args.get(i + 2));
+        let escape = !args.iter().any(|arg| arg == "--no-escape");
+        match (index.parse(), template) {
+            (Ok(index), Some(template)) => {
+                if let Err(e) = cli::run_format_entry(index, template, escape, profile.as_deref()) {
+                    eprintln!("Failed to format entry: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            _ => eprintln!("Usage: mac-clip --format-entry <index> <template> [--no-escape]"),
+        }
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--daemon-logs") {
+        match cli::flag_value(&args, "--lines").map_or(Ok(50), str::parse) {
+            Ok(lines) => {
+                if let Err(e) = cli::run_daemon_logs(lines) {
+                    eprintln!("Failed to follow the daemon log: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            Err(_) => eprintln!("Usage: mac-clip --daemon-logs [--lines <count>]"),
+        }
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--auto-paste-last") {
+        match cli::flag_value(&args, "-n").map_or(Ok(1), str::parse) {
+            Ok(nth) => {
+                if let Err(e) = cli::run_auto_paste_last(nth, profile.as_deref()) {
+                    eprintln!("Failed to paste: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            Err(_) => eprintln!("Usage: mac-clip --auto-paste-last [-n <count back>]"),
+        }
+        return Ok(());
+    }
+
+    if let Some(query) = cli::flag_value(&args, "--search") {
+        let include_archives = args.iter().any(|arg| arg == "--include-archives");
+        if let Err(e) = cli::run_search(query, include_archives, profile.as_deref()) {
+            eprintln!("Failed to search history: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(i) = args.iter().position(|arg| arg == "--diff") {
+        let json = match cli::flag_value(&args, "--format") {
+            None | Some("text") => Some(false),
+            Some("json") => Some(true),
+            Some(_) => None,
+        };
+        match (args.get(i + 1), args.get(i + 2), json) {
+            (Some(old), Some(new), Some(json)) => {
+                if let Err(e) = cli::run_diff(Path::new(old), Path::new(new), json) {
+                    eprintln!("Failed to compare histories: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            _ => {
+                eprintln!("Usage: mac-clip --diff <old history> <new history> [--format text|json]")
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = cli::flag_value(&args, "--export-bundle") {
+        if let Err(e) = cli::run_export_bundle(Path::new(path), profile.as_deref()) {
+            eprintln!("Failed to export bundle: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--copy") {
+        match cli::flag_value(&args, "--copy") {
+            Some(text) => {
+                if let Err(e) = cli::run_copy(text, profile.as_deref()) {
+                    eprintln!("Failed to copy: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            None => eprintln!("Usage: mac-clip --copy <text>, or --copy - to read it from stdin"),
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = cli::flag_value(&args, "--export-plist") {
+        if let Err(e) = cli::run_export_plist(Path::new(path), profile.as_deref()) {
+            eprintln!("Failed to export plist: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = cli::flag_value(&args, "--import-bundle") {
+        if let Err(e) = cli::run_import_bundle(Path::new(path), profile.as_deref()) {
+            eprintln!("Failed to import bundle: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--migrate-from-pasta") {
+        let path = cli::flag_value(&args, "--migrate-from-pasta").map(Path::new);
+        if let Err(e) = cli::run_migrate_from_pasta(path, profile.as_deref()) {
+            eprintln!("Failed to migrate from Pasta: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--reset-telemetry") {
+        if let Err(e) = cli::run_reset_telemetry(profile.as_deref()) {
+            eprintln!("Failed to reset telemetry: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if env::args().any(|arg| arg == "--gc") {
+        if let Err(e) = cli::run_gc(profile.as_deref()) {
+            eprintln!("Failed to run GC: {}", e);
+        }
+        return Ok(());
+    }
+
+    // Check if --daemon flag is provided
+    if env::args().any(|arg| arg == "--daemon") {
+        if let Err(e) = daemon::setup_daemon() {
+            eprintln!("Failed to setup daemon: {}", e);
+        } else {
+            println!("Mac-Clip daemon setup complete. The application will now start automatically when you log in.");
+            return Ok(());
+        }
+    }
+
+    let position = storage::profile_dir(profile.as_deref())
+        .ok()
+        .and_then(|dir| {
+            window_position(
+                &Config::load_profile(profile.as_deref()),
+                window_state::load(&dir.join("window.json")),
+            )
+        })
+        .map_or(Position::Centered, |position| {
+            Position::Specific(position.x, position.y)
+        });
+
+    let lock_path = storage::runtime_dir()
+        .expect("Failed to create runtime directory")
+        .join(instance::LOCK_FILE);
+    let instance_lock = match InstanceLock::acquire(&lock_path) {
+        Ok(lock) => lock,
+        Err(e @ instance::LockError::AlreadyRunning(_)) => {
+            eprintln!("{}, exiting", e);
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("Failed to lock {}: {}", lock_path.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    MacClip::run(Settings {
+        window: window::Settings {
+            size: (WINDOW_WIDTH, WINDOW_HEIGHT),
+            position,
+            visible: false,
+            ..window::Settings::default()
+        },
+        flags: Flags {
+            instance_lock: Some(instance_lock),
+            profile,
         },
         ..Settings::default()
     })
@@ -0,0 +1,118 @@
+use crate::Message;
+use iced::{
+    theme,
+    widget::{container, horizontal_rule, text, Column},
+    Element, Font, Length,
+};
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag, TagEnd};
+
+const INDENT: f32 = 16.0;
+
+/// Cheap check for whether `content` is probably Markdown, used to decide
+/// whether to offer the rendered view.
+pub fn looks_like_markdown(content: &str) -> bool {
+    content.contains("**")
+        || content.contains('`')
+        || content.lines().any(|line| {
+            let line = line.trim_start();
+            line.starts_with("# ")
+                || line.starts_with("## ")
+                || line.starts_with("### ")
+                || line.starts_with("- ")
+                || line.starts_with("> ")
+        })
+}
+
+#[derive(Clone, Copy)]
+enum Block {
+    Paragraph,
+    Heading(HeadingLevel),
+    Code,
+}
+
+/// Renders Markdown as a read-only column of blocks: headings, paragraphs,
+/// lists, quotes, code blocks and rules. iced's text widget has a single
+/// style per widget, so inline emphasis and links show as plain text.
+pub fn render<'a>(content: &str) -> Element<'a, Message> {
+    let mut blocks = Column::new().spacing(6).width(Length::Fill);
+    let mut buffer = String::new();
+    let mut block = Block::Paragraph;
+    let mut quote_depth: usize = 0;
+    let mut lists: Vec<Option<u64>> = Vec::new();
+
+    for event in Parser::new(content) {
+        let indent = quote_depth + lists.len();
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => block = Block::Heading(level),
+            Event::Start(Tag::CodeBlock(_)) => block = Block::Code,
+            Event::Start(Tag::BlockQuote(_)) => quote_depth += 1,
+            Event::End(TagEnd::BlockQuote(_)) => quote_depth = quote_depth.saturating_sub(1),
+            Event::Start(Tag::List(start)) => {
+                blocks = push_block(blocks, &mut buffer, block, indent);
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                blocks = push_block(blocks, &mut buffer, block, indent);
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        buffer.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => buffer.push_str("• "),
+                }
+            }
+            Event::End(TagEnd::Paragraph | TagEnd::Item) => {
+                blocks = push_block(blocks, &mut buffer, block, indent);
+            }
+            Event::End(TagEnd::Heading(_) | TagEnd::CodeBlock) => {
+                blocks = push_block(blocks, &mut buffer, block, indent);
+                block = Block::Paragraph;
+            }
+            Event::Text(t) | Event::Code(t) => buffer.push_str(&t),
+            Event::SoftBreak => buffer.push(' '),
+            Event::HardBreak => buffer.push('\n'),
+            Event::TaskListMarker(done) => buffer.push_str(if done { "[x] " } else { "[ ] " }),
+            Event::Rule => blocks = blocks.push(horizontal_rule(1)),
+            _ => {}
+        }
+    }
+
+    push_block(blocks, &mut buffer, block, 0).into()
+}
+
+/// Moves the buffered text into `blocks` styled for `block`, doing nothing
+/// if there's no text yet.
+fn push_block<'a>(
+    blocks: Column<'a, Message>,
+    buffer: &mut String,
+    block: Block,
+    indent: usize,
+) -> Column<'a, Message> {
+    let content = std::mem::take(buffer);
+    let content = content.trim_end();
+    if content.is_empty() {
+        return blocks;
+    }
+
+    let widget: Element<'a, Message> = match block {
+        Block::Paragraph => text(content).size(13).into(),
+        Block::Heading(level) => text(content)
+            .size(match level {
+                HeadingLevel::H1 => 22,
+                HeadingLevel::H2 => 19,
+                HeadingLevel::H3 => 16,
+                _ => 14,
+            })
+            .into(),
+        Block::Code => container(text(content).size(12).font(Font::MONOSPACE))
+            .width(Length::Fill)
+            .padding(6)
+            .style(theme::Container::Box)
+            .into(),
+    };
+
+    blocks.push(container(widget).padding([0.0, 0.0, 0.0, INDENT * indent as f32]))
+}
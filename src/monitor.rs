@@ -0,0 +1,317 @@
+use crate::{
+    binary,
+    clipboard::SharedClipboard,
+    macos::{self, Flavors},
+};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicIsize, AtomicU64, Ordering},
+        Arc, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Restarts a watchdog makes within `RESTART_WINDOW` before holding off.
+const MAX_RESTARTS: usize = 5;
+const RESTART_WINDOW: Duration = Duration::from_secs(60);
+
+/// How often a monitor's heartbeat is checked. Each check expects at least
+/// one trip round the monitor's loop since the last.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The pasteboard change count right after mac-clip last wrote to it.
+static OWN_WRITE: AtomicIsize = AtomicIsize::new(isize::MIN);
+
+/// Records that mac-clip itself just wrote to the clipboard, so
+/// `watch_clipboard` doesn't count the write as a copy.
+pub fn note_own_write() {
+    if let Some(count) = macos::pasteboard_change_count() {
+        OWN_WRITE.store(count, Ordering::Relaxed);
+    }
+}
+
+/// Which selection a clipboard change came from. `Primary` is the X11
+/// mouse selection and only exists on Linux.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Selection {
+    #[default]
+    Clipboard,
+    Primary,
+}
+
+impl Selection {
+    pub fn is_clipboard(&self) -> bool {
+        *self == Selection::Clipboard
+    }
+
+    pub fn badge(self) -> &'static str {
+        match self {
+            Selection::Clipboard => "[C]",
+            Selection::Primary => "[P]",
+        }
+    }
+}
+
+/// Polls the clipboard every `interval` and calls `on_change` with each new,
+/// non-empty text value. With a non-zero `settle`, a change is only reported
+/// if the clipboard still holds the same text `settle` after it was first
+/// seen, which skips the intermediate values some apps write and immediately
+/// replace.
+///
+/// With a `double_copy` window, copying the same text twice within it
+/// reports the text again, with `true`, even if it didn't change and
+/// without waiting for it to settle. Writes recorded with
+/// `note_own_write` don't count. Only macOS counts copies, so elsewhere
+/// this never happens.
+///
+/// Beats `heartbeat` on every poll. Runs forever, so call it from a
+/// dedicated thread.
+pub fn watch_clipboard(
+    clipboard: SharedClipboard,
+    interval: Duration,
+    settle: Duration,
+    double_copy: Option<Duration>,
+    heartbeat: &HeartbeatMonitor,
+    mut on_change: impl FnMut(String, bool),
+) {
+    // A monitor that panicked while reading leaves the lock poisoned, which
+    // mustn't stop the restarted one
+    let read = || {
+        clipboard
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_text()
+            .ok()
+    };
+
+    let mut last_content = String::new();
+    let mut last_count = macos::pasteboard_change_count();
+    let mut last_copy_at: Option<Instant> = None;
+    loop {
+        heartbeat.beat();
+        thread::sleep(interval);
+
+        // Copying the same text again only shows in the change count
+        let count = macos::pasteboard_change_count();
+        let own_write = count.is_some_and(|count| count == OWN_WRITE.load(Ordering::Relaxed));
+        let copies = match (count, last_count) {
+            (Some(count), Some(last)) if !own_write => count.saturating_sub(last).max(0),
+            _ => 0,
+        };
+        last_count = count;
+        let now = Instant::now();
+        let quick = double_copy.is_some_and(|window| {
+            copies >= 2
+                || copies == 1 && last_copy_at.is_some_and(|at| now.duration_since(at) <= window)
+        });
+
+        let content = read();
+        // Two different texts in quick succession are just two copies
+        let double_copied = quick && content.as_ref() == Some(&last_content);
+        if copies > 0 {
+            // A third quick copy starts a new pair
+            last_copy_at = (!double_copied).then_some(now);
+        }
+        let Some(content) = content else {
+            continue;
+        };
+        if content.is_empty() {
+            continue;
+        }
+        if double_copied {
+            info!("Detected a double copy");
+            last_content = content.clone();
+            on_change(content, true);
+            continue;
+        }
+        if content == last_content {
+            continue;
+        }
+        if !settle.is_zero() {
+            thread::sleep(settle);
+            if read().as_ref() != Some(&content) {
+                // Whatever replaced it gets its own settle period next time
+                info!("Ignoring transient clipboard change");
+                continue;
+            }
+        }
+
+        info!("Detected clipboard change: {}", content);
+        last_content = content.clone();
+        on_change(content, false);
+    }
+}
+
+/// What a heartbeat check found out, when it changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatChange {
+    /// No beat since the last check.
+    Stalled,
+    /// Beating again after a stall.
+    Recovered,
+}
+
+/// Counts a monitor's trips round its loop, so another thread can spot it
+/// hanging, e.g. waiting forever for the clipboard lock. A panic is left to
+/// `WatchdogThread`, but a hung thread can't be restarted, so this only
+/// reports it. Clones share the count and keep their own checks.
+#[derive(Debug, Clone, Default)]
+pub struct HeartbeatMonitor {
+    beats: Arc<AtomicU64>,
+    last_seen: u64,
+    stalled: bool,
+}
+
+impl HeartbeatMonitor {
+    pub fn beat(&self) {
+        self.beats.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Compares the count with the last check's. Reports only changes, and
+    /// nothing until the monitor has started.
+    pub fn check(&mut self) -> Option<HeartbeatChange> {
+        let beats = self.beats.load(Ordering::Relaxed);
+        let beating = beats != self.last_seen;
+        self.last_seen = beats;
+        // Still beating, or still stalled
+        if beats == 0 || beating != self.stalled {
+            return None;
+        }
+        self.stalled = !beating;
+        Some(if beating {
+            HeartbeatChange::Recovered
+        } else {
+            HeartbeatChange::Stalled
+        })
+    }
+}
+
+/// Keeps a monitor running: runs it on a thread of its own and starts it
+/// again whenever it panics or returns, which a monitor only does when
+/// something went wrong.
+pub struct WatchdogThread {
+    name: String,
+    /// When the monitor was restarted within the last `RESTART_WINDOW`.
+    restarts: VecDeque<Instant>,
+}
+
+impl WatchdogThread {
+    pub fn new(name: &str) -> Self {
+        WatchdogThread {
+            name: name.to_string(),
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Runs `monitor` for as long as the app runs. Call it from a dedicated
+    /// thread.
+    pub fn run(mut self, monitor: impl Fn() + Send + Sync + 'static) {
+        let monitor = Arc::new(monitor);
+        loop {
+            let body = Arc::clone(&monitor);
+            let handle = match thread::Builder::new()
+                .name(self.name.clone())
+                .spawn(move || body())
+            {
+                Ok(handle) => handle,
+                Err(e) => {
+                    error!("Failed to start the {}: {}", self.name, e);
+                    return;
+                }
+            };
+            match handle.join() {
+                Ok(()) => warn!("The {} stopped", self.name),
+                Err(_) => warn!("The {} panicked", self.name),
+            }
+
+            let delay = self.schedule_restart(Instant::now());
+            if !delay.is_zero() {
+                warn!(
+                    "The {} was restarted {} times in the last minute, waiting {}s",
+                    self.name,
+                    MAX_RESTARTS,
+                    delay.as_secs()
+                );
+                thread::sleep(delay);
+            }
+            warn!("Restarting the {}", self.name);
+        }
+    }
+
+    /// Records a restart wanted at `now` and returns how long to hold it off
+    /// so that at most `MAX_RESTARTS` fall within any `RESTART_WINDOW`.
+    pub fn schedule_restart(&mut self, now: Instant) -> Duration {
+        while self
+            .restarts
+            .front()
+            .is_some_and(|&at| now.duration_since(at) >= RESTART_WINDOW)
+        {
+            self.restarts.pop_front();
+        }
+        let delay = match self.restarts.front() {
+            Some(&oldest) if self.restarts.len() >= MAX_RESTARTS => {
+                RESTART_WINDOW - now.duration_since(oldest)
+            }
+            _ => Duration::ZERO,
+        };
+        self.restarts.push_back(now + delay);
+        delay
+    }
+}
+
+/// Polls the pasteboard's change count every `interval` and calls
+/// `on_change` with every representation of each copy that has no text.
+/// Only macOS counts changes, so elsewhere this returns straight away.
+pub fn watch_binary(interval: Duration, mut on_change: impl FnMut(Flavors)) {
+    let mut last_count = macos::pasteboard_change_count();
+    if last_count.is_none() {
+        return;
+    }
+    loop {
+        thread::sleep(interval);
+
+        let count = macos::pasteboard_change_count();
+        if count == last_count {
+            continue;
+        }
+        last_count = count;
+        let flavors = macos::read_pasteboard_flavors();
+        if flavors.is_empty() || binary::has_text(&flavors) {
+            continue;
+        }
+        info!("Detected a copy without text");
+        on_change(flavors);
+    }
+}
+
+/// Polls the X11 `PRIMARY` selection every `interval` and calls `on_change`
+/// with each new, non-empty value. Only returns if the X server connection
+/// can't be set up.
+#[cfg(target_os = "linux")]
+pub fn watch_primary_selection(
+    interval: Duration,
+    mut on_change: impl FnMut(String),
+) -> Result<(), x11_clipboard::error::Error> {
+    let clipboard = x11_clipboard::Clipboard::new()?;
+    let atoms = &clipboard.getter.atoms;
+    let mut last_content = String::new();
+    loop {
+        thread::sleep(interval);
+
+        let Ok(bytes) = clipboard.load(atoms.primary, atoms.utf8_string, atoms.property, interval)
+        else {
+            continue;
+        };
+        let content = String::from_utf8_lossy(&bytes).into_owned();
+        if !content.is_empty() && content != last_content {
+            info!("Detected primary selection change");
+            last_content = content.clone();
+            on_change(content);
+        }
+    }
+}
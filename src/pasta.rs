@@ -0,0 +1,174 @@
+//! Reading the history of Pasta, another clipboard manager, for
+//! `--migrate-from-pasta`.
+//!
+//! Pasta keeps its history in a Core Data SQLite store, whose table and
+//! column names have changed between versions, so the table is found by its
+//! columns rather than by name.
+
+use crate::{storage, ClipboardEntry};
+use rusqlite::{types::Value, Connection, OpenFlags};
+use std::{
+    cmp::Reverse,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Where Pasta keeps its database, relative to the home directory.
+const DB_PATH: &str =
+    "Library/Containers/com.tomtasche.pasta/Data/Library/Application Support/pasta/database.sqlite";
+
+/// Columns holding the copied text, most likely first.
+const TEXT_COLUMNS: &[&str] = &[
+    "ZTEXT",
+    "ZCONTENT",
+    "ZSTRING",
+    "ZPLAINTEXT",
+    "text",
+    "content",
+];
+/// Columns holding when it was copied.
+const DATE_COLUMNS: &[&str] = &[
+    "ZDATE",
+    "ZCREATEDAT",
+    "ZCREATIONDATE",
+    "ZTIMESTAMP",
+    "date",
+    "created_at",
+];
+/// Columns holding the bundle identifier of the app it was copied in.
+const APP_COLUMNS: &[&str] = &[
+    "ZBUNDLEIDENTIFIER",
+    "ZAPPBUNDLEID",
+    "ZSOURCEAPP",
+    "bundle_id",
+    "source_app",
+];
+
+/// Seconds from the Unix epoch to 2001-01-01, which Core Data counts dates
+/// from.
+const CORE_DATA_EPOCH: f64 = 978_307_200.0;
+
+/// Pasta's database in the user's home directory.
+pub fn default_db_path() -> Option<PathBuf> {
+    directories::BaseDirs::new().map(|dirs| dirs.home_dir().join(DB_PATH))
+}
+
+/// The text entries in the Pasta database at `path`, newest first. Entries
+/// without text, like images, are skipped.
+pub fn parse_pasta_db(path: &Path) -> io::Result<Vec<ClipboardEntry>> {
+    let db = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(io::Error::other)?;
+    let table = find_table(&db)?;
+    let column = |column: Option<&String>| column.map_or("NULL".to_string(), |c| quote(c));
+    let sql = format!(
+        "SELECT {}, {}, {} FROM {}",
+        quote(&table.text),
+        column(table.date.as_ref()),
+        column(table.app.as_ref()),
+        quote(&table.name)
+    );
+    let core_data = table.name.starts_with('Z');
+
+    let mut statement = db.prepare(&sql).map_err(io::Error::other)?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, Value>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })
+        .map_err(io::Error::other)?;
+    let mut entries = Vec::new();
+    for row in rows {
+        let (content, date, source_app) = row.map_err(io::Error::other)?;
+        let Some(content) = content.filter(|c| !c.trim().is_empty()) else {
+            continue;
+        };
+        entries.push(ClipboardEntry {
+            content,
+            timestamp: unix_time(&date, core_data).unwrap_or_else(storage::unix_now),
+            source_app: source_app.filter(|app| !app.is_empty()),
+            ..Default::default()
+        });
+    }
+    entries.sort_by_key(|e| Reverse(e.timestamp));
+    Ok(entries)
+}
+
+/// The table holding the history and which of its columns to read.
+struct Table {
+    name: String,
+    text: String,
+    date: Option<String>,
+    app: Option<String>,
+}
+
+fn find_table(db: &Connection) -> io::Result<Table> {
+    let mut statement = db
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'")
+        .map_err(io::Error::other)?;
+    let names: Vec<String> = statement
+        .query_map([], |row| row.get(0))
+        .and_then(Iterator::collect)
+        .map_err(io::Error::other)?;
+
+    for name in names {
+        let mut info = db
+            .prepare(&format!("PRAGMA table_info({})", quote(&name)))
+            .map_err(io::Error::other)?;
+        let columns: Vec<String> = info
+            .query_map([], |row| row.get(1))
+            .and_then(Iterator::collect)
+            .map_err(io::Error::other)?;
+        let find = |candidates: &[&str]| {
+            candidates.iter().find_map(|candidate| {
+                columns
+                    .iter()
+                    .find(|column| column.eq_ignore_ascii_case(candidate))
+                    .cloned()
+            })
+        };
+        if let Some(text) = find(TEXT_COLUMNS) {
+            return Ok(Table {
+                name,
+                text,
+                date: find(DATE_COLUMNS),
+                app: find(APP_COLUMNS),
+            });
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "no table of clipboard text found; is this Pasta's database?",
+    ))
+}
+
+/// A date column's value as Unix time. Core Data stores seconds since 2001;
+/// other numbers are taken as Unix seconds, or milliseconds if they're too
+/// large for that, and text as RFC 3339.
+fn unix_time(value: &Value, core_data: bool) -> Option<u64> {
+    let seconds = match value {
+        Value::Integer(n) => *n as f64,
+        Value::Real(n) => *n,
+        Value::Text(text) => {
+            return chrono::DateTime::parse_from_rfc3339(text)
+                .ok()
+                .and_then(|time| u64::try_from(time.timestamp()).ok())
+        }
+        _ => return None,
+    };
+    let seconds = if core_data {
+        seconds + CORE_DATA_EPOCH
+    } else if seconds > 1e11 {
+        seconds / 1000.0
+    } else {
+        seconds
+    };
+    (seconds >= 0.0).then_some(seconds as u64)
+}
+
+/// Quotes an identifier for SQL.
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
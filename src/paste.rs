@@ -0,0 +1,116 @@
+//! SmartPaste: adjusting text for the app it's pasted into, e.g. dropping
+//! the trailing newline that would run a command in Terminal.
+
+use crate::{config::SmartPasteRule, transform::RegexTransforms};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A change applied to text before it's pasted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    /// Removes newlines at the end, so pasting into a shell doesn't run the
+    /// command straight away.
+    StripTrailingNewlines,
+    /// Rewrites Markdown into Slack's message markup.
+    SlackMarkdown,
+    /// Replaces typographic punctuation with ASCII and drops any other
+    /// non-ASCII characters.
+    StripUnicode,
+}
+
+impl Transform {
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Transform::StripTrailingNewlines => text.trim_end_matches(['\n', '\r']).to_string(),
+            Transform::SlackMarkdown => slack_markdown(text),
+            Transform::StripUnicode => strip_unicode(text),
+        }
+    }
+}
+
+/// The rules used when the config doesn't list any.
+pub fn default_rules() -> Vec<SmartPasteRule> {
+    let rule = |app_bundle_id: &str, transforms: &[Transform]| SmartPasteRule {
+        app_bundle_id: app_bundle_id.to_string(),
+        transforms: transforms.to_vec(),
+    };
+    vec![
+        rule("com.apple.Terminal", &[Transform::StripTrailingNewlines]),
+        rule("com.googlecode.iterm2", &[Transform::StripTrailingNewlines]),
+        rule("com.tinyspeck.slackmacgap", &[Transform::SlackMarkdown]),
+        rule("com.microsoft.Excel", &[Transform::StripUnicode]),
+    ]
+}
+
+/// Returns `text` with the transforms of the first rule for `bundle_id`
+/// applied, or `None` if no rule matches.
+pub fn transform(rules: &[SmartPasteRule], bundle_id: &str, text: &str) -> Option<String> {
+    let rule = rules.iter().find(|rule| rule.app_bundle_id == bundle_id)?;
+    Some(
+        rule.transforms
+            .iter()
+            .fold(text.to_string(), |text, transform| transform.apply(&text)),
+    )
+}
+
+/// Returns `text` with the regex substitutions for `bundle_id` applied in
+/// order, or `None` if the app has none.
+pub fn substitute(transforms: &RegexTransforms, bundle_id: &str, text: &str) -> Option<String> {
+    let rules = transforms.for_app(bundle_id);
+    if rules.is_empty() {
+        return None;
+    }
+    Some(
+        rules
+            .iter()
+            .fold(text.to_string(), |text, (re, replacement)| {
+                re.replace_all(&text, replacement.as_str()).into_owned()
+            }),
+    )
+}
+
+fn slack_markdown(text: &str) -> String {
+    let heading = Regex::new(r"(?m)^#{1,6}\s+(.+?)\s*#*$").unwrap();
+    let bullet = Regex::new(r"(?m)^(\s*)[-*+]\s+").unwrap();
+    let bold = Regex::new(r"\*\*(.+?)\*\*|__(.+?)__").unwrap();
+    let italic = Regex::new(r"\*([^*\s][^*]*?)\*").unwrap();
+    let strike = Regex::new(r"~~(.+?)~~").unwrap();
+    let link = Regex::new(r"\[([^\]]+)\]\(([^)\s]+)\)").unwrap();
+
+    // Code blocks are left as they are; Slack uses the same fences
+    text.split("```")
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                return part.to_string();
+            }
+            let part = bullet.replace_all(part, "$1• ");
+            // Bold becomes single asterisks, so mark it while italics are
+            // rewritten to underscores
+            let part = heading.replace_all(&part, "\u{0}$1\u{0}");
+            let part = bold.replace_all(&part, "\u{0}$1$2\u{0}");
+            let part = italic.replace_all(&part, "_${1}_");
+            let part = strike.replace_all(&part, "~$1~");
+            let part = link.replace_all(&part, "$1 ($2)");
+            part.replace('\u{0}', "*")
+        })
+        .collect::<Vec<_>>()
+        .join("```")
+}
+
+fn strip_unicode(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{2032}' => stripped.push('\''),
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{2033}' => stripped.push('"'),
+            '\u{2013}' | '\u{2014}' | '\u{2212}' => stripped.push('-'),
+            '\u{2026}' => stripped.push_str("..."),
+            '\u{00A0}' | '\u{2007}' | '\u{202F}' => stripped.push(' '),
+            c if c.is_ascii() => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
@@ -0,0 +1,90 @@
+//! Entries shown as a QR code, to pick them up with a phone's camera.
+
+use iced::widget::canvas::{self, Frame, Geometry};
+use iced::{mouse, Color, Point, Rectangle, Renderer, Size, Theme};
+use qrcodegen::{QrCode, QrCodeEcc};
+use std::{fs::File, io, io::BufWriter, path::Path};
+
+/// The most bytes a QR code holds at the lowest error correction level.
+pub const MAX_BYTES: usize = 2953;
+
+/// Light modules around the code that scanners need to find it.
+const QUIET_ZONE: i32 = 4;
+
+/// Pixels per module in the saved image.
+const PNG_SCALE: u32 = 8;
+
+/// Encodes `content`, or None if it doesn't fit in a QR code.
+pub fn encode(content: &str) -> Option<QrCode> {
+    if content.len() > MAX_BYTES {
+        return None;
+    }
+    QrCode::encode_text(content, QrCodeEcc::Low).ok()
+}
+
+/// Draws a QR code in black on white, as large as fits its bounds.
+#[derive(Debug, Clone)]
+pub struct QrView {
+    pub code: QrCode,
+}
+
+impl<Message> canvas::Program<Message> for QrView {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let modules = self.code.size() + 2 * QUIET_ZONE;
+        let module = (bounds.width.min(bounds.height) / modules as f32).floor();
+        let side = module * modules as f32;
+        let origin = Point::new(
+            ((bounds.width - side) / 2.0).floor(),
+            ((bounds.height - side) / 2.0).floor(),
+        );
+
+        frame.fill_rectangle(origin, Size::new(side, side), Color::WHITE);
+        for y in 0..self.code.size() {
+            for x in 0..self.code.size() {
+                if self.code.get_module(x, y) {
+                    frame.fill_rectangle(
+                        Point::new(
+                            origin.x + (x + QUIET_ZONE) as f32 * module,
+                            origin.y + (y + QUIET_ZONE) as f32 * module,
+                        ),
+                        Size::new(module, module),
+                        Color::BLACK,
+                    );
+                }
+            }
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
+/// Writes `code` as a grayscale PNG, quiet zone included.
+pub fn save_png(code: &QrCode, path: &Path) -> io::Result<()> {
+    let modules = (code.size() + 2 * QUIET_ZONE) as u32;
+    let side = modules * PNG_SCALE;
+    let mut pixels = Vec::with_capacity((side * side) as usize);
+    for py in 0..side {
+        for px in 0..side {
+            let x = (px / PNG_SCALE) as i32 - QUIET_ZONE;
+            let y = (py / PNG_SCALE) as i32 - QUIET_ZONE;
+            // get_module is false outside the code, which gives the quiet zone
+            pixels.push(if code.get_module(x, y) { 0 } else { 255 });
+        }
+    }
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), side, side);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    Ok(())
+}
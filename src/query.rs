@@ -0,0 +1,242 @@
+//! The search query language: words, `"exact phrases"`, `AND`, `OR`, `NOT`
+//! and parentheses. Adjacent terms are ANDed, so `foo bar` finds entries
+//! containing both words. Matching ignores case.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryNode {
+    And(Box<QueryNode>, Box<QueryNode>),
+    Or(Box<QueryNode>, Box<QueryNode>),
+    Not(Box<QueryNode>),
+    /// A single word, matched as a substring.
+    Literal(String),
+    /// Quoted text, matched as a substring including its spaces.
+    Phrase(String),
+}
+
+impl QueryNode {
+    /// Whether `content` satisfies the query.
+    pub fn matches(&self, content: &str) -> bool {
+        self.matches_lowercase(&content.to_lowercase())
+    }
+
+    /// Like `matches`, for content that's already lowercase.
+    pub fn matches_lowercase(&self, content: &str) -> bool {
+        match self {
+            QueryNode::And(left, right) => {
+                left.matches_lowercase(content) && right.matches_lowercase(content)
+            }
+            QueryNode::Or(left, right) => {
+                left.matches_lowercase(content) || right.matches_lowercase(content)
+            }
+            QueryNode::Not(node) => !node.matches_lowercase(content),
+            QueryNode::Literal(text) | QueryNode::Phrase(text) => content.contains(text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// An operator with nothing after it, e.g. `foo AND`.
+    MissingOperand(&'static str),
+    /// An operator with nothing before it, e.g. `OR foo`.
+    UnexpectedOperator(&'static str),
+    UnclosedParen,
+    UnexpectedCloseParen,
+    UnclosedQuote,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingOperand(op) => write!(f, "{} needs a term after it", op),
+            ParseError::UnexpectedOperator(op) => write!(f, "{} needs a term before it", op),
+            ParseError::UnclosedParen => write!(f, "missing )"),
+            ParseError::UnexpectedCloseParen => write!(f, "unexpected )"),
+            ParseError::UnclosedQuote => write!(f, "missing closing quote"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Word(String),
+    Phrase(String),
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let mut phrase = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => phrase.push(c),
+                        None => return Err(ParseError::UnclosedQuote),
+                    }
+                }
+                tokens.push(Token::Phrase(phrase.to_lowercase()));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                // Operators are only recognised in capitals, so "and" is
+                // still searchable
+                tokens.push(match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word.to_lowercase()),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses `query`, returning `None` for a query without any terms.
+///
+/// ```text
+/// or      := and ("OR" and)*
+/// and     := unary ("AND"? unary)*
+/// unary   := "NOT" unary | primary
+/// primary := "(" or ")" | phrase | word
+/// ```
+pub fn parse(query: &str) -> Result<Option<QueryNode>, ParseError> {
+    let tokens = tokenize(query)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+
+    let mut parser = Parser { tokens, pos: 0 };
+    let node = parser.or()?;
+    match parser.peek() {
+        None => Ok(Some(node)),
+        Some(Token::Close) => Err(ParseError::UnexpectedCloseParen),
+        // or() only stops at the end or a stray ")"
+        Some(_) => unreachable!(),
+    }
+}
+
+/// Parses `query`, falling back to requiring each of its words when it isn't
+/// valid, so a half-typed query in the search field still narrows the list.
+pub fn parse_lenient(query: &str) -> Option<QueryNode> {
+    parse(query).unwrap_or_else(|_| {
+        query
+            .split_whitespace()
+            .filter(|word| !matches!(*word, "AND" | "OR" | "NOT"))
+            .map(|word| word.trim_matches(['"', '(', ')']).to_lowercase())
+            .filter(|word| !word.is_empty())
+            .map(QueryNode::Literal)
+            .reduce(|left, right| QueryNode::And(Box::new(left), Box::new(right)))
+    })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<QueryNode, ParseError> {
+        let mut node = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            if self.at_operand_end() {
+                return Err(ParseError::MissingOperand("OR"));
+            }
+            node = QueryNode::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<QueryNode, ParseError> {
+        let mut node = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                    if self.at_operand_end() {
+                        return Err(ParseError::MissingOperand("AND"));
+                    }
+                }
+                Some(Token::Or | Token::Close) | None => return Ok(node),
+                // Adjacent terms
+                Some(_) => {}
+            }
+            node = QueryNode::And(Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<QueryNode, ParseError> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            if self.at_operand_end() {
+                return Err(ParseError::MissingOperand("NOT"));
+            }
+            return Ok(QueryNode::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<QueryNode, ParseError> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(QueryNode::Literal(word)),
+            Some(Token::Phrase(phrase)) => Ok(QueryNode::Phrase(phrase)),
+            Some(Token::Open) => {
+                let node = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(node),
+                    _ => Err(ParseError::UnclosedParen),
+                }
+            }
+            Some(Token::And) => Err(ParseError::UnexpectedOperator("AND")),
+            Some(Token::Or) => Err(ParseError::UnexpectedOperator("OR")),
+            Some(Token::Close) => Err(ParseError::UnexpectedCloseParen),
+            Some(Token::Not) => unreachable!("handled by unary()"),
+            None => Err(ParseError::UnclosedParen),
+        }
+    }
+
+    /// Whether the operator just consumed has nothing to apply to.
+    fn at_operand_end(&self) -> bool {
+        matches!(self.peek(), None | Some(Token::Close))
+    }
+}
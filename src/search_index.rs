@@ -0,0 +1,55 @@
+//! A lowercased copy of each entry's search text, so filtering the history
+//! on every keystroke doesn't lowercase every entry again.
+
+use crate::ClipboardEntry;
+use std::collections::VecDeque;
+
+/// Search text for each history entry, in the same order as the entries.
+/// Kept in step by mirroring each change made to the history; changes that
+/// touch many entries at once rebuild it instead.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    texts: VecDeque<String>,
+}
+
+impl SearchIndex {
+    pub fn new<'a>(entries: impl IntoIterator<Item = &'a ClipboardEntry>) -> Self {
+        SearchIndex {
+            texts: entries.into_iter().map(key).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.texts.len()
+    }
+
+    /// The lowercased search text of entry `index`.
+    pub fn get(&self, index: usize) -> &str {
+        &self.texts[index]
+    }
+
+    pub fn push_front(&mut self, entry: &ClipboardEntry) {
+        self.texts.push_front(key(entry));
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.texts.remove(index);
+    }
+
+    pub fn move_to_front(&mut self, index: usize) {
+        if let Some(text) = self.texts.remove(index) {
+            self.texts.push_front(text);
+        }
+    }
+
+    /// Refreshes entry `index` after its title or label changed.
+    pub fn update(&mut self, index: usize, entry: &ClipboardEntry) {
+        if let Some(text) = self.texts.get_mut(index) {
+            *text = key(entry);
+        }
+    }
+}
+
+fn key(entry: &ClipboardEntry) -> String {
+    entry.search_text().to_lowercase()
+}
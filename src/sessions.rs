@@ -0,0 +1,70 @@
+use crate::ClipboardEntry;
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, collections::VecDeque, fs, io, path::PathBuf};
+
+const SESSION_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// A named snapshot of a day's clipboard entries, oldest first so they can
+/// be replayed in the order they were copied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub name: String,
+    pub date: NaiveDate,
+    pub entries: Vec<ClipboardEntry>,
+}
+
+/// Reads and writes `session_<date>.json` files in the sessions directory.
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        SessionStore { dir }
+    }
+
+    /// Saves the entries copied in the last 24 hours as today's session,
+    /// replacing any session already saved today.
+    pub fn save_today(&self, entries: &VecDeque<ClipboardEntry>, now: u64) -> io::Result<Session> {
+        let date = Local::now().date_naive();
+        let cutoff = now.saturating_sub(SESSION_WINDOW_SECS);
+        let session = Session {
+            name: format!("session_{}", date),
+            date,
+            entries: entries
+                .iter()
+                .rev()
+                .filter(|e| e.timestamp >= cutoff)
+                .cloned()
+                .collect(),
+        };
+
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("{}.json", session.name));
+        fs::write(path, serde_json::to_string(&session)?)?;
+        Ok(session)
+    }
+
+    /// Lists saved sessions, newest first.
+    pub fn list(&self) -> io::Result<Vec<Session>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let is_session = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("session_") && name.ends_with(".json"));
+            if is_session {
+                let data = fs::read_to_string(&path)?;
+                sessions.push(serde_json::from_str::<Session>(&data)?);
+            }
+        }
+        sessions.sort_by_key(|s| Reverse(s.date));
+        Ok(sessions)
+    }
+}
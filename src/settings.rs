@@ -0,0 +1,41 @@
+//! Choices offered by the Settings tab, which edits `config.json` from the
+//! window.
+
+use std::fmt;
+
+/// Options for `max_age_days`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgeLimit {
+    Never,
+    Days(u64),
+    /// Any other number of days, typed in.
+    Custom,
+}
+
+impl AgeLimit {
+    pub const CHOICES: [AgeLimit; 5] = [
+        AgeLimit::Never,
+        AgeLimit::Days(7),
+        AgeLimit::Days(30),
+        AgeLimit::Days(90),
+        AgeLimit::Custom,
+    ];
+
+    pub fn from_days(days: Option<u64>) -> AgeLimit {
+        match days {
+            None => AgeLimit::Never,
+            Some(days) if AgeLimit::CHOICES.contains(&AgeLimit::Days(days)) => AgeLimit::Days(days),
+            Some(_) => AgeLimit::Custom,
+        }
+    }
+}
+
+impl fmt::Display for AgeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AgeLimit::Never => write!(f, "Never"),
+            AgeLimit::Days(days) => write!(f, "{} days", days),
+            AgeLimit::Custom => write!(f, "Custom"),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Uploading an entry to a paste service and getting back a link to it.
+
+use crate::config::{Config, ShareService};
+use serde::Deserialize;
+use serde_json::json;
+use std::{fmt, time::Duration};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const GIST_API_URL: &str = "https://api.github.com/gists";
+const PASTEBIN_API_URL: &str = "https://pastebin.com/api/api_post.php";
+
+#[derive(Debug)]
+pub enum ShareError {
+    /// No token in `api_tokens` for a service that requires one.
+    MissingToken(&'static str),
+    /// `share_service` is `custom` but `share_url` isn't set.
+    MissingUrl,
+    Http(reqwest::Error),
+    /// The service answered, but not with a link.
+    UnexpectedResponse(String),
+}
+
+impl fmt::Display for ShareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShareError::MissingToken(key) => {
+                write!(f, "add a \"{}\" token to api_tokens in config.json", key)
+            }
+            ShareError::MissingUrl => write!(f, "set share_url in config.json"),
+            ShareError::Http(e) => write!(f, "{}", e),
+            ShareError::UnexpectedResponse(body) => write!(f, "unexpected response: {}", body),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ShareError {
+    fn from(e: reqwest::Error) -> Self {
+        ShareError::Http(e)
+    }
+}
+
+/// A service that stores text and hands out a URL for it.
+pub trait ShareProvider {
+    async fn share(&self, client: &reqwest::Client, content: &str) -> Result<String, ShareError>;
+}
+
+/// Creates a secret GitHub Gist. Needs a token with the `gist` scope.
+pub struct GistProvider {
+    pub token: String,
+}
+
+impl ShareProvider for GistProvider {
+    async fn share(&self, client: &reqwest::Client, content: &str) -> Result<String, ShareError> {
+        #[derive(Deserialize)]
+        struct Gist {
+            html_url: String,
+        }
+
+        let gist: Gist = client
+            .post(GIST_API_URL)
+            .bearer_auth(&self.token)
+            .header("Accept", "application/vnd.github+json")
+            .json(&json!({
+                "description": "Shared from mac-clip",
+                "public": false,
+                "files": { "clip.txt": { "content": content } },
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(gist.html_url)
+    }
+}
+
+/// Creates an unlisted paste on pastebin.com. Needs the account's developer
+/// API key.
+pub struct PastebinProvider {
+    pub api_key: String,
+}
+
+impl ShareProvider for PastebinProvider {
+    async fn share(&self, client: &reqwest::Client, content: &str) -> Result<String, ShareError> {
+        let body = client
+            .post(PASTEBIN_API_URL)
+            .form(&[
+                ("api_dev_key", self.api_key.as_str()),
+                ("api_option", "paste"),
+                ("api_paste_code", content),
+                // Unlisted
+                ("api_paste_private", "1"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        // Errors come back as 200s with a "Bad API request" body
+        let body = body.trim();
+        if body.starts_with("https://") {
+            Ok(body.to_string())
+        } else {
+            Err(ShareError::UnexpectedResponse(body.to_string()))
+        }
+    }
+}
+
+/// POSTs `{"content": ...}` to any URL. The link is taken from a `url` field
+/// in a JSON response, or the whole response body otherwise.
+pub struct CustomProvider {
+    pub url: String,
+    pub token: Option<String>,
+}
+
+impl ShareProvider for CustomProvider {
+    async fn share(&self, client: &reqwest::Client, content: &str) -> Result<String, ShareError> {
+        let mut request = client.post(&self.url).json(&json!({ "content": content }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let body = request.send().await?.error_for_status()?.text().await?;
+
+        let url = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|value| value.get("url")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| body.trim().to_string());
+        if url.is_empty() {
+            Err(ShareError::UnexpectedResponse(body))
+        } else {
+            Ok(url)
+        }
+    }
+}
+
+/// Uploads `content` to the service chosen in the config and returns its URL.
+pub async fn share(config: &Config, content: &str) -> Result<String, ShareError> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("mac-clip/", env!("CARGO_PKG_VERSION")))
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let token = |key: &'static str| {
+        config
+            .api_tokens
+            .get(key)
+            .cloned()
+            .ok_or(ShareError::MissingToken(key))
+    };
+
+    match config.share_service {
+        ShareService::Gist => {
+            let provider = GistProvider {
+                token: token("github")?,
+            };
+            provider.share(&client, content).await
+        }
+        ShareService::Pastebin => {
+            let provider = PastebinProvider {
+                api_key: token("pastebin")?,
+            };
+            provider.share(&client, content).await
+        }
+        ShareService::Custom => {
+            let provider = CustomProvider {
+                url: config.share_url.clone().ok_or(ShareError::MissingUrl)?,
+                token: token("custom").ok(),
+            };
+            provider.share(&client, content).await
+        }
+    }
+}
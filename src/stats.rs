@@ -0,0 +1,141 @@
+//! Figures about the history for the Stats tab.
+
+use crate::{ClipboardEntry, Message};
+use iced::widget::canvas::{self, event, Event, Frame, Geometry, Text};
+use iced::{alignment, mouse, Color, Point, Rectangle, Renderer, Size, Theme};
+use std::collections::VecDeque;
+
+/// Labels of the size histogram's buckets, in characters.
+pub const BUCKET_LABELS: [&str; 5] = ["<50", "50-200", "200-1K", "1K-10K", ">10K"];
+
+/// Height of the bucket labels under the bars.
+const LABEL_HEIGHT: f32 = 20.0;
+
+/// The histogram bucket for content `chars` characters long.
+pub fn size_bucket(chars: usize) -> usize {
+    match chars {
+        0..=49 => 0,
+        50..=199 => 1,
+        200..=999 => 2,
+        1000..=9999 => 3,
+        _ => 4,
+    }
+}
+
+/// How many entries fall in each of `BUCKET_LABELS`. Entries kept on disk in
+/// low-memory mode count by their in-memory preview.
+pub fn size_histogram(entries: &VecDeque<ClipboardEntry>) -> [u32; 5] {
+    let mut counts = [0; 5];
+    for entry in entries {
+        counts[size_bucket(entry.content.chars().count())] += 1;
+    }
+    counts
+}
+
+/// Bytes of content in the history.
+pub fn storage_used(entries: &VecDeque<ClipboardEntry>) -> usize {
+    entries.iter().map(|entry| entry.content.len()).sum()
+}
+
+/// Bars for a size histogram, each labelled with its bucket and count.
+/// Clicking a bar jumps to the first entry in it.
+pub struct SizeHistogram {
+    pub counts: [u32; 5],
+}
+
+impl SizeHistogram {
+    /// The bucket of the bar at `x`, measured from the left edge.
+    fn bucket_at(&self, x: f32, width: f32) -> Option<usize> {
+        let bucket = (x / width * self.counts.len() as f32) as usize;
+        (x >= 0.0 && bucket < self.counts.len()).then_some(bucket)
+    }
+}
+
+impl canvas::Program<Message> for SizeHistogram {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut (),
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event else {
+            return (event::Status::Ignored, None);
+        };
+        let Some(position) = cursor.position_in(bounds) else {
+            return (event::Status::Ignored, None);
+        };
+        match self.bucket_at(position.x, bounds.width) {
+            Some(bucket) if self.counts[bucket] > 0 => (
+                event::Status::Captured,
+                Some(Message::JumpToSizeBucket(bucket)),
+            ),
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.extended_palette();
+        let slot = bounds.width / self.counts.len() as f32;
+        let chart_height = bounds.height - 2.0 * LABEL_HEIGHT;
+        let highest = self.counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+
+        for (i, (&count, label)) in self.counts.iter().zip(BUCKET_LABELS).enumerate() {
+            let center = slot * (i as f32 + 0.5);
+            let height = chart_height * count as f32 / highest;
+            let top = LABEL_HEIGHT + chart_height - height;
+            frame.fill_rectangle(
+                Point::new(slot * i as f32 + slot * 0.15, top),
+                Size::new(slot * 0.7, height),
+                palette.primary.base.color,
+            );
+            frame.fill_text(Text {
+                content: count.to_string(),
+                position: Point::new(center, top),
+                color: palette.background.base.text,
+                size: 12.0,
+                horizontal_alignment: alignment::Horizontal::Center,
+                vertical_alignment: alignment::Vertical::Bottom,
+                ..Text::default()
+            });
+            frame.fill_text(Text {
+                content: label.to_string(),
+                position: Point::new(center, bounds.height),
+                color: Color {
+                    a: 0.7,
+                    ..palette.background.base.text
+                },
+                size: 12.0,
+                horizontal_alignment: alignment::Horizontal::Center,
+                vertical_alignment: alignment::Vertical::Bottom,
+                ..Text::default()
+            });
+        }
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &(),
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        match cursor
+            .position_in(bounds)
+            .and_then(|position| self.bucket_at(position.x, bounds.width))
+        {
+            Some(bucket) if self.counts[bucket] > 0 => mouse::Interaction::Pointer,
+            _ => mouse::Interaction::default(),
+        }
+    }
+}
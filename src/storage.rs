@@ -0,0 +1,308 @@
+use crate::{
+    archive::Archiver,
+    bodies::BodyStore,
+    config::{Config, DedupMode, StorageFormat},
+    history, ClipboardEntry,
+};
+use log::{error, info};
+use serde::Serialize;
+use std::{
+    collections::{HashSet, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Where per-session files like the instance lock go: the runtime directory
+/// where the platform has one, the data directory otherwise.
+pub fn runtime_dir() -> io::Result<PathBuf> {
+    let runtime_dir = directories::ProjectDirs::from("com", "mac-clip", "mac-clip")
+        .and_then(|dirs| dirs.runtime_dir().map(Path::to_path_buf));
+    match runtime_dir {
+        Some(dir) => {
+            fs::create_dir_all(&dir)?;
+            Ok(dir)
+        }
+        None => data_dir(),
+    }
+}
+
+pub fn data_dir() -> io::Result<PathBuf> {
+    let dir = directories::ProjectDirs::from("com", "mac-clip", "mac-clip")
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Could not determine data directory",
+            )
+        })?
+        .data_dir()
+        .to_path_buf();
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+pub fn archive_dir(profile: Option<&str>) -> io::Result<PathBuf> {
+    Ok(profile_dir(profile)?.join("archive"))
+}
+
+/// The profile used without `--profile`.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Where a profile keeps its history, sessions and the rest: the data
+/// directory itself for the default profile, as before there were profiles,
+/// and `profiles/<name>` in it for the others.
+pub fn profile_dir(profile: Option<&str>) -> io::Result<PathBuf> {
+    let dir = data_dir()?;
+    match profile.filter(|&name| name != DEFAULT_PROFILE) {
+        Some(name) => {
+            let dir = dir.join("profiles").join(name);
+            fs::create_dir_all(&dir)?;
+            Ok(dir)
+        }
+        None => Ok(dir),
+    }
+}
+
+/// A profile's config file in `data_dir`: `config.json` for the default
+/// profile and `config_<name>.json` for the others.
+pub fn config_path(data_dir: &Path, profile: Option<&str>) -> PathBuf {
+    match profile.filter(|&name| name != DEFAULT_PROFILE) {
+        Some(name) => data_dir.join(format!("config_{}.json", name)),
+        None => data_dir.join("config.json"),
+    }
+}
+
+/// Whether `name` can name a profile, which ends up in file names.
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Every profile with a config file or a directory in `data_dir`, sorted,
+/// the default one included.
+pub fn list_profiles(data_dir: &Path) -> io::Result<Vec<String>> {
+    let mut profiles = vec![DEFAULT_PROFILE.to_string()];
+    for entry in fs::read_dir(data_dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if let Some(profile) = name
+            .strip_prefix("config_")
+            .and_then(|rest| rest.strip_suffix(".json"))
+        {
+            profiles.push(profile.to_string());
+        }
+    }
+    let profiles_dir = data_dir.join("profiles");
+    if profiles_dir.is_dir() {
+        for entry in fs::read_dir(profiles_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                profiles.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+    }
+    profiles.sort();
+    profiles.dedup();
+    Ok(profiles)
+}
+
+/// Moves entries past `archive_after_days` into the archives in
+/// `archive_dir`, if enabled.
+pub fn archive_history(
+    entries: &mut VecDeque<ClipboardEntry>,
+    config: &Config,
+    bodies: &BodyStore,
+    archive_dir: &Path,
+) -> usize {
+    let Some(days) = config.archive_after_days else {
+        return 0;
+    };
+    match Archiver::archive_old(entries, days, archive_dir, |e| bodies.load(e)) {
+        Ok(moved) => {
+            if moved > 0 {
+                info!("Archived {} entries older than {} days", moved, days);
+            }
+            moved
+        }
+        Err(e) => {
+            error!("Failed to archive old entries: {}", e);
+            0
+        }
+    }
+}
+
+/// Reads a history file in either format, telling them apart by content.
+pub fn load_history(path: &Path) -> VecDeque<ClipboardEntry> {
+    if path.exists() {
+        info!("Loading clipboard history from {}", path.display());
+        let data = fs::read(path).expect("Failed to read history file");
+        history::decode(&data).unwrap_or_else(|_| VecDeque::new())
+    } else {
+        info!("No existing clipboard history found");
+        VecDeque::new()
+    }
+}
+
+pub fn save_history(
+    path: &Path,
+    entries: &VecDeque<ClipboardEntry>,
+    format: StorageFormat,
+) -> io::Result<()> {
+    fs::write(path, format.encoder().encode(entries)?)
+}
+
+/// The history file in `dir`, whichever format it's in. For readers that
+/// don't know the configured format.
+pub fn history_path(dir: &Path) -> PathBuf {
+    [StorageFormat::Json, StorageFormat::MessagePack]
+        .iter()
+        .map(|format| dir.join(format.file_name()))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| dir.join(StorageFormat::Json.file_name()))
+}
+
+/// Drops the oldest unpinned entries until at most `max_size` unpinned entries
+/// remain. Pinned entries are kept regardless and don't count towards the
+/// limit, so pinning can never leave the history unable to take new entries.
+/// Returns the indices removed, each as it was at the time of its removal.
+pub fn trim_history(entries: &mut VecDeque<ClipboardEntry>, max_size: usize) -> Vec<usize> {
+    let mut unpinned = entries.iter().filter(|e| !e.pinned).count();
+    let mut removed = Vec::new();
+    while unpinned > max_size {
+        match entries.iter().rposition(|e| !e.pinned) {
+            Some(index) => {
+                entries.remove(index);
+                unpinned -= 1;
+                removed.push(index);
+            }
+            None => break,
+        }
+    }
+    removed
+}
+
+/// Removes unpinned entries whose `expires_at` has passed.
+pub fn remove_expired(entries: &mut VecDeque<ClipboardEntry>, now: u64) -> usize {
+    let before = entries.len();
+    entries.retain(|e| e.pinned || e.expires_at.is_none_or(|expires_at| expires_at > now));
+    before - entries.len()
+}
+
+/// FNV-1a hash of the content. Body files are named after it, so unlike
+/// `DefaultHasher` it has to come out the same in every build.
+pub fn content_hash(content: &str) -> u64 {
+    content.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Removes unpinned entries older than `max_age_days`, if it's set.
+pub fn remove_aged(entries: &mut VecDeque<ClipboardEntry>, config: &Config, now: u64) -> usize {
+    let Some(days) = config.max_age_days else {
+        return 0;
+    };
+    let cutoff = now.saturating_sub(days * SECONDS_PER_DAY);
+    let before = entries.len();
+    entries.retain(|e| e.pinned || e.timestamp >= cutoff);
+    let removed = before - entries.len();
+    if removed > 0 {
+        info!("Removed {} entries older than {} days", removed, days);
+    }
+    removed
+}
+
+/// Removes duplicate (unless `dedup_mode` is off), excluded and expired
+/// entries, keeping the newest copy of each duplicate. Returns the number of
+/// entries removed.
+pub fn gc_history(entries: &mut VecDeque<ClipboardEntry>, config: &Config) -> usize {
+    let aged = remove_aged(entries, config, unix_now());
+    let before = entries.len();
+    let exclusions = config.exclusion_regexes();
+
+    let mut seen = HashSet::new();
+    entries.retain(|entry| {
+        if exclusions.iter().any(|re| re.is_match(&entry.content)) {
+            return false;
+        }
+        config.dedup_mode == DedupMode::Off || seen.insert(entry.content_hash())
+    });
+
+    aged + before - entries.len()
+}
+
+/// What changed between two snapshots of the history, for `--diff`.
+#[derive(Debug, Default, Serialize)]
+pub struct HistoryDiff {
+    /// Entries only in the new snapshot, indexed by their place in it.
+    pub added: Vec<DiffEntry>,
+    /// Entries only in the old snapshot, indexed by their place in it.
+    pub removed: Vec<DiffEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffEntry {
+    pub index: usize,
+    pub timestamp: u64,
+    pub content: String,
+}
+
+/// Compares two snapshots of the history by content hash, so an entry that
+/// only moved or was pinned doesn't count as a change.
+pub fn diff_histories(old: &[ClipboardEntry], new: &[ClipboardEntry]) -> HistoryDiff {
+    let only_in = |entries: &[ClipboardEntry], other: &[ClipboardEntry]| {
+        let other: HashSet<u64> = other.iter().map(ClipboardEntry::content_hash).collect();
+        entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !other.contains(&entry.content_hash()))
+            .map(|(index, entry)| DiffEntry {
+                index,
+                timestamp: entry.timestamp,
+                content: entry.content.clone(),
+            })
+            .collect()
+    };
+    HistoryDiff {
+        added: only_in(new, old),
+        removed: only_in(old, new),
+    }
+}
+
+/// Serializes pasteboard flavors with base64 bodies so the history file stays
+/// compact, readable JSON.
+pub mod flavors_base64 {
+    use crate::macos::Flavors;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(flavors: &Flavors, serializer: S) -> Result<S::Ok, S::Error> {
+        flavors
+            .iter()
+            .map(|(flavor, bytes)| (flavor, STANDARD.encode(bytes)))
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Flavors, D::Error> {
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(flavor, encoded)| {
+                STANDARD
+                    .decode(encoded)
+                    .map(|bytes| (flavor, bytes))
+                    .map_err(D::Error::custom)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,60 @@
+//! Custom widget styles, drawn from the current theme's palette so they
+//! follow light and dark mode.
+
+use iced::{widget::button, Background, Theme};
+
+const ENTRY_RADIUS: f32 = 6.0;
+
+/// A row of the history list: flat with rounded corners and a faint
+/// border, shaded on hover and in the accent colour while highlighted by
+/// keyboard navigation.
+pub struct EntryButton {
+    pub selected: bool,
+}
+
+impl button::StyleSheet for EntryButton {
+    type Style = Theme;
+
+    fn active(&self, theme: &Theme) -> button::Appearance {
+        let palette = theme.extended_palette();
+        let (fill, border) = if self.selected {
+            (palette.primary.weak, palette.primary.strong.color)
+        } else {
+            (palette.background.base, palette.background.strong.color)
+        };
+        button::Appearance {
+            background: Some(Background::Color(fill.color)),
+            border_radius: ENTRY_RADIUS.into(),
+            border_width: 1.0,
+            border_color: border,
+            text_color: fill.text,
+            ..Default::default()
+        }
+    }
+
+    fn hovered(&self, theme: &Theme) -> button::Appearance {
+        let palette = theme.extended_palette();
+        let active = self.active(theme);
+        if self.selected {
+            return button::Appearance {
+                border_color: palette.primary.base.color,
+                ..active
+            };
+        }
+        button::Appearance {
+            background: Some(Background::Color(palette.background.weak.color)),
+            text_color: palette.background.weak.text,
+            ..active
+        }
+    }
+
+    fn pressed(&self, theme: &Theme) -> button::Appearance {
+        let palette = theme.extended_palette();
+        button::Appearance {
+            background: Some(Background::Color(palette.primary.base.color)),
+            text_color: palette.primary.base.text,
+            border_color: palette.primary.strong.color,
+            ..self.active(theme)
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Anonymous feature usage events, only recorded with `telemetry_opt_in`.
+//! Events name a feature and when it was used, never anything that was
+//! copied. They're queued in a local file and sent to `telemetry_url` once
+//! a day.
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Queued events are sent once the oldest is this old.
+const BATCH_AGE_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelemetryEvent {
+    pub event: String,
+    pub ts: u64,
+}
+
+/// What's sent to `telemetry_url`.
+#[derive(Debug, Serialize)]
+pub struct Batch {
+    pub app_version: &'static str,
+    pub os_version: String,
+    pub events: Vec<TelemetryEvent>,
+}
+
+/// The local queue, one JSON event per line.
+pub struct TelemetryRecorder {
+    path: PathBuf,
+}
+
+impl TelemetryRecorder {
+    pub fn new(path: PathBuf) -> Self {
+        TelemetryRecorder { path }
+    }
+
+    pub fn record(&self, event: &'static str, ts: u64) -> io::Result<()> {
+        let mut line = serde_json::to_string(&TelemetryEvent {
+            event: event.to_string(),
+            ts,
+        })?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
+
+    /// The queued events, oldest first. Lines that don't parse are skipped.
+    pub fn queued(&self) -> io::Result<Vec<TelemetryEvent>> {
+        match fs::read_to_string(&self.path) {
+            Ok(data) => Ok(data
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The queued events as a batch, if the oldest has waited a day.
+    pub fn due_batch(&self, now: u64) -> io::Result<Option<Batch>> {
+        let events = self.queued()?;
+        let due = events
+            .first()
+            .is_some_and(|oldest| now.saturating_sub(oldest.ts) >= BATCH_AGE_SECS);
+        Ok(due.then(|| Batch {
+            app_version: env!("CARGO_PKG_VERSION"),
+            os_version: crate::macos::os_version()
+                .unwrap_or_else(|| std::env::consts::OS.to_string()),
+            events,
+        }))
+    }
+
+    /// Drops the first `count` events once they've been sent, keeping any
+    /// recorded in the meantime.
+    pub fn remove_sent(&self, count: usize) -> io::Result<()> {
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let rest: String = data
+            .lines()
+            .filter(|line| serde_json::from_str::<TelemetryEvent>(line).is_ok())
+            .skip(count)
+            .map(|line| format!("{}\n", line))
+            .collect();
+        if rest.is_empty() {
+            self.reset().map(|_| ())
+        } else {
+            fs::write(&self.path, rest)
+        }
+    }
+
+    /// Deletes the queue. Returns whether there was one.
+    pub fn reset(&self) -> io::Result<bool> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// POSTs `batch` to `url` as JSON and returns how many events it held.
+pub async fn send(url: &str, batch: &Batch) -> Result<usize, reqwest::Error> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("mac-clip/", env!("CARGO_PKG_VERSION")))
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    client
+        .post(url)
+        .json(batch)
+        .send()
+        .await?
+        .error_for_status()?;
+    info!("Sent {} telemetry events", batch.events.len());
+    Ok(batch.events.len())
+}
@@ -0,0 +1,2095 @@
+//! Drives `MacClip::update()` directly, without an event loop, window or
+//! background threads.
+
+use crate::{
+    auth, bundle,
+    classify::ContentType,
+    cli,
+    clipboard::MemoryClipboard,
+    config::{
+        Config, LargeContentPolicy, NewlineDisplay, RegexTransformRule, RowDetails, StorageFormat,
+        ViewMode, WindowAnchor,
+    },
+    diagnostics::{self, DaemonStatus},
+    diff::{self, Change},
+    entry_details, export,
+    history::HistoryStore,
+    instance::{InstanceLock, LockError},
+    macos::ScreenFrame,
+    monitor::{HeartbeatChange, HeartbeatMonitor, WatchdogThread},
+    pasta,
+    paste::{self, Transform},
+    query::{self, ParseError, QueryNode},
+    settings::AgeLimit,
+    share::{self, ShareError},
+    stats, storage,
+    telemetry::TelemetryRecorder,
+    transform::RegexTransforms,
+    webdav::{self, RemoteHistory},
+    widgets,
+    window_state::{self, WindowPosition},
+    ClipboardEntry, EntryId, Event, Flavors, MacClip, Message, Selection, Tab, MAX_HISTORY_SIZE,
+};
+use fs2::FileExt;
+use iced::{keyboard, Application};
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    path::Path,
+    time::{Duration, Instant},
+};
+use tempfile::TempDir;
+use tokio::sync::{mpsc, watch};
+
+struct Harness {
+    app: MacClip,
+    clipboard: MemoryClipboard,
+    dir: TempDir,
+    /// Commands like toasts start timers, which need a runtime to exist.
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Harness {
+    fn new() -> Self {
+        Harness::with_config("{}")
+    }
+
+    fn with_config(config: &str) -> Self {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("config.json"), config).unwrap();
+        let clipboard = MemoryClipboard::default();
+        let app = open_app(dir.path(), &clipboard);
+        Harness {
+            app,
+            clipboard,
+            dir,
+            runtime: tokio::runtime::Runtime::new().unwrap(),
+        }
+    }
+
+    fn send(&mut self, message: Message) {
+        let _runtime = self.runtime.enter();
+        let _ = self.app.update(message);
+    }
+
+    fn copy(&mut self, content: &str) {
+        self.send(Message::EventReceived(Event::ClipboardChanged {
+            content: content.to_string(),
+            source_app: None,
+            flavors: Flavors::new(),
+            selection: Selection::Clipboard,
+            user_initiated: true,
+            double_copied: false,
+        }));
+    }
+
+    fn id(&self, index: usize) -> EntryId {
+        self.app.entries[index].id
+    }
+
+    fn contents(&self) -> Vec<&str> {
+        self.app
+            .entries
+            .iter()
+            .map(|e| e.content.as_str())
+            .collect()
+    }
+
+    fn saved_contents(&self) -> Vec<String> {
+        storage::load_history(&self.dir.path().join("history.json"))
+            .into_iter()
+            .map(|e| e.content)
+            .collect()
+    }
+}
+
+fn open_app(dir: &Path, clipboard: &MemoryClipboard) -> MacClip {
+    let (tx, _rx) = mpsc::unbounded_channel();
+    let (_event_tx, event_rx) = watch::channel(None);
+    MacClip::with_storage(
+        dir,
+        dir.join("config.json"),
+        clipboard.shared(),
+        tx,
+        event_rx,
+    )
+}
+
+#[test]
+fn copies_are_added_newest_first() {
+    let mut harness = Harness::new();
+    harness.copy("first");
+    harness.copy("second");
+
+    assert_eq!(harness.contents(), ["second", "first"]);
+    assert_eq!(harness.saved_contents(), ["second", "first"]);
+}
+
+#[test]
+fn repeated_copies_are_recorded_once() {
+    let mut harness = Harness::new();
+    harness.copy("same");
+    harness.copy("same");
+
+    assert_eq!(harness.contents(), ["same"]);
+}
+
+#[test]
+fn copying_an_older_entry_moves_it_to_the_front() {
+    let mut harness = Harness::new();
+    harness.copy("first");
+    harness.copy("second");
+    harness.copy("first");
+
+    assert_eq!(harness.contents(), ["first", "second"]);
+}
+
+#[test]
+fn clipboard_changes_without_a_key_press_are_flagged() {
+    let mut harness = Harness::with_config(r#"{"warn_on_spontaneous_changes": true}"#);
+    let spontaneous = |content: &str| {
+        Message::EventReceived(Event::ClipboardChanged {
+            content: content.to_string(),
+            source_app: None,
+            flavors: Flavors::new(),
+            selection: Selection::Clipboard,
+            user_initiated: false,
+            double_copied: false,
+        })
+    };
+    harness.copy("older");
+    harness.copy("newest");
+    assert_eq!(harness.app.spontaneous_change, None);
+
+    // mac-clip's own copy comes back without a key press
+    harness.send(Message::CopyEntry(harness.id(1)));
+    harness.send(spontaneous("older"));
+    assert_eq!(harness.app.spontaneous_change, None);
+
+    harness.send(spontaneous("injected"));
+    assert!(harness.app.spontaneous_change.is_some());
+    assert_eq!(harness.contents()[0], "injected");
+
+    harness.send(Message::DismissSpontaneousChange);
+    assert_eq!(harness.app.spontaneous_change, None);
+}
+
+#[test]
+fn copies_pass_through_every_filter_before_being_stored() {
+    let mut harness =
+        Harness::with_config(r#"{"exclusion_patterns": ["^secret"], "max_copies_per_minute": 2}"#);
+    harness.copy("one\0");
+    harness.copy(" \0");
+    harness.copy("secret");
+    harness.copy("one");
+    harness.copy("two");
+    harness.copy("three");
+
+    assert_eq!(harness.contents(), ["two", "one"]);
+}
+
+#[test]
+fn long_searches_warn_and_are_cut_off_at_the_limit() {
+    let mut harness = Harness::with_config(r#"{"max_search_length": 10}"#);
+
+    harness.send(Message::SearchChanged("abcdefgh".to_string()));
+    assert!(harness.app.toast.is_none());
+    harness.send(Message::SearchChanged("abcdefghi".to_string()));
+    assert_eq!(
+        harness.app.toast.as_ref().unwrap().1,
+        "Searches stop at 10 characters"
+    );
+    harness.send(Message::SearchChanged("abcdefghijklmnop".to_string()));
+    assert_eq!(harness.app.search_query, "abcdefghij");
+}
+
+#[test]
+fn normalized_dedup_ignores_whitespace() {
+    let mut harness = Harness::with_config(r#"{"dedup_mode": "normalized_all"}"#);
+    harness.copy("hello   world");
+    harness.copy("other");
+    harness.copy("  hello world\n");
+
+    assert_eq!(harness.contents(), ["hello   world", "other"]);
+}
+
+#[test]
+fn exact_front_dedup_keeps_older_copies() {
+    let mut harness = Harness::with_config(r#"{"dedup_mode": "exact_front"}"#);
+    harness.copy("first");
+    harness.copy("second");
+    harness.copy("first");
+
+    assert_eq!(harness.contents(), ["first", "second", "first"]);
+}
+
+#[test]
+fn blank_copies_are_ignored() {
+    let mut harness = Harness::new();
+    harness.copy("  \n\t");
+    harness.copy("\0\u{1}");
+
+    assert!(harness.app.entries.is_empty());
+}
+
+#[test]
+fn history_is_trimmed_but_keeps_pins() {
+    let mut harness = Harness::new();
+    harness.copy("keep me");
+    harness.send(Message::TogglePin(harness.id(0)));
+    for i in 0..MAX_HISTORY_SIZE + 5 {
+        harness.copy(&format!("entry {}", i));
+    }
+
+    assert_eq!(harness.app.entries.len(), MAX_HISTORY_SIZE + 1);
+    assert!(harness.contents().contains(&"keep me"));
+    assert!(!harness.contents().contains(&"entry 0"));
+}
+
+#[test]
+fn exclusion_patterns_skip_matching_copies() {
+    let mut harness = Harness::with_config(r#"{"exclusion_patterns": ["^secret"]}"#);
+    harness.copy("secret token");
+    harness.copy("public note");
+
+    assert_eq!(harness.contents(), ["public note"]);
+}
+
+#[test]
+fn copies_from_ignored_apps_are_skipped() {
+    let mut harness = Harness::with_config(r#"{"ignore_apps": ["com.1password.1password"]}"#);
+    let copy_from = |content: &str, app: &str| {
+        Message::EventReceived(Event::ClipboardChanged {
+            content: content.to_string(),
+            source_app: Some(app.to_string()),
+            flavors: Flavors::new(),
+            selection: Selection::Clipboard,
+            user_initiated: true,
+            double_copied: false,
+        })
+    };
+    harness.send(copy_from("hunter2", "com.1password.1password"));
+    harness.send(copy_from("notes", "com.apple.Notes"));
+
+    assert_eq!(harness.contents(), ["notes"]);
+}
+
+#[test]
+fn copies_without_text_are_kept_as_hex_dumps_of_their_data() {
+    let mut harness = Harness::with_config(r#"{"max_binary_entry_kb": 1}"#);
+    let png: Vec<u8> = [0x89, b'P', b'N', b'G']
+        .into_iter()
+        .cycle()
+        .take(40)
+        .collect();
+    let flavors = Flavors::from([
+        ("public.png".to_string(), png),
+        ("dyn.ah62d4rv4gu8y".to_string(), vec![0; 100]),
+    ]);
+    let binary_copy = |flavors: &Flavors| {
+        Message::EventReceived(Event::BinaryCopied {
+            flavors: flavors.clone(),
+            source_app: None,
+        })
+    };
+
+    harness.send(binary_copy(&flavors));
+    harness.copy("text");
+    harness.send(binary_copy(&flavors));
+    harness.send(binary_copy(&Flavors::from([(
+        "public.tiff".to_string(),
+        vec![0; 2048],
+    )])));
+
+    assert_eq!(harness.app.entries.len(), 2);
+    let entry = &harness.app.entries[0];
+    assert_eq!(entry.content_type, ContentType::Binary);
+    assert_eq!(entry.mime_type.as_deref(), Some("image/png"));
+    assert_eq!(entry.flavors, flavors);
+    assert_eq!(
+        entry.content,
+        format!("{} … (0.1 KiB)", ["89 50 4E 47"; 8].join(" "))
+    );
+}
+
+#[test]
+fn entries_can_be_copied_as_json_without_pasting() {
+    let mut harness = Harness::new();
+    harness.copy("hello \"world\"");
+    let id = harness.id(0);
+    harness.send(Message::SetLabel(id, "greeting".to_string()));
+
+    harness.send(Message::CopyEntryAsJson(id));
+
+    let json: serde_json::Value = serde_json::from_str(&harness.clipboard.contents()).unwrap();
+    assert_eq!(json["content"], "hello \"world\"");
+    assert_eq!(json["label"], "greeting");
+    assert_eq!(json["timestamp"], harness.app.entries[0].timestamp);
+    assert!(harness.clipboard.contents().contains("\n  \"content\""));
+    assert_eq!(harness.app.entries.len(), 1);
+    assert_eq!(harness.app.toast.as_ref().unwrap().1, "Copied as JSON");
+}
+
+#[test]
+fn short_entries_can_be_shown_and_saved_as_qr_codes() {
+    let mut harness = Harness::new();
+    harness.copy(&"x".repeat(3000));
+    harness.copy("https://example.com");
+    let (short, long) = (harness.id(0), harness.id(1));
+
+    harness.send(Message::ShowQrCode(long));
+    assert!(harness.app.qr_code.is_none());
+
+    harness.send(Message::ShowQrCode(short));
+    assert!(harness.app.qr_code.is_some());
+    let image = harness.clipboard.contents();
+    assert!(image.ends_with(".png"));
+    assert!(fs::read(&image).unwrap().starts_with(b"\x89PNG"));
+    fs::remove_file(image).unwrap();
+
+    harness.send(Message::CloseFullView);
+    assert!(harness.app.qr_code.is_none());
+}
+
+#[test]
+fn the_size_histogram_jumps_to_the_first_entry_of_a_bucket() {
+    let mut harness = Harness::with_config(r#"{"display_limit": 1}"#);
+    harness.copy(&"a".repeat(20_000));
+    harness.copy(&"b".repeat(500));
+    harness.copy(&"c".repeat(100));
+    harness.copy("short");
+
+    assert_eq!(stats::size_histogram(&harness.app.entries), [1, 1, 1, 0, 1]);
+    assert_eq!(stats::storage_used(&harness.app.entries), 20_605);
+
+    harness.send(Message::ShowTab(Tab::Stats));
+    harness.send(Message::JumpToSizeBucket(2));
+    assert_eq!(harness.app.tab, Tab::History);
+    assert_eq!(harness.app.selected, Some(2));
+    assert_eq!(harness.app.shown_entries().len(), 3);
+
+    harness.send(Message::JumpToSizeBucket(3));
+    assert_eq!(harness.app.selected, Some(2));
+}
+
+#[test]
+fn double_copies_are_recorded_despite_exclusions() {
+    let mut harness =
+        Harness::with_config(r#"{"exclusion_patterns": ["^secret"], "double_copy_capture": true}"#);
+    let double_copy = |content: &str| {
+        Message::EventReceived(Event::ClipboardChanged {
+            content: content.to_string(),
+            source_app: None,
+            flavors: Flavors::new(),
+            selection: Selection::Clipboard,
+            user_initiated: true,
+            double_copied: true,
+        })
+    };
+    harness.copy("secret token");
+    harness.send(double_copy("secret token"));
+
+    assert_eq!(harness.contents(), ["secret token"]);
+}
+
+#[test]
+fn selecting_an_entry_sets_the_clipboard_and_hides_the_window() {
+    let mut harness = Harness::new();
+    harness.copy("older");
+    harness.copy("newer");
+    harness.app.window_visible = true;
+
+    harness.send(Message::SelectEntry(harness.id(1)));
+
+    assert_eq!(harness.clipboard.contents(), "older");
+    assert!(!harness.app.window_visible);
+}
+
+#[test]
+fn the_cycling_hotkey_steps_through_entries_and_pastes_on_release() {
+    let mut harness = Harness::with_config(r#"{"hold_to_cycle": true}"#);
+    for content in ["first", "second", "third"] {
+        harness.copy(content);
+    }
+
+    harness.send(Message::EventReceived(Event::CycleHotkeyTriggered));
+    assert!(harness.app.window_visible);
+    assert_eq!(harness.app.selected, Some(1));
+    harness.send(Message::EventReceived(Event::CycleHotkeyTriggered));
+    harness.send(Message::EventReceived(Event::CycleHotkeyTriggered));
+    assert_eq!(harness.app.selected, Some(0));
+    harness.send(Message::EventReceived(Event::CycleHotkeyTriggered));
+
+    harness.send(Message::CycleReleased);
+    assert_eq!(harness.clipboard.contents(), "second");
+    assert!(!harness.app.window_visible);
+}
+
+#[test]
+fn file_paths_can_be_revealed_instead_of_pasted_unless_option_is_held() {
+    let mut harness =
+        Harness::with_config(r#"{"default_action_by_kind": {"file_path": "reveal"}}"#);
+    harness.copy("/tmp/mac-clip-missing/report.pdf");
+    harness.copy("notes");
+    harness.app.window_visible = true;
+    assert_eq!(harness.app.entries[0].content_type, ContentType::PlainText);
+    assert_eq!(harness.app.entries[1].content_type, ContentType::FilePath);
+
+    harness.send(Message::SelectEntry(harness.id(1)));
+    assert!(harness.app.window_visible);
+    assert_eq!(
+        harness
+            .app
+            .toast
+            .as_ref()
+            .map(|(_, message)| message.as_str()),
+        Some("/tmp/mac-clip-missing/report.pdf no longer exists")
+    );
+
+    harness.send(Message::ModifiersChanged(keyboard::Modifiers::ALT));
+    harness.send(Message::SelectEntry(harness.id(1)));
+    assert_eq!(
+        harness.clipboard.contents(),
+        "/tmp/mac-clip-missing/report.pdf"
+    );
+}
+
+#[test]
+fn a_collection_is_pasted_one_entry_per_hotkey_press() {
+    let mut harness = Harness::new();
+    for content in ["Ada", "ada@example.com", "unrelated"] {
+        harness.copy(content);
+    }
+    harness.send(Message::ToggleChecked(harness.id(2)));
+    harness.send(Message::ToggleChecked(harness.id(1)));
+    harness.send(Message::CollectionNameChanged("signup form".to_string()));
+    harness.send(Message::SaveCollection);
+    assert!(
+        fs::read_to_string(harness.dir.path().join("collections.json"))
+            .unwrap()
+            .contains("signup form")
+    );
+
+    harness.send(Message::ToggleCollection(0));
+    harness.send(Message::EventReceived(Event::CollectionHotkeyTriggered));
+    assert_eq!(harness.clipboard.contents(), "Ada");
+    harness.send(Message::EventReceived(Event::CollectionHotkeyTriggered));
+    assert_eq!(harness.clipboard.contents(), "ada@example.com");
+    assert_eq!(harness.app.active_collection, None);
+    // Nothing happens once the collection has been pasted through
+    harness.send(Message::EventReceived(Event::CollectionHotkeyTriggered));
+    assert_eq!(harness.clipboard.contents(), "ada@example.com");
+}
+
+#[test]
+fn reloading_the_config_applies_the_new_history_size() {
+    let mut harness = Harness::new();
+    for i in 0..5 {
+        harness.copy(&format!("entry {}", i));
+    }
+
+    fs::write(
+        harness.dir.path().join("config.json"),
+        r#"{"max_history_size": 2, "exclusion_patterns": ["^secret"]}"#,
+    )
+    .unwrap();
+    harness.send(Message::ReloadConfig);
+    harness.copy("secret value");
+
+    assert_eq!(harness.contents(), ["entry 4", "entry 3"]);
+    assert_eq!(harness.saved_contents(), ["entry 4", "entry 3"]);
+}
+
+#[test]
+fn settings_are_not_saved_over_a_malformed_config() {
+    let malformed = r#"{"require_pin": true,"#;
+    let mut harness = Harness::with_config(malformed);
+
+    harness.send(Message::ToggleViewMode);
+    let saved = fs::read_to_string(harness.dir.path().join("config.json")).unwrap();
+    assert_eq!(saved, malformed);
+}
+
+#[test]
+fn a_malformed_config_is_not_reloaded() {
+    let hash = bcrypt::hash("1234", 4).unwrap();
+    let mut harness = Harness::with_config(&format!(
+        r#"{{"require_pin": true, "pin_hash": "{}"}}"#,
+        hash
+    ));
+    assert!(harness.app.pin_required());
+
+    fs::write(
+        harness.dir.path().join("config.json"),
+        r#"{"require_pin": true,"#,
+    )
+    .unwrap();
+    harness.send(Message::ReloadConfig);
+
+    assert!(harness.app.pin_required());
+    assert_eq!(harness.app.config.pin_hash, Some(hash));
+}
+
+#[test]
+fn the_list_is_capped_but_search_and_show_more_reach_older_entries() {
+    let mut harness = Harness::with_config(r#"{"display_limit": 2}"#);
+    for i in 0..5 {
+        harness.copy(&format!("entry {}", i));
+    }
+    let shown = |harness: &Harness| -> Vec<String> {
+        harness
+            .app
+            .shown_entries()
+            .iter()
+            .map(|(_, e)| e.content.clone())
+            .collect()
+    };
+
+    assert_eq!(shown(&harness), ["entry 4", "entry 3"]);
+    assert_eq!(harness.saved_contents().len(), 5);
+
+    harness.send(Message::SearchChanged("entry 0".to_string()));
+    assert_eq!(shown(&harness), ["entry 0"]);
+
+    harness.send(Message::SearchChanged(String::new()));
+    harness.send(Message::ShowMoreEntries);
+    assert_eq!(
+        shown(&harness),
+        ["entry 4", "entry 3", "entry 2", "entry 1"]
+    );
+}
+
+#[test]
+fn startup_delays_are_read_and_long_ones_flagged() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.json");
+    fs::write(
+        &path,
+        r#"{"startup_hotkey_delay_ms": 500, "startup_monitor_delay_ms": 60000}"#,
+    )
+    .unwrap();
+
+    let config = Config::load(&path);
+    assert_eq!(
+        (
+            config.startup_hotkey_delay_ms,
+            config.startup_monitor_delay_ms
+        ),
+        (500, 60000)
+    );
+    let flagged: Vec<_> = Config::check_file(&path)
+        .unwrap()
+        .into_iter()
+        .map(|issue| issue.path)
+        .collect();
+    assert_eq!(flagged, ["$.startup_monitor_delay_ms"]);
+}
+
+#[test]
+fn labels_are_saved_and_searchable() {
+    let mut harness = Harness::new();
+    harness.copy("sk-8f3a9c");
+    harness.copy("hello");
+
+    harness.send(Message::OpenFullView(harness.id(1)));
+    harness.send(Message::SetLabel(harness.id(1), "API key ".to_string()));
+    harness.send(Message::SetLabel(
+        harness.id(1),
+        "API key for staging".to_string(),
+    ));
+    harness.send(Message::CloseFullView);
+    harness.send(Message::SearchChanged("staging".to_string()));
+
+    assert_eq!(harness.app.visible_entries()[0].1.content, "sk-8f3a9c");
+    assert_eq!(harness.app.visible_entries().len(), 1);
+    let reopened = open_app(harness.dir.path(), &harness.clipboard);
+    assert_eq!(reopened.entries[1].label(), Some("API key for staging"));
+}
+
+#[test]
+fn double_clicking_the_content_edits_the_title() {
+    let mut harness = Harness::new();
+    harness.copy("eyJhbGciOiJIUzI1NiJ9");
+    harness.send(Message::OpenFullView(harness.id(0)));
+
+    harness.send(Message::PreviewClicked(harness.id(0)));
+    assert!(harness.app.title_edit.is_none());
+    harness.send(Message::PreviewClicked(harness.id(0)));
+    assert_eq!(harness.app.title_edit, Some((0, String::new())));
+
+    harness.send(Message::TitleDraftChanged(" Staging JWT ".to_string()));
+    harness.send(Message::SetEntryTitle(
+        harness.id(0),
+        " Staging JWT ".to_string(),
+    ));
+
+    assert!(harness.app.title_edit.is_none());
+    let reopened = open_app(harness.dir.path(), &harness.clipboard);
+    assert_eq!(reopened.entries[0].title.as_deref(), Some("Staging JWT"));
+}
+
+#[test]
+fn title_matches_are_listed_first() {
+    let mut harness = Harness::new();
+    harness.copy("deploy notes");
+    harness.copy("kubectl apply -f prod.yaml");
+    harness.copy("unrelated");
+    harness.send(Message::SetEntryTitle(
+        harness.id(1),
+        "Deploy to prod".to_string(),
+    ));
+
+    harness.send(Message::SearchChanged("deploy".to_string()));
+    let visible: Vec<usize> = harness
+        .app
+        .visible_entries()
+        .iter()
+        .map(|(i, _)| *i)
+        .collect();
+
+    assert_eq!(visible, [1, 2]);
+}
+
+#[test]
+fn copy_and_stay_open_keeps_the_window_up() {
+    let mut harness = Harness::with_config(r#"{"paste_behavior": "copy_and_stay_open"}"#);
+    harness.copy("older");
+    harness.copy("newer");
+    harness.app.window_visible = true;
+
+    harness.send(Message::SelectEntry(harness.id(1)));
+
+    assert_eq!(harness.clipboard.contents(), "older");
+    assert!(harness.app.window_visible);
+}
+
+#[test]
+fn deleting_an_entry_is_persisted() {
+    let mut harness = Harness::new();
+    harness.copy("first");
+    harness.copy("second");
+
+    harness.send(Message::DeleteEntry(harness.id(0)));
+
+    assert_eq!(harness.contents(), ["first"]);
+    assert_eq!(harness.saved_contents(), ["first"]);
+}
+
+#[test]
+fn history_is_reloaded_on_startup() {
+    let mut harness = Harness::new();
+    harness.copy("remember me");
+    harness.send(Message::TogglePin(harness.id(0)));
+
+    let reopened = open_app(harness.dir.path(), &harness.clipboard);
+
+    assert_eq!(reopened.entries.len(), 1);
+    assert_eq!(reopened.entries[0].content, "remember me");
+    assert!(reopened.entries[0].pinned);
+}
+
+#[test]
+fn window_position_is_remembered() {
+    let mut harness = Harness::with_config(r#"{"remember_window_position": true}"#);
+    harness.send(Message::WindowMoved(10, 10));
+    harness.app.window_visible = true;
+    harness.send(Message::WindowMoved(200, 150));
+
+    let reopened = open_app(harness.dir.path(), &harness.clipboard);
+
+    assert_eq!(
+        reopened.window_position,
+        Some(WindowPosition { x: 200, y: 150 })
+    );
+}
+
+#[test]
+fn window_position_is_clamped_onto_a_screen() {
+    let main = ScreenFrame {
+        x: 0.0,
+        y: 25.0,
+        width: 1440.0,
+        height: 875.0,
+        notch: 0.0,
+    };
+    let external = ScreenFrame {
+        x: 1440.0,
+        y: 0.0,
+        width: 1920.0,
+        height: 1080.0,
+        notch: 0.0,
+    };
+
+    // Straddling both screens: kept on the one it mostly covers
+    let position = WindowPosition { x: 3200, y: 900 };
+    assert_eq!(
+        window_state::clamp(position, (400, 500), &[main, external]),
+        WindowPosition { x: 2960, y: 580 }
+    );
+    // The external screen was unplugged
+    assert_eq!(
+        window_state::clamp(position, (400, 500), &[main]),
+        WindowPosition { x: 1040, y: 400 }
+    );
+    // No screen information
+    assert_eq!(window_state::clamp(position, (400, 500), &[]), position);
+}
+
+#[test]
+fn regex_transforms_apply_to_their_app_in_order() {
+    let rule = |app_bundle_id: &str, pattern: &str, replacement: &str| RegexTransformRule {
+        app_bundle_id: app_bundle_id.to_string(),
+        pattern: pattern.to_string(),
+        replacement: replacement.to_string(),
+    };
+    let transforms = RegexTransforms::new(&[
+        rule(
+            "com.atlassian.jira",
+            r"https://github\.com/([\w-]+)/([\w-]+)/pull/(\d+)",
+            "[$2#$3|$0]",
+        ),
+        rule("com.atlassian.jira", r"\s+$", ""),
+        rule("com.atlassian.jira", "(unclosed", ""),
+    ]);
+
+    assert_eq!(
+        paste::substitute(
+            &transforms,
+            "com.atlassian.jira",
+            "See https://github.com/acme/api/pull/42\n"
+        )
+        .as_deref(),
+        Some("See [api#42|https://github.com/acme/api/pull/42]")
+    );
+    assert_eq!(
+        paste::substitute(&transforms, "com.apple.TextEdit", "text"),
+        None
+    );
+}
+
+#[test]
+fn anchored_windows_open_on_the_screen_with_the_pointer() {
+    let main = ScreenFrame {
+        x: 0.0,
+        y: 25.0,
+        width: 1440.0,
+        height: 875.0,
+        notch: 0.0,
+    };
+    let external = ScreenFrame {
+        x: 1440.0,
+        y: 0.0,
+        width: 1920.0,
+        height: 1080.0,
+        notch: 0.0,
+    };
+    let screens = [main, external];
+    let on_external = Some((2000.0, 300.0));
+
+    assert_eq!(
+        window_state::anchored(
+            WindowAnchor::TopRight,
+            (400, 500),
+            10,
+            on_external,
+            &screens
+        ),
+        Some(WindowPosition { x: 2950, y: 10 })
+    );
+    assert_eq!(
+        window_state::anchored(WindowAnchor::BottomLeft, (400, 500), 10, None, &screens),
+        Some(WindowPosition { x: 10, y: 390 })
+    );
+    // Kept fully on screen when the pointer is near the bottom-right corner
+    assert_eq!(
+        window_state::anchored(
+            WindowAnchor::Cursor,
+            (400, 500),
+            10,
+            Some((3300.0, 1000.0)),
+            &screens
+        ),
+        Some(WindowPosition { x: 2960, y: 580 })
+    );
+    assert_eq!(
+        window_state::anchored(WindowAnchor::Center, (400, 500), 10, on_external, &screens),
+        None
+    );
+
+    // With the menu bar hidden, the top corners stay clear of the notch
+    let notched = ScreenFrame {
+        y: 0.0,
+        height: 900.0,
+        notch: 32.0,
+        ..main
+    };
+    assert_eq!(
+        window_state::anchored(WindowAnchor::TopLeft, (400, 500), 20, None, &[notched]),
+        Some(WindowPosition { x: 20, y: 52 })
+    );
+    assert_eq!(
+        window_state::anchored(WindowAnchor::BottomLeft, (400, 500), 20, None, &[notched]),
+        Some(WindowPosition { x: 20, y: 380 })
+    );
+}
+
+#[test]
+fn a_following_window_glides_after_the_pointer_until_hovered() {
+    let size = (400, 500);
+    assert_eq!(
+        window_state::follow_cursor(None, (100.0, 100.0), size),
+        Some((100.0, 120.0))
+    );
+    assert_eq!(
+        window_state::follow_cursor(Some((100.0, 120.0)), (600.0, 100.0), size),
+        Some((200.0, 120.0))
+    );
+    assert_eq!(
+        window_state::follow_cursor(Some((100.0, 120.0)), (300.0, 400.0), size),
+        None
+    );
+}
+
+#[test]
+fn smart_paste_transforms_text_for_the_target_app() {
+    let rules = paste::default_rules();
+
+    assert_eq!(
+        paste::transform(&rules, "com.apple.Terminal", "ls -la\n\n").as_deref(),
+        Some("ls -la")
+    );
+    assert_eq!(
+        paste::transform(
+            &rules,
+            "com.tinyspeck.slackmacgap",
+            "# Notes\n- **bold** and *italic* ~~gone~~ [docs](https://example.com)\n```\n**x**\n```"
+        )
+        .as_deref(),
+        Some("*Notes*\n• *bold* and _italic_ ~gone~ docs (https://example.com)\n```\n**x**\n```")
+    );
+    assert_eq!(
+        paste::transform(
+            &rules,
+            "com.microsoft.Excel",
+            "\u{201C}caf\u{e9}\u{201D} \u{2014} 5\u{a0}kg"
+        )
+        .as_deref(),
+        Some("\"caf\" - 5 kg")
+    );
+    assert_eq!(
+        paste::transform(&rules, "com.apple.TextEdit", "text\n"),
+        None
+    );
+    assert_eq!(Transform::StripTrailingNewlines.apply("a\r\n"), "a");
+}
+
+fn literal(text: &str) -> Box<QueryNode> {
+    Box::new(QueryNode::Literal(text.to_string()))
+}
+
+#[test]
+fn query_parser_builds_the_expected_tree() {
+    assert_eq!(query::parse(""), Ok(None));
+    assert_eq!(query::parse("   "), Ok(None));
+    assert_eq!(query::parse("Foo"), Ok(Some(*literal("foo"))));
+    assert_eq!(
+        query::parse("foo bar"),
+        Ok(Some(QueryNode::And(literal("foo"), literal("bar"))))
+    );
+    assert_eq!(
+        query::parse("foo AND bar OR baz"),
+        Ok(Some(QueryNode::Or(
+            Box::new(QueryNode::And(literal("foo"), literal("bar"))),
+            literal("baz")
+        )))
+    );
+    assert_eq!(
+        query::parse("foo AND (bar OR baz)"),
+        Ok(Some(QueryNode::And(
+            literal("foo"),
+            Box::new(QueryNode::Or(literal("bar"), literal("baz")))
+        )))
+    );
+    assert_eq!(
+        query::parse("NOT NOT foo"),
+        Ok(Some(QueryNode::Not(Box::new(QueryNode::Not(literal(
+            "foo"
+        ))))))
+    );
+    assert_eq!(
+        query::parse(r#"NOT "Exact  Phrase"(x)"#),
+        Ok(Some(QueryNode::And(
+            Box::new(QueryNode::Not(Box::new(QueryNode::Phrase(
+                "exact  phrase".to_string()
+            )))),
+            literal("x")
+        )))
+    );
+    // Lowercase operators are ordinary words
+    assert_eq!(
+        query::parse("this or that"),
+        Ok(Some(QueryNode::And(
+            Box::new(QueryNode::And(literal("this"), literal("or"))),
+            literal("that")
+        )))
+    );
+}
+
+#[test]
+fn query_parser_rejects_malformed_queries() {
+    assert_eq!(query::parse(r#""open"#), Err(ParseError::UnclosedQuote));
+    assert_eq!(query::parse(r#"a "b" "c"#), Err(ParseError::UnclosedQuote));
+    assert_eq!(query::parse("(foo"), Err(ParseError::UnclosedParen));
+    assert_eq!(
+        query::parse("(foo OR (bar)"),
+        Err(ParseError::UnclosedParen)
+    );
+    assert_eq!(query::parse("foo)"), Err(ParseError::UnexpectedCloseParen));
+    assert_eq!(query::parse("()"), Err(ParseError::UnexpectedCloseParen));
+    assert_eq!(
+        query::parse("foo AND"),
+        Err(ParseError::MissingOperand("AND"))
+    );
+    assert_eq!(
+        query::parse("(foo OR)"),
+        Err(ParseError::MissingOperand("OR"))
+    );
+    assert_eq!(query::parse("NOT"), Err(ParseError::MissingOperand("NOT")));
+    assert_eq!(
+        query::parse("OR foo"),
+        Err(ParseError::UnexpectedOperator("OR"))
+    );
+    assert_eq!(
+        query::parse("foo AND OR bar"),
+        Err(ParseError::UnexpectedOperator("OR"))
+    );
+}
+
+#[test]
+fn query_evaluation_matches_entry_content() {
+    let matches = |q: &str, content: &str| query::parse(q).unwrap().unwrap().matches(content);
+
+    assert!(matches("hello", "Say HELLO there"));
+    assert!(matches("hello world", "world, hello"));
+    assert!(!matches(r#""hello world""#, "world, hello"));
+    assert!(matches(r#""hello world""#, "oh Hello World!"));
+    assert!(matches("cat OR dog", "hot dog"));
+    assert!(!matches("cat AND dog", "hot dog"));
+    assert!(matches("NOT cat", "hot dog"));
+    assert!(!matches("dog NOT hot", "hot dog"));
+    assert!(matches("(cat OR dog) AND NOT (fish OR bird)", "dog food"));
+    assert!(!matches(
+        "(cat OR dog) AND NOT (fish OR bird)",
+        "dog and bird"
+    ));
+}
+
+#[test]
+fn search_field_uses_the_query_language() {
+    let mut harness = Harness::new();
+    harness.copy("invoice draft copy");
+    harness.copy("invoice final");
+    harness.copy("unrelated");
+
+    harness.send(Message::SearchChanged(
+        r#"invoice NOT "draft copy""#.to_string(),
+    ));
+    let visible: Vec<&str> = harness
+        .app
+        .visible_entries()
+        .iter()
+        .map(|(_, e)| e.content.as_str())
+        .collect();
+    assert_eq!(visible, ["invoice final"]);
+
+    // A half-typed query still searches for the words typed so far
+    harness.send(Message::SearchChanged("invoice AND".to_string()));
+    assert_eq!(harness.app.visible_entries().len(), 2);
+    harness.send(Message::SearchChanged(r#""invoice fin"#.to_string()));
+    assert_eq!(harness.app.visible_entries()[0].1.content, "invoice final");
+    assert_eq!(harness.app.visible_entries().len(), 1);
+}
+
+#[test]
+fn sharing_without_a_token_explains_what_to_set() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let result = runtime.block_on(share::share(&Config::default(), "hello"));
+
+    assert!(matches!(result, Err(ShareError::MissingToken("github"))));
+}
+
+#[test]
+fn search_index_follows_the_history() {
+    let mut harness = Harness::with_config(r#"{"max_history_size": 3}"#);
+    for word in ["Alpha", "Beta", "Gamma", "Delta"] {
+        harness.copy(word);
+    }
+    harness.copy("Beta");
+    harness.send(Message::DeleteEntry(harness.id(1)));
+    harness.send(Message::SetEntryTitle(harness.id(1), "Greek".to_string()));
+
+    let expected: Vec<String> = harness
+        .app
+        .entries
+        .iter()
+        .map(|e| e.search_text().to_lowercase())
+        .collect();
+    let indexed: Vec<&str> = (0..harness.app.search_index.len())
+        .map(|i| harness.app.search_index.get(i))
+        .collect();
+    assert_eq!(indexed, expected);
+    harness.send(Message::SearchChanged("GREEK".to_string()));
+    assert_eq!(harness.app.visible_entries()[0].1.content, "Gamma");
+}
+
+#[test]
+fn a_masked_window_keeps_full_views_closed() {
+    let mut harness = Harness::with_config(r#"{"screen_capture_guard": "mask"}"#);
+    harness.copy("secret");
+    harness.app.capture_masked = true;
+
+    harness.send(Message::OpenFullView(harness.id(0)));
+    assert_eq!(harness.app.full_view, None);
+
+    harness.app.capture_masked = false;
+    harness.send(Message::OpenFullView(harness.id(0)));
+    assert_eq!(harness.app.full_view, Some(0));
+}
+
+#[test]
+fn a_text_file_is_imported_line_by_line_in_order() {
+    let mut harness = Harness::with_config(r#"{"exclusion_patterns": ["^secret"]}"#);
+    harness.copy("existing");
+    let path = harness.dir.path().join("snippets.txt");
+    fs::write(&path, "first\r\nsecret key\n\nsecond\n").unwrap();
+
+    harness.send(Message::ImportFileSelected(path));
+    harness.send(Message::ImportProgress(0, 3));
+
+    assert_eq!(harness.contents(), ["first", "second", "existing"]);
+    assert_eq!(harness.saved_contents(), ["first", "second", "existing"]);
+    assert_eq!(harness.app.text_import.as_ref().unwrap().imported, 2);
+    harness.send(Message::ImportComplete(2));
+    assert!(harness.app.text_import.is_none());
+}
+
+#[test]
+fn entries_past_the_age_limit_are_removed_unless_pinned() {
+    const DAY: u64 = 24 * 60 * 60;
+    let dir = TempDir::new().unwrap();
+    let now = storage::unix_now();
+    let entries = [
+        ("recent", now - DAY, false),
+        ("old pinned", now - 40 * DAY, true),
+        ("old", now - 40 * DAY, false),
+    ]
+    .into_iter()
+    .map(|(content, timestamp, pinned)| ClipboardEntry {
+        content: content.to_string(),
+        timestamp,
+        pinned,
+        ..Default::default()
+    })
+    .collect();
+    storage::save_history(
+        &dir.path().join("history.json"),
+        &entries,
+        StorageFormat::Json,
+    )
+    .unwrap();
+    let mut harness = Harness {
+        app: open_app(dir.path(), &MemoryClipboard::default()),
+        clipboard: MemoryClipboard::default(),
+        dir,
+        runtime: tokio::runtime::Runtime::new().unwrap(),
+    };
+    assert_eq!(harness.contents(), ["recent", "old pinned", "old"]);
+
+    harness.send(Message::SetAgeLimit(AgeLimit::Days(30)));
+    assert_eq!(harness.contents(), ["recent", "old pinned"]);
+    assert_eq!(harness.saved_contents(), ["recent", "old pinned"]);
+    let config = Config::load(&harness.dir.path().join("config.json"));
+    assert_eq!(config.max_age_days, Some(30));
+
+    harness.send(Message::SetAgeLimit(AgeLimit::Custom));
+    harness.send(Message::CustomAgeLimitChanged("0".to_string()));
+    assert_eq!(harness.app.config.max_age_days, Some(30));
+    harness.send(Message::CustomAgeLimitChanged("12".to_string()));
+    assert_eq!(harness.app.config.max_age_days, Some(12));
+}
+
+#[test]
+fn row_details_are_chosen_in_settings_and_saved() {
+    let mut harness = Harness::new();
+    let entry = ClipboardEntry {
+        content: "héllo".to_string(),
+        source_app: Some("com.apple.Safari".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(entry_details(&entry, harness.app.config.row_details), None);
+
+    harness.send(Message::SetRowDetails(RowDetails {
+        source_app: true,
+        char_count: true,
+        ..RowDetails::default()
+    }));
+    assert_eq!(
+        entry_details(&entry, harness.app.config.row_details).as_deref(),
+        Some("com.apple.Safari · 5 chars")
+    );
+    let saved = Config::load(&harness.dir.path().join("config.json"));
+    assert_eq!(saved.row_details, harness.app.config.row_details);
+}
+
+#[test]
+fn copying_a_snippet_trigger_puts_its_entry_on_the_clipboard() {
+    let mut harness = Harness::new();
+    harness.copy("1 Infinite Loop\nCupertino");
+    harness.send(Message::OpenFullView(harness.id(0)));
+    harness.send(Message::SetSnippetTrigger(
+        harness.id(0),
+        "/addr".to_string(),
+    ));
+    harness.send(Message::CloseFullView);
+    assert_eq!(harness.app.entries[0].snippet_trigger(), Some("/addr"));
+
+    harness.copy("/addr ");
+    assert_eq!(harness.clipboard.contents(), "1 Infinite Loop\nCupertino");
+    assert_eq!(harness.contents(), ["1 Infinite Loop\nCupertino"]);
+
+    // Reloaded with the history
+    let app = open_app(harness.dir.path(), &harness.clipboard);
+    assert_eq!(app.entries[0].snippet_trigger(), Some("/addr"));
+}
+
+#[test]
+fn snippet_triggers_are_not_expanded_while_the_pin_is_required() {
+    let mut harness = Harness::new();
+    harness.copy("1 Infinite Loop\nCupertino");
+    harness.send(Message::OpenFullView(harness.id(0)));
+    harness.send(Message::SetSnippetTrigger(
+        harness.id(0),
+        "/addr".to_string(),
+    ));
+    harness.send(Message::CloseFullView);
+    assert!(harness.app.expansions.expand("/addr").is_some());
+    harness.app.config.require_pin = true;
+    harness.app.config.pin_hash = Some(bcrypt::hash("1234", 4).unwrap());
+    assert!(harness.app.pin_required());
+
+    harness.copy("/addr");
+    assert_eq!(harness.clipboard.contents(), "");
+    assert_eq!(harness.contents(), ["/addr", "1 Infinite Loop\nCupertino"]);
+}
+
+#[test]
+fn view_mode_is_toggled_and_saved() {
+    let mut harness = Harness::with_config(r#"{"grid_columns": 2}"#);
+    for content in ["one", "two\nlines\nand more", "three"] {
+        harness.copy(content);
+    }
+    harness.app.window_visible = true;
+
+    harness.send(Message::ToggleViewMode);
+    assert_eq!(harness.app.config.view_mode, ViewMode::Grid);
+    let saved = Config::load(&harness.dir.path().join("config.json"));
+    assert_eq!(saved.view_mode, ViewMode::Grid);
+    assert_eq!(saved.grid_columns, 2);
+    // A full row of two tiles and a short one
+    let _ = harness.app.view();
+
+    harness.send(Message::ToggleViewMode);
+    assert_eq!(harness.app.config.view_mode, ViewMode::List);
+}
+
+#[test]
+fn messages_keep_referring_to_the_same_entry_as_others_are_added() {
+    let mut harness = Harness::new();
+    harness.copy("first");
+    let id = harness.id(0);
+    harness.copy("second");
+    harness.send(Message::TogglePin(id));
+    assert!(harness.app.entries[1].pinned);
+
+    // Ids are saved, and entries from before they existed get new ones
+    let reopened = open_app(harness.dir.path(), &MemoryClipboard::default());
+    assert_eq!(reopened.entries[1].id, id);
+    fs::write(
+        harness.dir.path().join("history.json"),
+        r#"[{"content": "a", "timestamp": 1}, {"content": "b", "timestamp": 2}]"#,
+    )
+    .unwrap();
+    let reopened = open_app(harness.dir.path(), &MemoryClipboard::default());
+    assert_ne!(reopened.entries[0].id, reopened.entries[1].id);
+}
+
+#[test]
+fn telemetry_records_feature_names_only_when_opted_in() {
+    let mut harness = Harness::new();
+    harness.copy("secret token");
+    harness.send(Message::TogglePin(harness.id(0)));
+    let queue = harness.dir.path().join("telemetry.jsonl");
+    assert!(!queue.exists());
+
+    let mut harness = Harness::with_config(r#"{"telemetry_opt_in": true}"#);
+    harness.copy("secret token");
+    harness.send(Message::TogglePin(harness.id(0)));
+    harness.send(Message::SearchChanged("s".to_string()));
+    harness.send(Message::SearchChanged("se".to_string()));
+    let queue = harness.dir.path().join("telemetry.jsonl");
+    assert!(!fs::read_to_string(&queue).unwrap().contains("secret"));
+
+    let recorder = TelemetryRecorder::new(queue);
+    let events: Vec<String> = recorder
+        .queued()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.event)
+        .collect();
+    assert_eq!(events, ["toggle_pin", "search"]);
+    let now = storage::unix_now();
+    assert!(recorder.due_batch(now).unwrap().is_none());
+    let batch = recorder.due_batch(now + 24 * 60 * 60).unwrap().unwrap();
+    assert_eq!(batch.events.len(), 2);
+
+    recorder.record("select_entry", now).unwrap();
+    recorder.remove_sent(batch.events.len()).unwrap();
+    assert_eq!(recorder.queued().unwrap().len(), 1);
+    assert!(recorder.reset().unwrap());
+    assert!(recorder.queued().unwrap().is_empty());
+}
+
+#[test]
+fn newlines_are_shown_as_configured() {
+    let content = "one\ntwo\nthree";
+    let preview = |newlines| widgets::entry_preview(content, newlines, 2);
+    assert_eq!(preview(NewlineDisplay::Symbol), "one↵two↵three");
+    assert_eq!(preview(NewlineDisplay::Space), "one two three");
+    assert_eq!(preview(NewlineDisplay::EscapeSequence), "one\\ntwo\\nthree");
+    assert_eq!(preview(NewlineDisplay::Actual), "one\ntwo\n... 1 more line");
+    assert_eq!(
+        widgets::entry_preview(&"x".repeat(60), NewlineDisplay::Actual, 2),
+        format!("{}...", "x".repeat(50))
+    );
+}
+
+#[test]
+fn t_copies_the_highlighted_entrys_timestamp() {
+    let mut harness = Harness::new();
+    harness.copy("first");
+    harness.copy("second");
+    harness.send(Message::MoveSelection(1));
+    harness.send(Message::MoveSelection(1));
+    harness.send(Message::CopySelectedTimestamp);
+
+    let copied = chrono::DateTime::parse_from_rfc3339(&harness.clipboard.contents()).unwrap();
+    assert_eq!(copied.timestamp() as u64, harness.app.entries[1].timestamp);
+    assert_eq!(harness.contents(), ["second", "first"]);
+}
+
+/// Compares searching the index with lowercasing every entry per search.
+/// Run with `cargo test --release -- --ignored --nocapture search_benchmark`.
+#[test]
+#[ignore]
+fn search_benchmark() {
+    const ENTRIES: usize = 2000;
+    const SEARCHES: u32 = 100;
+
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("config.json"),
+        format!(r#"{{"max_history_size": {}}}"#, ENTRIES),
+    )
+    .unwrap();
+    let entries = (0..ENTRIES)
+        .map(|i| ClipboardEntry {
+            content: format!("Entry {} {}", i, "Lorem Ipsum Dolor Sit Amet ".repeat(20)),
+            ..Default::default()
+        })
+        .collect();
+    storage::save_history(
+        &dir.path().join("history.json"),
+        &entries,
+        StorageFormat::Json,
+    )
+    .unwrap();
+    let mut app = open_app(dir.path(), &MemoryClipboard::default());
+    app.search_query = "ipsum NOT 1999".to_string();
+    let query = query::parse_lenient(&app.search_query).unwrap();
+
+    let start = Instant::now();
+    for _ in 0..SEARCHES {
+        let matched = app
+            .entries
+            .iter()
+            .filter(|e| query.matches(&e.search_text()))
+            .count();
+        assert_eq!(matched, ENTRIES - 1);
+    }
+    let lowercasing = start.elapsed() / SEARCHES;
+
+    let start = Instant::now();
+    for _ in 0..SEARCHES {
+        assert_eq!(app.visible_entries().len(), ENTRIES - 1);
+    }
+    let indexed = start.elapsed() / SEARCHES;
+
+    println!(
+        "{} entries: {:?} per search lowercasing, {:?} with the index",
+        ENTRIES, lowercasing, indexed
+    );
+}
+
+#[test]
+fn diff_marks_common_removed_and_added_lines() {
+    let changes = diff::diff_lines("a\nb\nc\nd", "a\nc\nx\nd");
+
+    assert_eq!(
+        changes,
+        [
+            Change::Equal("a".to_string()),
+            Change::Removed("b".to_string()),
+            Change::Equal("c".to_string()),
+            Change::Added("x".to_string()),
+            Change::Equal("d".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn two_ticked_entries_can_be_compared() {
+    let mut harness = Harness::new();
+    harness.copy("host: a\nport: 80");
+    harness.copy("host: a\nport: 8080");
+    harness.copy("unrelated");
+    harness.send(Message::ToggleChecked(harness.id(2)));
+    harness.send(Message::ToggleChecked(harness.id(0)));
+    harness.send(Message::ToggleChecked(harness.id(0)));
+    harness.send(Message::ToggleChecked(harness.id(1)));
+    assert_eq!(harness.app.checked, [2, 1]);
+
+    harness.send(Message::CompareEntries(harness.id(2), harness.id(1)));
+    assert_eq!(
+        harness.app.comparison,
+        [
+            Change::Equal("host: a".to_string()),
+            Change::Removed("port: 80".to_string()),
+            Change::Added("port: 8080".to_string()),
+        ]
+    );
+    // A new copy shifts the compared entries down
+    harness.copy("newest");
+    assert_eq!(harness.app.comparison_view, Some((3, 2)));
+
+    harness.send(Message::CloseFullView);
+    assert!(harness.app.comparison_view.is_none());
+    assert_eq!(harness.app.checked, [3, 2]);
+}
+
+#[test]
+fn large_copies_follow_the_configured_policy() {
+    let large = "é".repeat(10);
+
+    let mut harness = Harness::with_config(r#"{"max_entry_bytes": 5}"#);
+    // Nobody to ask while the window is hidden
+    harness.copy(&large);
+    assert!(harness.app.entries.is_empty());
+    harness.app.window_visible = true;
+    harness.copy(&large);
+    assert!(harness.app.entries.is_empty());
+    harness.send(Message::ResolveLargeCopy(LargeContentPolicy::Store));
+    assert_eq!(harness.contents(), [large.as_str()]);
+
+    let mut harness =
+        Harness::with_config(r#"{"max_entry_bytes": 5, "large_content_policy": "truncate"}"#);
+    harness.copy(&large);
+    assert_eq!(harness.contents(), ["éé"]);
+}
+
+#[test]
+fn content_hashes_are_the_same_in_every_build() {
+    assert_eq!(storage::content_hash(""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(storage::content_hash("a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(storage::content_hash("foobar"), 0x8594_4171_f739_67e8);
+}
+
+#[test]
+fn only_one_instance_can_hold_the_lock() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("mac-clip.lock");
+
+    let lock = InstanceLock::acquire(&path).unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        std::process::id().to_string()
+    );
+    assert!(matches!(
+        InstanceLock::acquire(&path),
+        Err(LockError::AlreadyRunning(Some(pid))) if pid == std::process::id()
+    ));
+
+    drop(lock);
+    assert!(!path.exists());
+    assert!(InstanceLock::acquire(&path).is_ok());
+}
+
+#[test]
+fn a_held_lock_without_a_pid_is_not_taken_over() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("mac-clip.lock");
+    let starting = File::create(&path).unwrap();
+    starting.try_lock_exclusive().unwrap();
+
+    assert!(matches!(
+        InstanceLock::acquire(&path),
+        Err(LockError::AlreadyRunning(None))
+    ));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "");
+}
+
+#[test]
+fn webdav_downloads_are_merged_with_the_newer_copy_winning() {
+    let mut harness = Harness::new();
+    harness.copy("kept");
+    harness.copy("edited elsewhere");
+    let mut edited = harness.app.entries[0].clone();
+    edited.timestamp += 10;
+    edited.pinned = true;
+    let mut stale = harness.app.entries[1].clone();
+    stale.timestamp -= 10;
+    stale.pinned = true;
+    let added = ClipboardEntry {
+        content: "from another mac".to_string(),
+        timestamp: edited.timestamp - 5,
+        ..Default::default()
+    };
+
+    harness.send(Message::WebDavDownloaded(Ok(Some(RemoteHistory {
+        entries: [edited, added, stale].into(),
+        ..Default::default()
+    }))));
+
+    let summary: Vec<_> = harness
+        .app
+        .entries
+        .iter()
+        .map(|e| (e.content.as_str(), e.pinned))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("edited elsewhere", true),
+            ("from another mac", false),
+            ("kept", false),
+        ]
+    );
+    assert_eq!(harness.saved_contents().len(), 3);
+}
+
+#[test]
+fn entries_deleted_here_stay_deleted_through_a_webdav_sync() {
+    let mut harness = Harness::with_config(r#"{"webdav_url": "https://dav.example.com"}"#);
+    harness.copy("deleted");
+    harness.copy("kept");
+    let remote: VecDeque<ClipboardEntry> = harness.app.entries.clone();
+    let deleted = harness.id(1);
+
+    harness.send(Message::DeleteEntry(deleted));
+    harness.send(Message::WebDavDownloaded(Ok(Some(RemoteHistory {
+        entries: remote,
+        ..Default::default()
+    }))));
+    assert_eq!(harness.contents(), ["kept"]);
+    let saved = webdav::load_tombstones(&harness.dir.path().join("webdav_deleted.json"));
+    assert!(saved.contains_key(&deleted));
+
+    // Deleted on another machine
+    let kept = harness.id(0);
+    harness.send(Message::WebDavDownloaded(Ok(Some(RemoteHistory {
+        entries: VecDeque::new(),
+        deleted: [(kept, storage::unix_now())].into(),
+    }))));
+    assert!(harness.contents().is_empty());
+
+    // As uploaded before deletions were synced
+    let old: RemoteHistory = serde_json::from_str(r#"[{"content": "a", "timestamp": 1}]"#).unwrap();
+    assert_eq!(old.entries.len(), 1);
+}
+
+#[test]
+fn sync_safe_storage_merges_entries_saved_elsewhere() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("history.json");
+    let entry = |content: &str, timestamp: u64| ClipboardEntry {
+        content: content.to_string(),
+        timestamp,
+        ..Default::default()
+    };
+    let contents = |entries: &VecDeque<ClipboardEntry>| {
+        entries
+            .iter()
+            .map(|e| e.content.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let mut laptop = HistoryStore::new(dir.path(), StorageFormat::Json, true);
+    let mut laptop_entries = laptop.load();
+    laptop_entries.push_front(entry("shared", 1));
+    assert!(!laptop.save(&mut laptop_entries).unwrap());
+
+    let mut desktop = HistoryStore::new(dir.path(), StorageFormat::Json, true);
+    let mut desktop_entries = desktop.load();
+    // Make sure the next write gets a new modification time
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    laptop_entries.push_front(entry("from laptop", 2));
+    laptop.save(&mut laptop_entries).unwrap();
+
+    desktop_entries.push_front(entry("from desktop", 3));
+    assert!(desktop.save(&mut desktop_entries).unwrap());
+    assert_eq!(
+        contents(&desktop_entries),
+        ["from desktop", "from laptop", "shared"]
+    );
+    assert_eq!(
+        contents(&storage::load_history(&path)),
+        ["from desktop", "from laptop", "shared"]
+    );
+}
+
+#[test]
+fn switching_storage_format_converts_the_history() {
+    let dir = TempDir::new().unwrap();
+    let summary = |entries: &VecDeque<ClipboardEntry>| {
+        entries
+            .iter()
+            .map(|e| (e.content.clone(), e.pinned))
+            .collect::<Vec<_>>()
+    };
+    let json_path = dir.path().join("history.json");
+    let msgpack_path = dir.path().join("history.msgpack");
+    let mut entries: VecDeque<ClipboardEntry> = ["b", "a"]
+        .iter()
+        .map(|content| ClipboardEntry {
+            content: content.to_string(),
+            pinned: *content == "a",
+            ..Default::default()
+        })
+        .collect();
+    HistoryStore::new(dir.path(), StorageFormat::Json, false)
+        .save(&mut entries)
+        .unwrap();
+
+    let mut store = HistoryStore::new(dir.path(), StorageFormat::MessagePack, false);
+    let loaded = store.load();
+    assert_eq!(summary(&loaded), summary(&entries));
+    assert!(msgpack_path.exists());
+    assert!(!json_path.exists());
+    assert_eq!(storage::history_path(dir.path()), msgpack_path);
+
+    store.set_format(StorageFormat::Json);
+    store.save(&mut entries).unwrap();
+    assert!(!msgpack_path.exists());
+    assert_eq!(
+        summary(&storage::load_history(&json_path)),
+        summary(&entries)
+    );
+}
+
+#[test]
+fn status_reports_whether_the_daemon_runs_and_its_last_capture() {
+    let status = DaemonStatus {
+        launch_agent_loaded: true,
+        pid: Some(42),
+        entries: 12,
+        last_capture: Some(1_000),
+    };
+    assert!(status.healthy());
+    assert_eq!(
+        status.summary(1_000 + 2 * 3600 + 5),
+        [
+            "LaunchAgent: loaded",
+            "Process: running (PID 42)",
+            "History: 12 entries",
+            "Last capture: 2 hours ago",
+        ]
+    );
+
+    let stopped = DaemonStatus {
+        pid: None,
+        last_capture: None,
+        ..status
+    };
+    assert!(!stopped.healthy());
+    assert_eq!(stopped.summary(0)[3], "Last capture: never");
+    assert_eq!(diagnostics::describe_age(61), "1 minute ago");
+}
+
+#[test]
+fn recent_searches_can_be_recalled() {
+    let mut harness = Harness::new();
+    harness.copy("apple pie");
+    harness.copy("banana bread");
+    for query in ["apple", "banana", "apple"] {
+        harness.send(Message::SearchChanged(query.to_string()));
+        harness.send(Message::ToggleWindow);
+    }
+    assert_eq!(harness.app.search_history, ["apple", "banana"]);
+
+    harness.send(Message::SearchChanged(String::new()));
+    harness.send(Message::MoveSelection(-1));
+    harness.send(Message::MoveSelection(-1));
+    assert_eq!(harness.app.search_query, "banana");
+    harness.send(Message::MoveSelection(1));
+    harness.send(Message::MoveSelection(1));
+    assert_eq!(harness.app.search_query, "");
+    assert_eq!(harness.app.selected, None);
+
+    harness.send(Message::SearchHistorySelected("banana".to_string()));
+    harness.send(Message::MoveSelection(1));
+    assert_eq!(harness.app.selected, Some(0));
+
+    harness.send(Message::ClearSearchHistory);
+    assert!(harness.app.search_history.is_empty());
+}
+
+#[test]
+fn quick_access_hotkeys_paste_the_newest_entries() {
+    let mut harness = Harness::with_config(r#"{"quick_access_slots": 12}"#);
+    for content in ["third", "second", "first"] {
+        harness.copy(content);
+    }
+
+    harness.send(Message::EventReceived(Event::QuickAccessTriggered(1)));
+    assert_eq!(harness.clipboard.contents(), "second");
+    // Out of range
+    harness.send(Message::EventReceived(Event::QuickAccessTriggered(5)));
+    assert_eq!(harness.clipboard.contents(), "second");
+
+    assert_eq!(crate::quick_access_label(0), "⌥⌘1");
+    assert_eq!(crate::quick_access_label(9), "⌥⌘0");
+    assert_eq!(crate::quick_access_label(10), "⇧⌥⌘1");
+    assert_ne!(
+        crate::quick_access_hotkey(0).id(),
+        crate::quick_access_hotkey(10).id()
+    );
+}
+
+#[test]
+fn copy_domain_copies_the_domain_of_an_email_address() {
+    let mut harness = Harness::new();
+    harness.copy(" someone@mail.example.com ");
+    assert_eq!(harness.app.entries[0].content_type, ContentType::Email);
+
+    harness.send(Message::CopyEmailDomain(harness.id(0)));
+    assert_eq!(harness.clipboard.contents(), "mail.example.com");
+}
+
+#[test]
+fn profiles_have_their_own_config_and_history() {
+    let dir = TempDir::new().unwrap();
+    assert_eq!(
+        storage::config_path(dir.path(), Some("default")),
+        dir.path().join("config.json")
+    );
+    let work_dir = dir.path().join("profiles").join("work");
+    fs::create_dir_all(&work_dir).unwrap();
+    fs::write(
+        storage::config_path(dir.path(), Some("work")),
+        r#"{"max_history_size": 1}"#,
+    )
+    .unwrap();
+    fs::write(dir.path().join("config_home.json"), "{}").unwrap();
+
+    let clipboard = MemoryClipboard::default();
+    let (tx, _rx) = mpsc::unbounded_channel();
+    let (_event_tx, event_rx) = watch::channel(None);
+    let config_path = storage::config_path(dir.path(), Some("work"));
+    let mut work = Harness {
+        app: MacClip::with_storage(&work_dir, config_path, clipboard.shared(), tx, event_rx),
+        clipboard,
+        dir,
+        runtime: tokio::runtime::Runtime::new().unwrap(),
+    };
+    work.copy("first");
+    work.copy("second");
+
+    assert_eq!(work.contents(), ["second"]);
+    assert!(work_dir.join("history.json").exists());
+    assert!(!work.dir.path().join("history.json").exists());
+    assert_eq!(
+        storage::list_profiles(work.dir.path()).unwrap(),
+        ["default", "home", "work"]
+    );
+    assert!(storage::is_valid_profile_name("work-2"));
+    assert!(!storage::is_valid_profile_name("../work"));
+}
+
+#[test]
+fn pasta_history_is_read_from_its_core_data_store() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("database.sqlite");
+    let db = rusqlite::Connection::open(&path).unwrap();
+    db.execute_batch(
+        "CREATE TABLE Z_METADATA (Z_VERSION INTEGER, Z_UUID VARCHAR);
+         CREATE TABLE ZPASTEITEM (Z_PK INTEGER PRIMARY KEY, ZTEXT VARCHAR, ZDATE TIMESTAMP,
+                                  ZBUNDLEIDENTIFIER VARCHAR);
+         INSERT INTO ZPASTEITEM VALUES (1, 'older', 700000000.5, 'com.apple.Safari');
+         INSERT INTO ZPASTEITEM VALUES (2, 'newer', 700000060, NULL);
+         INSERT INTO ZPASTEITEM VALUES (3, NULL, 700000120, 'com.apple.Preview');",
+    )
+    .unwrap();
+    drop(db);
+
+    let entries = pasta::parse_pasta_db(&path).unwrap();
+    let summary: Vec<_> = entries
+        .iter()
+        .map(|e| (e.content.as_str(), e.timestamp, e.source_app.as_deref()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("newer", 1_678_307_260, None),
+            ("older", 1_678_307_200, Some("com.apple.Safari")),
+        ]
+    );
+    assert!(pasta::parse_pasta_db(&dir.path().join("missing.sqlite")).is_err());
+}
+
+#[test]
+fn a_bundle_moves_the_config_and_history_to_another_machine() {
+    let old_machine = TempDir::new().unwrap();
+    let new_machine = TempDir::new().unwrap();
+    let bundle_path = old_machine.path().join("setup.bundle");
+    fs::write(
+        old_machine.path().join("config.json"),
+        r#"{"max_history_size": 7}"#,
+    )
+    .unwrap();
+    let mut entries: VecDeque<ClipboardEntry> = [ClipboardEntry {
+        content: "moved".to_string(),
+        pinned: true,
+        ..Default::default()
+    }]
+    .into();
+    HistoryStore::new(old_machine.path(), StorageFormat::Json, false)
+        .save(&mut entries)
+        .unwrap();
+    // Left over on the new machine in the other format, so it must not win
+    storage::save_history(
+        &new_machine.path().join("history.msgpack"),
+        &VecDeque::from([ClipboardEntry::default()]),
+        StorageFormat::MessagePack,
+    )
+    .unwrap();
+
+    let exported = bundle::export(
+        old_machine.path(),
+        &old_machine.path().join("config.json"),
+        &bundle_path,
+    )
+    .unwrap();
+    // Into a profile, whose config is kept outside its directory
+    let config_path = storage::config_path(new_machine.path(), Some("work"));
+    let imported = bundle::import(new_machine.path(), &config_path, &bundle_path).unwrap();
+
+    assert_eq!(imported.files, exported.files);
+    assert_eq!(imported.version, bundle::BUNDLE_VERSION);
+    let history = HistoryStore::new(new_machine.path(), StorageFormat::Json, false).load();
+    assert_eq!(history.len(), 1);
+    assert_eq!(
+        (history[0].content.as_str(), history[0].pinned),
+        ("moved", true)
+    );
+    assert!(!new_machine.path().join("history.msgpack").exists());
+    let config = Config::load(&config_path);
+    assert_eq!(config.max_history_size, 7);
+}
+
+#[test]
+fn the_watchdog_holds_off_after_five_restarts_a_minute() {
+    let mut watchdog = WatchdogThread::new("clipboard monitor");
+    let start = Instant::now();
+    let at = |secs| start + Duration::from_secs(secs);
+
+    for secs in 0..5 {
+        assert_eq!(watchdog.schedule_restart(at(secs)), Duration::ZERO);
+    }
+    assert_eq!(watchdog.schedule_restart(at(10)), Duration::from_secs(50));
+    // The held-off restart counts from when it happens
+    assert_eq!(watchdog.schedule_restart(at(61)), Duration::ZERO);
+    assert_eq!(watchdog.schedule_restart(at(62)), Duration::ZERO);
+    assert_eq!(watchdog.schedule_restart(at(63)), Duration::ZERO);
+    assert_eq!(watchdog.schedule_restart(at(64)), Duration::ZERO);
+    assert_eq!(watchdog.schedule_restart(at(65)), Duration::from_secs(55));
+}
+
+#[test]
+fn the_watchdog_restarts_a_monitor_that_panicked() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let runs = AtomicUsize::new(0);
+    thread::spawn(move || {
+        WatchdogThread::new("test monitor").run(move || {
+            let run = runs.fetch_add(1, Ordering::SeqCst);
+            tx.send(run).unwrap();
+            if run == 0 {
+                panic!("clipboard read failed");
+            }
+            thread::park();
+        })
+    });
+
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(0));
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(1));
+}
+
+#[test]
+fn a_monitor_that_stops_beating_is_reported_once() {
+    let monitor = HeartbeatMonitor::default();
+    let mut checker = monitor.clone();
+    // Not started yet
+    assert_eq!(checker.check(), None);
+
+    monitor.beat();
+    assert_eq!(checker.check(), None);
+    assert_eq!(checker.check(), Some(HeartbeatChange::Stalled));
+    assert_eq!(checker.check(), None);
+
+    monitor.beat();
+    assert_eq!(checker.check(), Some(HeartbeatChange::Recovered));
+    monitor.beat();
+    assert_eq!(checker.check(), None);
+}
+
+#[test]
+fn daemon_logs_start_from_the_last_lines() {
+    let log = "one\ntwo\nthree\nfour";
+    let mut reader = log.as_bytes();
+
+    let lines = cli::read_last_lines(&mut reader, 2).unwrap();
+    assert_eq!(lines, ["three\n", "four"]);
+    assert!(reader.is_empty());
+    assert!(cli::read_last_lines(&mut log.as_bytes(), 0)
+        .unwrap()
+        .is_empty());
+}
+
+/// Run with `cargo test --release -- --ignored --nocapture storage_format_benchmark`.
+#[test]
+#[ignore]
+fn storage_format_benchmark() {
+    const ENTRIES: usize = 500;
+    const ROUNDS: u32 = 20;
+
+    let mut entries: VecDeque<ClipboardEntry> = (0..ENTRIES)
+        .map(|i| ClipboardEntry {
+            content: format!("Entry {} {}", i, "Lorem Ipsum Dolor Sit Amet ".repeat(20)),
+            timestamp: 1_700_000_000 + i as u64,
+            source_app: Some("com.apple.Safari".to_string()),
+            ..Default::default()
+        })
+        .collect();
+
+    for format in [StorageFormat::Json, StorageFormat::MessagePack] {
+        let dir = TempDir::new().unwrap();
+        let mut store = HistoryStore::new(dir.path(), format, false);
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            store.save(&mut entries).unwrap();
+        }
+        let save = start.elapsed() / ROUNDS;
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            assert_eq!(store.load().len(), ENTRIES);
+        }
+        let load = start.elapsed() / ROUNDS;
+
+        println!(
+            "{:?}, {} entries: {} bytes, {:?} to save, {:?} to load",
+            format,
+            ENTRIES,
+            fs::metadata(store.path()).unwrap().len(),
+            save,
+            load
+        );
+    }
+}
+
+#[test]
+fn frozen_history_holds_new_copies_until_unfrozen() {
+    let mut harness = Harness::with_config(r#"{"max_history_size": 2}"#);
+    harness.copy("old");
+    harness.copy("older");
+
+    harness.send(Message::EventReceived(Event::FreezeHotkeyTriggered));
+    harness.copy("first");
+    harness.copy("second");
+    harness.copy("third");
+    assert_eq!(harness.contents(), ["older", "old"]);
+    assert_eq!(harness.app.pending_entries.len(), 2);
+
+    harness.send(Message::ToggleFreeze);
+    assert!(!harness.app.freeze_history);
+    assert_eq!(harness.contents(), ["third", "second"]);
+    assert!(harness.app.pending_entries.is_empty());
+}
+
+#[test]
+fn plist_export_has_content_timestamp_and_tags() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("history.plist");
+    let entries = [
+        ClipboardEntry {
+            content: "hello".to_string(),
+            timestamp: 1_700_000_000,
+            label: Some("greeting".to_string()),
+            ..Default::default()
+        },
+        ClipboardEntry {
+            content: "89 50 4e 47 (1 KB)".to_string(),
+            content_type: ContentType::Binary,
+            ..Default::default()
+        },
+    ];
+    export::export_plist(&entries, &path).unwrap();
+
+    let value = plist::Value::from_file(&path).unwrap();
+    let items = value.as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    let item = items[0].as_dictionary().unwrap();
+    assert_eq!(item["content"].as_string(), Some("hello"));
+    let timestamp = std::time::SystemTime::from(item["timestamp"].as_date().unwrap());
+    assert_eq!(
+        timestamp.duration_since(std::time::UNIX_EPOCH).unwrap(),
+        Duration::from_secs(1_700_000_000)
+    );
+    assert_eq!(
+        item["tags"].as_array().unwrap(),
+        &[plist::Value::from("greeting")]
+    );
+}
+
+#[test]
+fn detect_language_tags_entries_for_language_searches() {
+    let mut harness = Harness::with_config(r#"{"detect_language": true}"#);
+    harness.copy("Le chat est assis sur le tapis et regarde les oiseaux par la fenêtre.");
+    harness.copy("This is a longer sentence written in plain English, so that it is easy to tell.");
+    harness.copy("short");
+    assert_eq!(harness.app.entries[2].language.as_deref(), Some("fr"));
+    assert_eq!(harness.app.entries[1].language.as_deref(), Some("en"));
+    assert_eq!(harness.app.entries[0].language, None);
+
+    harness.send(Message::SearchChanged("language:fr chat".to_string()));
+    let visible = harness.app.visible_entries();
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].0, 2);
+}
+
+#[test]
+fn history_diff_lists_added_and_removed_entries_by_content() {
+    let entry = |content: &str, timestamp: u64| ClipboardEntry {
+        content: content.to_string(),
+        timestamp,
+        ..Default::default()
+    };
+    let old = [entry("kept", 1), entry("dropped", 2)];
+    let mut moved = entry("kept", 5);
+    moved.pinned = true;
+    let new = [entry("new", 6), moved];
+
+    let diff = storage::diff_histories(&old, &new);
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!((diff.added[0].index, diff.added[0].timestamp), (0, 6));
+    assert_eq!(diff.added[0].content, "new");
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(diff.removed[0].index, 1);
+    assert_eq!(diff.removed[0].content, "dropped");
+}
+
+#[test]
+fn resizing_the_preview_pane_saves_its_height_within_range() {
+    let mut harness = Harness::new();
+    harness.send(Message::PreviewResized(1000.0));
+    assert_eq!(harness.app.config.preview_height, 400);
+    harness.send(Message::PreviewResized(212.4));
+    assert_eq!(harness.app.config.preview_height, 212);
+
+    harness.send(Message::PreviewResizeFinished);
+    let saved = Config::load(&harness.dir.path().join("config.json"));
+    assert_eq!(saved.preview_height, 212);
+}
+
+#[test]
+fn keys_work_the_keypad_while_the_pin_is_required() {
+    let hash = bcrypt::hash("1234", 4).unwrap();
+    let mut harness = Harness::with_config(&format!(
+        r#"{{"require_pin": true, "pin_hash": "{}"}}"#,
+        hash
+    ));
+    harness.copy("older");
+    harness.copy("newest");
+
+    harness.send(Message::MoveSelection(1));
+    harness.send(Message::DeleteSelected);
+    harness.send(Message::ActivateSelection);
+    harness.send(Message::SelectEntry(harness.id(1)));
+    assert_eq!(harness.contents(), ["newest", "older"]);
+    assert_eq!(harness.clipboard.contents(), "");
+    assert_eq!(harness.app.selected, None);
+
+    for digit in "1234".chars() {
+        harness.send(Message::PinDigitEntered(digit));
+    }
+    harness.send(Message::ActivateSelection);
+    // The check itself runs off the UI thread
+    assert!(harness.app.pin_required());
+    harness.send(Message::PinChecked(auth::verify("1234", &hash)));
+    assert!(!harness.app.pin_required());
+}
+
+#[test]
+fn cycling_opens_onto_the_pin_screen_without_selecting() {
+    let hash = bcrypt::hash("1234", 4).unwrap();
+    let mut harness = Harness::with_config(&format!(
+        r#"{{"require_pin": true, "pin_hash": "{}", "hold_to_cycle": true}}"#,
+        hash
+    ));
+    harness.copy("older");
+    harness.copy("newest");
+
+    harness.send(Message::EventReceived(Event::CycleHotkeyTriggered));
+    assert!(harness.app.window_visible);
+    assert!(!harness.app.cycling);
+    assert_eq!(harness.app.selected, None);
+
+    harness.send(Message::CycleReleased);
+    assert_eq!(harness.clipboard.contents(), "");
+}
+
+#[test]
+fn masked_entries_are_not_compared() {
+    let mut harness = Harness::new();
+    harness.copy("password: hunter2");
+    harness.copy("password: hunter3");
+    harness.app.capture_masked = true;
+
+    harness.send(Message::CompareEntries(harness.id(1), harness.id(0)));
+    assert!(harness.app.comparison_view.is_none());
+    assert!(harness.app.comparison.is_empty());
+}
+
+#[test]
+fn quick_access_hotkeys_wait_for_the_pin() {
+    let hash = bcrypt::hash("1234", 4).unwrap();
+    let mut harness = Harness::with_config(&format!(
+        r#"{{"require_pin": true, "pin_hash": "{}", "quick_access_slots": 3}}"#,
+        hash
+    ));
+    harness.copy("first");
+
+    harness.send(Message::EventReceived(Event::QuickAccessTriggered(0)));
+    assert_eq!(harness.clipboard.contents(), "");
+}
@@ -0,0 +1,43 @@
+//! Per-app regex substitutions applied to text before it's pasted, e.g.
+//! turning GitHub pull request links into Jira's link markup.
+
+use crate::config::RegexTransformRule;
+use log::error;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// `regex_transform_rules` compiled and grouped by app bundle identifier,
+/// in the order they're listed in the config.
+#[derive(Debug, Default)]
+pub struct RegexTransforms {
+    rules: HashMap<String, Vec<(Regex, String)>>,
+}
+
+impl RegexTransforms {
+    /// Compiles `rules`, skipping those with an invalid pattern.
+    pub fn new(rules: &[RegexTransformRule]) -> Self {
+        let mut compiled: HashMap<String, Vec<(Regex, String)>> = HashMap::new();
+        for rule in rules {
+            match Regex::new(&rule.pattern) {
+                Ok(re) => compiled
+                    .entry(rule.app_bundle_id.clone())
+                    .or_default()
+                    .push((re, rule.replacement.clone())),
+                Err(e) => error!(
+                    "Ignoring invalid regex transform pattern {:?}: {}",
+                    rule.pattern, e
+                ),
+            }
+        }
+        RegexTransforms { rules: compiled }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The substitutions for `bundle_id`, as pattern and replacement.
+    pub fn for_app(&self, bundle_id: &str) -> &[(Regex, String)] {
+        self.rules.get(bundle_id).map_or(&[], Vec::as_slice)
+    }
+}
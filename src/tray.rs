@@ -0,0 +1,151 @@
+//! Menu bar icon whose menu lists pinned and recent entries, so they can be
+//! pasted without opening the history window. Only available on macOS; the
+//! other platforms get no-op fallbacks.
+#![cfg_attr(not(target_os = "macos"), allow(dead_code))]
+
+use crate::{widgets, ClipboardEntry, Event};
+use std::collections::VecDeque;
+use tokio::sync::mpsc;
+
+const TITLE_CHARS: usize = 40;
+const OPEN_ID: &str = "open";
+const ENTRY_ID_PREFIX: &str = "entry:";
+
+/// What a click on a tray menu item asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    OpenWindow,
+    /// Paste the entry with this content hash.
+    Paste(u64),
+}
+
+impl TrayAction {
+    fn parse(id: &str) -> Option<TrayAction> {
+        if id == OPEN_ID {
+            return Some(TrayAction::OpenWindow);
+        }
+        id.strip_prefix(ENTRY_ID_PREFIX)
+            .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+            .map(TrayAction::Paste)
+    }
+}
+
+/// The menu items to show: pinned entries first, then the most recent ones,
+/// up to `count` in total.
+fn menu_entries(entries: &VecDeque<ClipboardEntry>, count: usize) -> Vec<(u64, String)> {
+    let pinned = entries.iter().filter(|e| e.pinned);
+    let recent = entries.iter().filter(|e| !e.pinned);
+    pinned
+        .chain(recent)
+        .take(count)
+        .map(|entry| {
+            let title = widgets::display_text(entry.title.as_deref().unwrap_or(&entry.content))
+                .replace('\n', " ");
+            let title = if title.chars().count() > TITLE_CHARS {
+                format!("{}…", title.chars().take(TITLE_CHARS).collect::<String>())
+            } else {
+                title
+            };
+            let title = if entry.pinned {
+                format!("📌 {}", title)
+            } else {
+                title
+            };
+            (entry.content_hash(), title)
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+pub struct Tray {
+    icon: tray_icon::TrayIcon,
+    /// What the menu currently shows, to skip rebuilding it when nothing
+    /// visible changed. `None` while entries are hidden.
+    shown: Option<Vec<(u64, String)>>,
+}
+
+#[cfg(target_os = "macos")]
+impl Tray {
+    /// Adds the menu bar icon. Must be called on the main thread once the
+    /// event loop is running.
+    pub fn new() -> Option<Tray> {
+        let icon = tray_icon::TrayIconBuilder::new()
+            .with_title("📋")
+            .with_tooltip("Mac Clip")
+            .with_menu(Box::new(build_menu(Some(&[]))))
+            .build();
+        match icon {
+            Ok(icon) => Some(Tray {
+                icon,
+                shown: Some(Vec::new()),
+            }),
+            Err(e) => {
+                log::error!("Failed to create menu bar icon: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Rebuilds the menu if the entries it would show have changed. With
+    /// `hidden`, e.g. behind the PIN, it shows no entries at all.
+    pub fn refresh(&mut self, entries: &VecDeque<ClipboardEntry>, count: usize, hidden: bool) {
+        let items = (!hidden).then(|| menu_entries(entries, count));
+        if items != self.shown {
+            self.icon
+                .set_menu(Some(Box::new(build_menu(items.as_deref()))));
+            self.shown = items;
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn build_menu(items: Option<&[(u64, String)]>) -> tray_icon::menu::Menu {
+    use tray_icon::menu::{Menu, MenuItem, PredefinedMenuItem};
+
+    let menu = Menu::new();
+    let mut result = Ok(());
+    if let Some(items) = items {
+        for (hash, title) in items {
+            let id = format!("{}{:x}", ENTRY_ID_PREFIX, hash);
+            result = result.and(menu.append(&MenuItem::with_id(id, title, true, None)));
+        }
+        if items.is_empty() {
+            result =
+                result.and(menu.append(&MenuItem::new("No clipboard history yet", false, None)));
+        }
+        result = result.and(menu.append(&PredefinedMenuItem::separator()));
+    }
+    result = result
+        .and(menu.append(&MenuItem::with_id(OPEN_ID, "Show History", true, None)))
+        .and(menu.append(&PredefinedMenuItem::quit(Some("Quit Mac Clip"))));
+    if let Err(e) = result {
+        log::error!("Failed to build menu bar menu: {}", e);
+    }
+    menu
+}
+
+/// Forwards tray menu clicks to the app's event channel. Runs forever, so
+/// call it from a dedicated thread.
+#[cfg(target_os = "macos")]
+pub fn listen(tx: mpsc::UnboundedSender<Event>) {
+    for event in tray_icon::menu::MenuEvent::receiver() {
+        if let Some(action) = TrayAction::parse(&event.id.0) {
+            let _ = tx.send(Event::TrayAction(action));
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub struct Tray;
+
+#[cfg(not(target_os = "macos"))]
+impl Tray {
+    pub fn new() -> Option<Tray> {
+        None
+    }
+
+    pub fn refresh(&mut self, _entries: &VecDeque<ClipboardEntry>, _count: usize, _hidden: bool) {}
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn listen(_tx: mpsc::UnboundedSender<Event>) {}
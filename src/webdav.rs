@@ -0,0 +1,213 @@
+//! Keeping the history in sync through a WebDAV server the user provides,
+//! as `<webdav_url>/mac-clip/history.json`.
+
+use crate::{
+    config::{Config, Credentials},
+    storage, ClipboardEntry, EntryId,
+};
+use log::{error, info};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, fs, io,
+    path::Path,
+    time::Duration,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const FOLDER: &str = "mac-clip";
+
+/// How long a deletion is remembered. A machine that hasn't synced for
+/// longer may bring the entry back.
+const TOMBSTONE_SECS: u64 = 90 * 24 * 60 * 60;
+
+/// The ids of deleted entries and when they were deleted, kept so a sync
+/// doesn't bring them back from a machine that still has them.
+pub type Tombstones = HashMap<EntryId, u64>;
+
+/// What's kept on the server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "RemoteFormat")]
+pub struct RemoteHistory {
+    pub entries: VecDeque<ClipboardEntry>,
+    pub deleted: Tombstones,
+}
+
+/// Versions before deletions were synced uploaded just the entries.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RemoteFormat {
+    Entries(VecDeque<ClipboardEntry>),
+    WithDeletions {
+        entries: VecDeque<ClipboardEntry>,
+        #[serde(default)]
+        deleted: Tombstones,
+    },
+}
+
+impl From<RemoteFormat> for RemoteHistory {
+    fn from(format: RemoteFormat) -> Self {
+        match format {
+            RemoteFormat::Entries(entries) => RemoteHistory {
+                entries,
+                deleted: Tombstones::new(),
+            },
+            RemoteFormat::WithDeletions { entries, deleted } => RemoteHistory { entries, deleted },
+        }
+    }
+}
+
+/// Adds the deletions in `theirs` to `ours`, keeping the later time for an
+/// entry in both.
+pub fn merge_tombstones(ours: &mut Tombstones, theirs: Tombstones) {
+    for (id, at) in theirs {
+        let known = ours.entry(id).or_insert(at);
+        *known = (*known).max(at);
+    }
+}
+
+/// Records the entries of `before` that are no longer in `after` as deleted
+/// at `now`. Returns whether there were any.
+pub fn record_deletions(
+    tombstones: &mut Tombstones,
+    before: &HashSet<EntryId>,
+    after: &VecDeque<ClipboardEntry>,
+    now: u64,
+) -> bool {
+    let kept: HashSet<EntryId> = after.iter().map(|e| e.id).collect();
+    let mut recorded = false;
+    for &id in before.difference(&kept) {
+        tombstones.entry(id).or_insert(now);
+        recorded = true;
+    }
+    recorded
+}
+
+/// Loads the deletions not yet old enough to forget.
+pub fn load_tombstones(path: &Path) -> Tombstones {
+    let mut tombstones: Tombstones = match fs::read(path) {
+        Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+            error!("Failed to parse {}: {}", path.display(), e);
+            Tombstones::new()
+        }),
+        Err(_) => Tombstones::new(),
+    };
+    let cutoff = storage::unix_now().saturating_sub(TOMBSTONE_SECS);
+    tombstones.retain(|_, &mut at| at >= cutoff);
+    tombstones
+}
+
+pub fn save_tombstones(path: &Path, tombstones: &Tombstones) -> io::Result<()> {
+    fs::write(path, serde_json::to_vec(tombstones)?)
+}
+
+#[derive(Debug)]
+pub enum SyncError {
+    Http(reqwest::Error),
+    /// The server answered with an error status.
+    Status(StatusCode),
+    /// The remote history isn't a history.
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::Http(e) => write!(f, "{}", e),
+            SyncError::Status(status) => write!(f, "the server answered {}", status),
+            SyncError::Json(e) => write!(f, "the remote history is invalid: {}", e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for SyncError {
+    fn from(e: reqwest::Error) -> Self {
+        SyncError::Http(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WebDavSync {
+    folder_url: String,
+    credentials: Option<Credentials>,
+}
+
+impl WebDavSync {
+    /// `None` unless `webdav_url` is set.
+    pub fn from_config(config: &Config) -> Option<WebDavSync> {
+        let url = config.webdav_url.as_deref()?;
+        Some(WebDavSync {
+            folder_url: format!("{}/{}", url.trim_end_matches('/'), FOLDER),
+            credentials: config.webdav_credentials.clone(),
+        })
+    }
+
+    fn history_url(&self) -> String {
+        format!("{}/history.json", self.folder_url)
+    }
+
+    /// Fetches the remote history; `None` if nothing has been uploaded yet.
+    pub async fn download(&self) -> Result<Option<RemoteHistory>, SyncError> {
+        let response = self
+            .request(Method::GET, &self.history_url())?
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let body = response.bytes().await?;
+                let entries = serde_json::from_slice(&body).map_err(SyncError::Json)?;
+                Ok(Some(entries))
+            }
+            status => Err(SyncError::Status(status)),
+        }
+    }
+
+    /// Replaces the remote history with `remote`, creating the folder for
+    /// it on first use.
+    pub async fn upload(&self, remote: &RemoteHistory) -> Result<(), SyncError> {
+        let body = serde_json::to_vec(remote).map_err(SyncError::Json)?;
+
+        let propfind = Method::from_bytes(b"PROPFIND").expect("valid method");
+        let folder = self
+            .request(propfind, &self.folder_url)?
+            .header("Depth", "0")
+            .send()
+            .await?;
+        if folder.status() == StatusCode::NOT_FOUND {
+            info!("Creating {} on the WebDAV server", self.folder_url);
+            let mkcol = Method::from_bytes(b"MKCOL").expect("valid method");
+            let created = self.request(mkcol, &self.folder_url)?.send().await?;
+            if !created.status().is_success() {
+                return Err(SyncError::Status(created.status()));
+            }
+        }
+
+        let response = self
+            .request(Method::PUT, &self.history_url())?
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(SyncError::Status(response.status()));
+        }
+        info!("Uploaded {} entries to WebDAV", remote.entries.len());
+        Ok(())
+    }
+
+    fn request(&self, method: Method, url: &str) -> Result<RequestBuilder, SyncError> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("mac-clip/", env!("CARGO_PKG_VERSION")))
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        let request = client.request(method, url);
+        Ok(match &self.credentials {
+            Some(credentials) => {
+                request.basic_auth(&credentials.username, Some(&credentials.password))
+            }
+            None => request,
+        })
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{config::NewlineDisplay, Message};
+use iced::{
+    mouse, theme,
+    widget::{
+        button,
+        canvas::{self, event, Frame, Geometry},
+    },
+    Element, Point, Rectangle, Renderer, Size, Theme,
+};
+
+const MAX_PREVIEW_CHARS: usize = 50;
+
+/// Height of the divider above the preview pane, larger than the line it
+/// draws so it's easy to grab.
+pub const DIVIDER_HEIGHT: f32 = 8.0;
+
+/// The divider between the history and the preview pane under it. Dragging
+/// it up or down resizes the pane, reported as `PreviewResized` while
+/// dragging and `PreviewResizeFinished` once let go.
+pub struct PreviewDivider {
+    /// The preview pane's current height.
+    pub height: f32,
+}
+
+impl canvas::Program<Message> for PreviewDivider {
+    /// Where a drag started and the pane's height at the time.
+    type State = Option<(f32, f32)>;
+
+    fn update(
+        &self,
+        drag: &mut Option<(f32, f32)>,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let canvas::Event::Mouse(event) = event else {
+            return (event::Status::Ignored, None);
+        };
+        match (event, *drag) {
+            (mouse::Event::ButtonPressed(mouse::Button::Left), None) => {
+                match cursor.position_over(bounds) {
+                    Some(position) => {
+                        *drag = Some((position.y, self.height));
+                        (event::Status::Captured, None)
+                    }
+                    None => (event::Status::Ignored, None),
+                }
+            }
+            // The pane is below, so dragging up makes it taller
+            (mouse::Event::CursorMoved { position }, Some((start, height))) => (
+                event::Status::Captured,
+                Some(Message::PreviewResized(height + start - position.y)),
+            ),
+            (mouse::Event::ButtonReleased(mouse::Button::Left), Some(_)) => {
+                *drag = None;
+                (
+                    event::Status::Captured,
+                    Some(Message::PreviewResizeFinished),
+                )
+            }
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _drag: &Option<(f32, f32)>,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        frame.fill_rectangle(
+            Point::new(0.0, (bounds.height - 1.0) / 2.0),
+            Size::new(bounds.width, 1.0),
+            theme.extended_palette().background.strong.color,
+        );
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        drag: &Option<(f32, f32)>,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        if drag.is_some() || cursor.is_over(bounds) {
+            mouse::Interaction::ResizingVertically
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+/// A narrow bar beside a scrollable, filled in the accent colour as far
+/// down as it's scrolled.
+pub struct ScrollIndicator {
+    /// From 0 at the top to 1 at the bottom.
+    pub progress: f32,
+}
+
+impl canvas::Program<Message> for ScrollIndicator {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let palette = theme.extended_palette();
+        let mut frame = Frame::new(renderer, bounds.size());
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            bounds.size(),
+            palette.background.strong.color,
+        );
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            Size::new(bounds.width, bounds.height * self.progress),
+            palette.primary.base.color,
+        );
+        vec![frame.into_geometry()]
+    }
+}
+
+/// Builds a small text-styled action button.
+pub fn action_button<'a>(
+    content: impl Into<Element<'a, Message>>,
+    on_press: Message,
+) -> Element<'a, Message> {
+    button(content)
+        .padding(8)
+        .style(theme::Button::Text)
+        .on_press(on_press)
+        .into()
+}
+
+/// Strips control characters other than tabs and newlines, which some apps
+/// leave in copied text and which render as garbage. The stored entry keeps
+/// the original so pasting stays byte-for-byte.
+pub fn display_text(content: &str) -> String {
+    content
+        .chars()
+        .filter(|&c| !c.is_control() || c == '\t' || c == '\n')
+        .collect()
+}
+
+/// The text shown for an entry in the history list: lines cut to 50
+/// characters, and line breaks shown as `newlines` says.
+pub fn entry_preview(content: &str, newlines: NewlineDisplay, max_lines: usize) -> String {
+    let content = display_text(content);
+    let truncate = |line: &str| {
+        if line.chars().count() > MAX_PREVIEW_CHARS {
+            format!(
+                "{}...",
+                line.chars().take(MAX_PREVIEW_CHARS).collect::<String>()
+            )
+        } else {
+            line.to_string()
+        }
+    };
+    match newlines {
+        NewlineDisplay::Symbol => truncate(&content.replace('\n', "↵")),
+        NewlineDisplay::Space => truncate(&content.replace('\n', " ")),
+        NewlineDisplay::EscapeSequence => truncate(&content.replace('\n', "\\n")),
+        NewlineDisplay::Actual => {
+            let lines: Vec<&str> = content.lines().collect();
+            let shown = max_lines.max(1).min(lines.len());
+            let mut preview: Vec<String> =
+                lines[..shown].iter().map(|line| truncate(line)).collect();
+            match lines.len() - shown {
+                0 => {}
+                1 => preview.push("... 1 more line".to_string()),
+                hidden => preview.push(format!("... {} more lines", hidden)),
+            }
+            preview.join("\n")
+        }
+    }
+}
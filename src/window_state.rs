@@ -0,0 +1,124 @@
+use crate::{config::WindowAnchor, macos::ScreenFrame};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+/// How far below the mouse pointer `cursor_follow` keeps the window.
+const FOLLOW_OFFSET: f64 = 20.0;
+/// How much of the way to the pointer the window moves each step.
+const FOLLOW_SMOOTHING: f64 = 0.2;
+
+/// Where the history window was last dragged to, in logical points from the
+/// top-left corner of the main screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowPosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+pub fn load(path: &Path) -> Option<WindowPosition> {
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+pub fn save(path: &Path, position: WindowPosition) -> io::Result<()> {
+    fs::write(path, serde_json::to_string(&position)?)
+}
+
+/// Moves a window of `size` at `position` so it lies within the screen it
+/// overlaps most, or the main screen if it's on none of them (e.g. the
+/// monitor it was on has been unplugged). Without any screen information the
+/// position is returned unchanged.
+pub fn clamp(
+    position: WindowPosition,
+    size: (u32, u32),
+    screens: &[ScreenFrame],
+) -> WindowPosition {
+    let (width, height) = (f64::from(size.0), f64::from(size.1));
+    let (x, y) = (f64::from(position.x), f64::from(position.y));
+
+    let overlap = |screen: &ScreenFrame| {
+        let w = (x + width).min(screen.x + screen.width) - x.max(screen.x);
+        let h = (y + height).min(screen.y + screen.height) - y.max(screen.y);
+        w.max(0.0) * h.max(0.0)
+    };
+    let screen = screens
+        .iter()
+        .filter(|screen| overlap(screen) > 0.0)
+        .max_by(|a, b| overlap(a).total_cmp(&overlap(b)))
+        .or_else(|| screens.first());
+
+    match screen {
+        Some(screen) => WindowPosition {
+            x: x.min(screen.x + screen.width - width).max(screen.x) as i32,
+            y: y.min(screen.y + screen.height - height).max(screen.y) as i32,
+        },
+        None => position,
+    }
+}
+
+/// The next position of a window of `size` at `current` following the mouse
+/// pointer for `cursor_follow`: a step towards just below the pointer,
+/// smoothed so it glides rather than jumps. Returns `None` while the pointer
+/// is over the window, so it holds still for its entries to be clicked.
+pub fn follow_cursor(
+    current: Option<(f64, f64)>,
+    cursor: (f64, f64),
+    size: (u32, u32),
+) -> Option<(f64, f64)> {
+    let target = (cursor.0, cursor.1 + FOLLOW_OFFSET);
+    let Some((x, y)) = current else {
+        return Some(target);
+    };
+    let over_window = (x..x + f64::from(size.0)).contains(&cursor.0)
+        && (y..y + f64::from(size.1)).contains(&cursor.1);
+    if over_window {
+        return None;
+    }
+    Some((
+        x * (1.0 - FOLLOW_SMOOTHING) + target.0 * FOLLOW_SMOOTHING,
+        y * (1.0 - FOLLOW_SMOOTHING) + target.1 * FOLLOW_SMOOTHING,
+    ))
+}
+
+/// Where `anchor` puts a window of `size` on the screen the mouse pointer is
+/// on, falling back to the main screen, `margin` points from its edges (and
+/// below the screen's notch). Returns `None` for `Center`, which is left to
+/// the window system, and when there's no screen information.
+pub fn anchored(
+    anchor: WindowAnchor,
+    size: (u32, u32),
+    margin: u32,
+    cursor: Option<(f64, f64)>,
+    screens: &[ScreenFrame],
+) -> Option<WindowPosition> {
+    let (width, height) = (f64::from(size.0), f64::from(size.1));
+    let screen = cursor
+        .and_then(|(x, y)| {
+            screens
+                .iter()
+                .find(|s| (s.x..s.x + s.width).contains(&x) && (s.y..s.y + s.height).contains(&y))
+        })
+        .or_else(|| screens.first())?;
+
+    let margin = f64::from(margin);
+    let left = screen.x + margin;
+    let right = screen.x + screen.width - width - margin;
+    let top = screen.y + screen.notch + margin;
+    let bottom = screen.y + screen.height - height - margin;
+    let (x, y) = match anchor {
+        WindowAnchor::Center => return None,
+        WindowAnchor::Cursor => cursor?,
+        WindowAnchor::TopLeft => (left, top),
+        WindowAnchor::TopRight => (right, top),
+        WindowAnchor::BottomLeft => (left, bottom),
+        WindowAnchor::BottomRight => (right, bottom),
+    };
+    Some(clamp(
+        WindowPosition {
+            x: x as i32,
+            y: y as i32,
+        },
+        size,
+        screens,
+    ))
+}
@@ -0,0 +1,256 @@
+//! Runs the `mac-clip` binary's command-line flags in a subprocess, with a
+//! temporary home directory and a file for the clipboard.
+#![cfg(feature = "integration-tests")]
+
+use assert_cmd::Command;
+use serde_json::json;
+use std::{fs, path::PathBuf};
+use tempfile::TempDir;
+
+/// A home directory of its own, so runs don't see the real history or each
+/// other's.
+struct Home {
+    dir: TempDir,
+}
+
+impl Home {
+    fn new() -> Self {
+        let home = Home {
+            dir: TempDir::new().unwrap(),
+        };
+        fs::create_dir_all(home.data_dir()).unwrap();
+        home
+    }
+
+    /// Where `directories` puts the data directory under this home.
+    fn data_dir(&self) -> PathBuf {
+        if cfg!(target_os = "macos") {
+            self.dir
+                .path()
+                .join("Library/Application Support/com.mac-clip.mac-clip")
+        } else {
+            self.dir.path().join(".local/share/mac-clip")
+        }
+    }
+
+    fn clipboard(&self) -> PathBuf {
+        self.dir.path().join("clipboard.txt")
+    }
+
+    fn write_history(&self, contents: &[&str]) {
+        let entries: Vec<_> = contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| json!({"content": content, "timestamp": 1_700_000_000 - i as u64}))
+            .collect();
+        fs::write(
+            self.data_dir().join("history.json"),
+            serde_json::to_string(&entries).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn write_config(&self, config: &str) {
+        fs::write(self.data_dir().join("config.json"), config).unwrap();
+    }
+
+    fn mac_clip(&self) -> Command {
+        let mut command = Command::cargo_bin("mac-clip").unwrap();
+        command
+            .env("HOME", self.dir.path())
+            .env_remove("XDG_DATA_HOME")
+            .env_remove("XDG_RUNTIME_DIR")
+            .env_remove("DISPLAY")
+            .env_remove("WAYLAND_DISPLAY")
+            .env("MAC_CLIP_TEST_CLIPBOARD", self.clipboard());
+        command
+    }
+}
+
+fn stdout(command: &mut Command) -> String {
+    let output = command.assert().success().get_output().stdout.clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn search_lists_matching_entries_with_their_index() {
+    let home = Home::new();
+    home.write_history(&["hello world", "line one\nline two", "goodbye"]);
+
+    assert_eq!(
+        stdout(home.mac_clip().args(["--search", ""])),
+        "0: hello world\n1: line one↵line two\n2: goodbye\n"
+    );
+    assert_eq!(
+        stdout(home.mac_clip().args(["--search", "hello OR goodbye"])),
+        "0: hello world\n2: goodbye\n"
+    );
+}
+
+#[test]
+fn search_reads_the_history_of_the_given_profile() {
+    let home = Home::new();
+    home.write_history(&["default entry"]);
+    let work = home.data_dir().join("profiles/work");
+    fs::create_dir_all(&work).unwrap();
+    fs::write(
+        work.join("history.json"),
+        json!([{"content": "work entry", "timestamp": 1_700_000_000}]).to_string(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        stdout(home.mac_clip().args(["--search", "", "--profile", "work"])),
+        "0: work entry\n"
+    );
+}
+
+#[test]
+fn status_counts_the_history_of_the_given_profile() {
+    let home = Home::new();
+    home.write_history(&["one", "two", "three"]);
+    let work = home.data_dir().join("profiles/work");
+    fs::create_dir_all(&work).unwrap();
+    fs::write(
+        work.join("history.json"),
+        json!([{"content": "work entry", "timestamp": 1_700_000_000}]).to_string(),
+    )
+    .unwrap();
+
+    let output = home
+        .mac_clip()
+        .args(["--status", "--profile", "work"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("History: 1 entries"), "{}", stdout);
+}
+
+#[test]
+fn search_fails_on_an_invalid_query() {
+    let home = Home::new();
+    home.write_history(&["hello"]);
+
+    home.mac_clip()
+        .args(["--search", "OR hello"])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn validate_config_exits_with_the_worst_issue() {
+    let home = Home::new();
+    home.mac_clip().arg("--validate-config").assert().code(0);
+
+    home.write_config(r#"{"max_history_size": 50}"#);
+    home.mac_clip().arg("--validate-config").assert().code(0);
+
+    home.write_config(r#"{"max_history_size": "fifty"}"#);
+    let output = home.mac_clip().arg("--validate-config").assert().code(1);
+    let printed = String::from_utf8_lossy(&output.get_output().stdout);
+    assert!(printed.contains("max_history_size"), "{}", printed);
+}
+
+#[test]
+fn an_exported_bundle_imports_into_another_home() {
+    let from = Home::new();
+    let to = Home::new();
+    from.write_history(&["moved"]);
+    let bundle = from.dir.path().join("setup.bundle");
+
+    stdout(from.mac_clip().arg("--export-bundle").arg(&bundle));
+    assert!(fs::metadata(&bundle).unwrap().len() > 0);
+    stdout(to.mac_clip().arg("--import-bundle").arg(&bundle));
+
+    assert_eq!(stdout(to.mac_clip().args(["--search", ""])), "0: moved\n");
+}
+
+#[test]
+fn auto_paste_last_puts_the_entry_on_the_clipboard() {
+    let home = Home::new();
+    home.write_history(&["newest", "older"]);
+
+    stdout(home.mac_clip().args(["--auto-paste-last", "-n", "2"]));
+    assert_eq!(fs::read_to_string(home.clipboard()).unwrap(), "older");
+
+    home.mac_clip()
+        .args(["--auto-paste-last", "-n", "3"])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn copy_adds_text_from_stdin_to_the_history_and_clipboard() {
+    let home = Home::new();
+    home.write_history(&["older"]);
+
+    stdout(
+        home.mac_clip()
+            .args(["--copy", "-"])
+            .write_stdin("abc123 Fix the build\n"),
+    );
+    assert_eq!(
+        fs::read_to_string(home.clipboard()).unwrap(),
+        "abc123 Fix the build"
+    );
+    assert_eq!(
+        stdout(home.mac_clip().args(["--search", ""])),
+        "0: abc123 Fix the build\n1: older\n"
+    );
+}
+
+#[test]
+fn copy_applies_the_capture_filters() {
+    let home = Home::new();
+    home.write_config(r#"{"exclusion_patterns": ["^secret"]}"#);
+    home.write_history(&["older"]);
+
+    stdout(home.mac_clip().args(["--copy", "secret token"]));
+    assert_eq!(
+        fs::read_to_string(home.clipboard()).unwrap(),
+        "secret token"
+    );
+    assert_eq!(stdout(home.mac_clip().args(["--search", ""])), "0: older\n");
+
+    // A one-time code matches the default sensitive_patterns
+    stdout(home.mac_clip().args(["--copy", "123456"]));
+    let history = fs::read_to_string(home.data_dir().join("history.json")).unwrap();
+    assert!(history.contains("expires_at"), "{}", history);
+}
+
+#[test]
+fn format_entry_leaves_placeholders_in_the_content_alone() {
+    let home = Home::new();
+    home.write_history(&["{index} and {app}"]);
+
+    assert_eq!(
+        stdout(
+            home.mac_clip()
+                .args(["--format-entry", "0", "[{content}] {index}", "--no-escape"])
+        ),
+        "[{index} and {app}] 0\n"
+    );
+}
+
+#[test]
+fn format_entry_fails_on_a_missing_index() {
+    let home = Home::new();
+    home.write_history(&["only"]);
+
+    home.mac_clip()
+        .args(["--format-entry", "3", "{content}"])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn export_bundle_fails_on_an_unwritable_path() {
+    let home = Home::new();
+    home.write_history(&["kept"]);
+
+    home.mac_clip()
+        .arg("--export-bundle")
+        .arg(home.dir.path().join("missing/setup.bundle"))
+        .assert()
+        .code(1);
+}